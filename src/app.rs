@@ -1,8 +1,35 @@
 use std::cmp::min;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crossbeam_channel::{Receiver, Sender};
 
-use crate::db::{DBRequest, DBResponse, SortDir};
+use crate::db::{
+    Affinity, CASE_SENSITIVE_PREFIX, CellKind, ColumnStats, DBRequest, DBResponse, EditType,
+    ExportFormat, IndexInfo, InsertValue, NUMERIC_SORT_PREFIX, PageKey, SortDir, SqlExpr,
+    format_cells, ident, parse_blob_placeholder, parse_clipboard_row,
+};
+use crate::logging;
+use crate::views::{self, SavedView};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Prefetched pages kept on the app side
+const PAGE_CACHE_SIZE: usize = 4;
+
+/// Leading bytes of a BLOB shown in the cell viewer
+const BLOB_PREVIEW_BYTES: usize = 64;
+
+/// Database errors kept for the `!` log
+const ERROR_LOG_SIZE: usize = 50;
+
+/// A `-wal` file bigger than this gets a checkpoint warning in the status bar
+pub const WAL_WARN_BYTES: u64 = 64 * 1024 * 1024;
+
+const SIDEBAR_MIN_WIDTH: u16 = 12;
+const SIDEBAR_MAX_WIDTH: u16 = 80;
 
 #[derive(Debug, Clone, Copy)]
 pub enum AppMode {
@@ -12,29 +39,398 @@ pub enum AppMode {
         col: usize,
         cursor: usize, // cursor in edit buffer
     },
+    /// Create-table wizard (form state lives in `App::create_form`)
+    CreateTable,
+}
+
+/// One column row in the create-table wizard
+#[derive(Debug, Clone)]
+pub struct ColumnDraft {
+    pub name: String,
+    pub col_type: String,
+    pub nullable: bool,
+    pub pk: bool,
+}
+
+impl Default for ColumnDraft {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            col_type: "TEXT".into(),
+            nullable: true,
+            pk: false,
+        }
+    }
+}
+
+/// In-progress create-table form
+#[derive(Debug, Clone, Default)]
+pub struct CreateTableForm {
+    pub name: String,
+    pub columns: Vec<ColumnDraft>,
+    /// 0 = table name line, 1.. = column rows
+    pub row: usize,
+    /// Field within a column row: 0 name, 1 type, 2 nullable, 3 primary key
+    pub field: usize,
+}
+
+impl CreateTableForm {
+    pub const FIELDS: usize = 4;
+    /// Types offered by Left/Right on the type field
+    pub const TYPES: [&'static str; 5] = ["INTEGER", "TEXT", "REAL", "BLOB", "NUMERIC"];
+
+    /// Build the CREATE TABLE statement, or explain what is missing
+    pub fn ddl(&self) -> Result<String, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Table name is required".into());
+        }
+        let cols: Vec<&ColumnDraft> = self
+            .columns
+            .iter()
+            .filter(|c| !c.name.trim().is_empty())
+            .collect();
+        if cols.is_empty() {
+            return Err("Add at least one named column".into());
+        }
+        let pk_count = cols.iter().filter(|c| c.pk).count();
+        let mut defs = Vec::with_capacity(cols.len() + 1);
+        for c in &cols {
+            let ty = c.col_type.trim();
+            if !ty
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || " _(),".contains(ch))
+            {
+                return Err(format!("Invalid type for column {}", c.name.trim()));
+            }
+            let mut def = ident(c.name.trim());
+            if !ty.is_empty() {
+                def.push(' ');
+                def.push_str(ty);
+            }
+            if c.pk && pk_count == 1 {
+                def.push_str(" PRIMARY KEY");
+            }
+            if !c.nullable {
+                def.push_str(" NOT NULL");
+            }
+            defs.push(def);
+        }
+        if pk_count > 1 {
+            let keys = cols
+                .iter()
+                .filter(|c| c.pk)
+                .map(|c| ident(c.name.trim()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            defs.push(format!("PRIMARY KEY ({})", keys));
+        }
+        Ok(format!(
+            "CREATE TABLE {} (\n  {}\n)",
+            ident(name),
+            defs.join(",\n  ")
+        ))
+    }
+
+    /// Text buffer of the focused field, if it is a text field
+    fn focused_text(&mut self) -> Option<&mut String> {
+        if self.row == 0 {
+            return Some(&mut self.name);
+        }
+        let col = self.columns.get_mut(self.row - 1)?;
+        match self.field {
+            0 => Some(&mut col.name),
+            1 => Some(&mut col.col_type),
+            _ => None,
+        }
+    }
+}
+
+/// What a single-line prompt (shown in the status bar) is collecting input for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptKind {
+    /// "path [as alias]" of a database to ATTACH
+    Attach,
+    /// Typed confirmation: the table name must be entered to drop it
+    ConfirmDrop {
+        table: String,
+    },
+    /// Typed confirmation: the table name (plus " reset" to restart AUTOINCREMENT) must
+    /// be entered to delete every row
+    ConfirmTruncate {
+        table: String,
+    },
+    /// "y" deletes the selected row (undo puts it back)
+    ConfirmDeleteRow {
+        table: String,
+        rowid: i64,
+    },
+    RenameTable {
+        table: String,
+    },
+    RenameColumn {
+        table: String,
+        column: String,
+    },
+    /// An explicit transaction is open: "c" commits, "r" rolls back
+    EndTransaction,
+    /// Typed confirmation: the number of matching rows must be entered to delete them
+    ConfirmDeleteMatching {
+        table: String,
+        filter: String,
+        count: usize,
+    },
+    /// `q` with a transaction open: commit or roll back, then quit
+    ConfirmQuit,
+    /// Destination file for the selected BLOB cell
+    SaveBlob {
+        table: String,
+        rowid: i64,
+        column: String,
+    },
+    /// Destination file for a database backup
+    Backup,
+    /// The backup destination exists; "y" replaces it
+    ConfirmOverwriteBackup {
+        path: String,
+    },
+    /// 1-based page number to jump to
+    GotoPage,
+    /// Column name to select (fuzzy)
+    GotoColumn,
+    /// Name to save the current table's filter and sort under
+    SaveView,
+    /// "a" runs ANALYZE, "r" rebuilds the indexes of `table` (if one is selected)
+    Maintenance {
+        table: Option<String>,
+    },
+}
+
+impl PromptKind {
+    pub fn label(&self) -> String {
+        match self {
+            PromptKind::Attach => "Attach database (path [as alias])".into(),
+            PromptKind::ConfirmDrop { table } => {
+                format!("DROP TABLE {} — type the table name to confirm", table)
+            }
+            PromptKind::ConfirmTruncate { table } => format!(
+                "Delete ALL rows of {t} — type the table name to confirm ('{t} reset' also restarts AUTOINCREMENT)",
+                t = table
+            ),
+            PromptKind::ConfirmDeleteRow { table, rowid } => format!(
+                "Delete row {} of {} — type y to confirm (u undoes)",
+                rowid, table
+            ),
+            PromptKind::RenameTable { table } => format!("Rename table {} to", table),
+            PromptKind::RenameColumn { table, column } => {
+                format!("Rename column {}.{} to", table, column)
+            }
+            PromptKind::ConfirmDeleteMatching {
+                table,
+                filter,
+                count,
+            } => format!(
+                "DELETE {} rows from {} matching '{}' — type {} to confirm",
+                count, table, filter, count
+            ),
+            PromptKind::EndTransaction => "Transaction open — c commit, r roll back".into(),
+            PromptKind::ConfirmQuit => {
+                "Transaction open — c commit and quit, r roll back and quit (Esc stays)".into()
+            }
+            PromptKind::Backup => "Backup database to (path)".into(),
+            PromptKind::SaveBlob { column, .. } => format!("Save {} BLOB to (path)", column),
+            PromptKind::ConfirmOverwriteBackup { path } => {
+                format!("{} exists — type y to overwrite", path)
+            }
+            PromptKind::GotoPage => "Go to page".into(),
+            PromptKind::GotoColumn => "Go to column (name, fuzzy)".into(),
+            PromptKind::SaveView => "Save view as (name)".into(),
+            PromptKind::Maintenance { table: Some(t) } => {
+                format!("Maintenance — a ANALYZE (statistics), r REINDEX {}", t)
+            }
+            PromptKind::Maintenance { table: None } => {
+                "Maintenance — a ANALYZE (statistics)".into()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+}
+
+/// Popup drawn over the main panes; it takes all keys while open
+#[derive(Debug, Clone)]
+pub enum Overlay {
+    /// Pick a value to filter on (Up/Down, Enter applies, Esc closes)
+    DistinctValues {
+        table: String,
+        column: String,
+        values: Vec<(Option<String>, i64)>,
+        /// Distinct values in the column; more than `values` when `limit` cut them off
+        total: usize,
+        limit: usize,
+        selected: usize,
+    },
+    /// Saved filter/sort views of this database (Enter applies, s saves, d deletes)
+    SavedViews {
+        views: Vec<SavedView>,
+        selected: usize,
+    },
+    /// Scrollable text (Up/Down scroll, any other key closes)
+    Info {
+        title: String,
+        lines: Vec<String>,
+        scroll: usize,
+    },
+    /// Any key closes it
+    ColumnStats {
+        table: String,
+        column: String,
+        /// Stats were computed over the filtered rows only
+        filtered: bool,
+        stats: ColumnStats,
+    },
+}
+
+/// Grouped-count view of the loaded table (G)
+#[derive(Debug, Clone)]
+pub struct GroupView {
+    pub column: String,
+    /// Row view state put back when leaving: sort, selected column, row offset
+    saved_sort: (Option<String>, Option<SortDir>),
+    saved_col: usize,
+    saved_offset: usize,
+}
+
+/// Tab completion in the `:` bar: the names the word being typed can become
+#[derive(Debug, Clone)]
+struct QueryCompletion {
+    /// Byte offset in the query where the completed word starts
+    start: usize,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+/// Rowid cell of the blank row `o` adds while it is being filled in
+const NEW_ROW_ROWID: &str = "*";
+
+/// The blank row `o` adds below the loaded rows, filled in column by column
+#[derive(Debug, Clone)]
+pub struct NewRow {
+    pub table: String,
+    /// One per entry of `columns` (the `__rowid__` slot is unused)
+    pub values: Vec<InsertValue>,
+    /// The open field is an explicit NULL; `edit_is_null` alone means DEFAULT
+    null_field: bool,
+    /// The row is open in the editor
+    editing: bool,
+    /// An InsertRow is in flight; if it fails `o` reopens the row with these values
+    sent: bool,
+}
+
+/// Collapsible groups in the schema sidebar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SidebarSection {
+    Tables,
+    Views,
+    Indexes,
+}
+
+impl SidebarSection {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SidebarSection::Tables => "Tables",
+            SidebarSection::Views => "Views",
+            SidebarSection::Indexes => "Indexes",
+        }
+    }
+}
+
+/// One line of the schema sidebar; indices point into `tables`/`views`/`indexes`/`schemas`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidebarEntry {
+    Section(SidebarSection),
+    Schema(usize),
+    Table(usize),
+    View(usize),
+    Index(usize),
+}
+
+/// How 0/1 values in boolean columns are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoolStyle {
+    /// false / true
+    Words,
+    /// ☐ / ☑
+    Boxes,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Focus {
     Tables,
     Data,
+    /// The cell viewer pane (only while it is open)
+    Viewer,
 }
 
 pub struct App {
     pub should_quit: bool,
+    /// Quit once the pending COMMIT/ROLLBACK from the quit prompt succeeds
+    pub quit_after_transaction: bool,
 
     // UI state
     pub mode: AppMode,
     pub status: String,
+    /// Opened with --read-only: refuse anything that writes
+    pub read_only: bool,
+    /// Opened with --immutable (implies read_only); shown as [IMMUTABLE] instead of [RO]
+    pub immutable: bool,
+    /// An explicit BEGIN is open (t); shown as [TXN]
+    pub in_transaction: bool,
 
     // Schema
     pub tables: Vec<String>,
+    pub views: Vec<String>,
+    pub indexes: Vec<IndexInfo>,
+    /// Schema names ("main" plus attached aliases)
+    pub schemas: Vec<String>,
+    /// FTS5 tables; their filter is a full-text MATCH query
+    pub fts_tables: Vec<String>,
+    /// When the LoadSchema in flight was sent; the sidebar shows a spinner until it answers
+    pub schema_loading: Option<std::time::Instant>,
+    /// When the LoadTable in flight was sent; a slow one dims the rows it will replace
+    pub table_loading: Option<std::time::Instant>,
+    /// Set while the data pane shows counts per value instead of rows
+    pub group_view: Option<GroupView>,
+    /// Query whose results the data pane shows instead of the table (`:`; Esc goes back)
+    pub query_view: Option<String>,
+    /// Query being typed in the `:` bar
+    pub query_input: Option<String>,
+    /// Tab cycles these candidates until the query is typed in again
+    query_completion: Option<QueryCompletion>,
+    /// Column names of the tables queries mention, fetched on Tab; reset with the schema
+    query_columns: HashMap<String, Vec<String>>,
+    /// Index into `sidebar_entries()`
     pub selected_table: usize,
+    pub collapsed_sections: HashSet<SidebarSection>,
+    /// Sidebar filter (`/` with the sidebar focused); empty shows everything
+    pub sidebar_query: String,
+    /// The sidebar filter is being typed
+    pub sidebar_query_input: bool,
+    /// Row counts per table shown in the sidebar (filled in lazily)
+    pub table_counts: HashMap<String, i64>,
+    /// Tables whose count is an estimate (sqlite_stat1)
+    pub estimated_counts: HashSet<String>,
 
     // Focus (which pane is active)
     pub focus: Focus,
 
     // Table data
+    /// Table whose rows are currently shown (may differ from the sidebar selection)
+    pub loaded_table: Option<String>,
     pub columns: Vec<String>,
     pub rows: Vec<Vec<String>>,
     pub page_size: usize,
@@ -46,27 +442,52 @@ pub struct App {
     pub view_start: usize,
     /// Buffer of rows loaded from the database (usually page-sized)
     pub buffer_rows: Vec<Vec<String>>,
+    /// Storage class of each cell in `buffer_rows`
+    pub buffer_kinds: Vec<Vec<CellKind>>,
     /// Global offset corresponding to the first row in `buffer_rows`
     pub buffer_offset: usize,
     /// The last requested global offset used for the current buffer
     pub last_requested_offset: usize,
+    /// Pages loaded ahead of PageDown, most recent last (see `PAGE_CACHE_SIZE`)
+    pub page_cache: VecDeque<(PageKey, DBResponse)>,
     pub page: usize,
     pub total_rows: Option<usize>,
+    /// `total_rows` is an estimate (`approx_count`)
+    pub total_estimated: bool,
+    /// Estimate the row count of big unfiltered tables instead of counting (--approx-count, #)
+    pub approx_count: bool,
+    /// Rows auxiliary views (distinct values, `:` queries) load before cutting off (--result-limit; 0: all)
+    pub result_limit: usize,
+    /// Limit of the distinct-values request in flight (+ raises it for the open picker)
+    distinct_limit: usize,
 
     // Cell selection
     pub sel_row: usize,
     pub sel_col: usize,
+    /// Absolute row position where a Shift+Up/Down selection started
+    pub sel_anchor_row: Option<usize>,
 
     // Editing
     pub edit_buffer: String,
     pub edit_is_null: bool,
+    /// SQL expression written instead of the buffer (Ctrl+t cycles)
+    pub edit_expr: Option<SqlExpr>,
+    /// (rowid, column, text) of the last submitted edit; a failed one is offered again by `e`
+    pub last_edit: Option<(i64, String, String)>,
+    /// Type the edit is written as (starts as AUTO; Tab cycles)
+    pub edit_type: EditType,
     /// Stable rowid of the cell being edited (prevents mismatch on view changes)
     pub edit_rowid: Option<i64>,
+    /// Row being inserted with `o` (kept after a failed insert so it can be fixed)
+    pub new_row: Option<NewRow>,
 
     // Column width tiers per visible column (0 = narrow, 1 = normal, 2 = wide)
     pub col_width_tiers: Vec<u8>,
     // Optional absolute widths for columns; 0 = not set (UI may derive)
     pub col_abs_widths: Vec<u16>,
+    /// Leftmost column drawn once absolute widths overflow the pane (horizontal scroll);
+    /// the draw slides it so `sel_col` stays on screen
+    pub col_scroll: usize,
 
     // Autosize requests (picked up by UI layer)
     pub autosize_col_request: Option<usize>,
@@ -75,6 +496,38 @@ pub struct App {
     // Cell viewer (show full text of current cell)
     pub show_cell_viewer: bool,
 
+    /// CSV export (E) appends to the file instead of replacing it (Ctrl+a in the prompt)
+    pub export_append: bool,
+    /// Excel-friendly CSV: UTF-8 BOM (Ctrl+b) and CRLF line endings (Ctrl+e)
+    pub export_bom: bool,
+    pub export_crlf: bool,
+    /// CSV export quotes every field (Ctrl+q in the prompt; off by default)
+    pub export_quote_all: bool,
+    /// CSV export writes __rowid__ first (Ctrl+r in the prompt; off by default)
+    pub export_include_rowid: bool,
+    /// CSV field separator (Ctrl+d cycles , ; tab |)
+    pub export_delimiter: char,
+    /// A CSV export is running in the worker; Esc cancels it through `export_cancel`
+    pub export_running: bool,
+    pub export_cancel: Arc<AtomicBool>,
+    /// Path and expected row count of the running export, for the progress line
+    pub export_target: String,
+    pub export_total: Option<usize>,
+    /// Export only the selected rows (Ctrl+s; on when E is pressed with a selection)
+    pub export_selection: bool,
+    /// Pretty-print JSON values in the cell viewer (J toggles)
+    pub viewer_pretty_json: bool,
+    /// Lines scrolled in the cell viewer; reset when another cell is selected
+    pub viewer_scroll: u16,
+    /// Largest useful `viewer_scroll` and the pane's text height (set while drawing)
+    pub viewer_max_scroll: u16,
+    pub viewer_page: u16,
+    /// (absolute row, column) the viewer scroll belongs to
+    pub viewer_cell: Option<(usize, usize)>,
+    /// First bytes of a large BLOB, keyed by (table, rowid, column)
+    pub blob_head: Option<((String, i64, String), Vec<u8>)>,
+    pub blob_head_requested: Option<(String, i64, String)>,
+
     // Filter/Sort
     pub filter: Option<String>,
     pub filter_input: Option<String>,
@@ -85,6 +538,87 @@ pub struct App {
     // Help overlay
     pub show_help: bool,
 
+    // Active single-line prompt, if any
+    pub prompt: Option<Prompt>,
+    // Active popup, if any
+    pub overlay: Option<Overlay>,
+
+    // Create-table wizard state (while in AppMode::CreateTable)
+    pub create_form: Option<CreateTableForm>,
+    /// Table to select (and open) once the next schema load arrives
+    pub pending_select_table: Option<String>,
+    /// Table and row offset from the previous session; opened after the first schema load
+    /// if the table still exists
+    pub restore_table: Option<(String, usize)>,
+    /// Row to select once the page containing it arrives (e.g. a freshly inserted row)
+    pub pending_select_rowid: Option<i64>,
+    /// Key of this database in the saved views file (its canonical path)
+    pub views_key: String,
+    /// Rows marked with m, by (table, rowid); kept for the session
+    pub bookmarks: HashSet<(String, i64)>,
+    /// Cells of the loaded table edited this session, by (rowid, column); drawn underlined
+    /// until another table is opened or `r` reloads
+    pub edited_cells: HashSet<(i64, String)>,
+    /// Undoable changes of the loaded table: each cell's value before its latest change
+    /// (shown as "was:" in the status bar)
+    pub previous_values: HashMap<(i64, String), Option<String>>,
+    /// Column and rowids of a fill-down in flight, marked as edited once it succeeds
+    pending_fill: Option<(String, Vec<i64>)>,
+    /// A '/" jump is locating the next bookmarked row
+    bookmark_jump: bool,
+
+    // Main database size in bytes and the SQLite library version (status bar)
+    pub db_size: Option<u64>,
+    pub sqlite_version: Option<String>,
+    /// Size of the `-wal` file; the status bar warns once it passes WAL_WARN_BYTES
+    pub wal_size: Option<u64>,
+
+    /// Shown in place of SQL NULL in the data grid (--null)
+    pub null_display: String,
+    /// Format of c/C/Y/Ctrl+C copies (--copy-format, T cycles)
+    pub copy_format: ExportFormat,
+    /// Program and args that take copies on stdin, tried before the built-in clipboard tools
+    /// (--clipboard-cmd or SQLITE_TUI_CLIPBOARD)
+    pub clipboard_cmd: Option<Vec<String>>,
+    /// Columns whose 0/1 values are shown as booleans (--bool-columns)
+    pub bool_columns: HashSet<String>,
+    pub bool_style: BoolStyle,
+    /// Declared affinity of each loaded column
+    pub column_affinities: Vec<Affinity>,
+    /// Generated (computed) columns among the loaded ones; shown dimmed, never edited
+    pub column_generated: Vec<bool>,
+    /// Right-align numeric columns (F2 toggles)
+    pub align_numbers: bool,
+    /// Alternate row backgrounds (F3 toggles)
+    pub zebra: bool,
+    /// Row-number gutter left of the data (F4 toggles)
+    pub show_row_numbers: bool,
+    /// Draw the `__rowid__` column (F5 toggles); it stays in `columns` either way
+    pub show_rowid: bool,
+    /// Schema sidebar width (Ctrl+Left/Right) and visibility (F6)
+    pub sidebar_width: u16,
+    pub show_sidebar: bool,
+    /// Full-screen data pane (z): no sidebar or filter bar; the status line stays
+    pub zen: bool,
+    /// Focus to restore when leaving zen mode
+    pub focus_before_zen: Focus,
+    /// Drawn width of each data column in the last frame (for clipping)
+    pub col_display_widths: Vec<u16>,
+    /// Width the data columns shared in the last frame (inside the border and gutter)
+    pub columns_width: u16,
+    /// Header cells in the last frame as (row, x range, column index), for mouse clicks
+    pub header_hits: Vec<(u16, std::ops::Range<u16>, usize)>,
+
+    // Set when a reload was triggered by another process changing the database
+    pub external_reload_pending: bool,
+    // Another process changed the database while a new row was being filled in; the
+    // reload waits until the row is inserted or discarded
+    pub external_change_deferred: bool,
+    /// Appended to the status once the next page arrives (e.g. "filled 3 rows")
+    pub reload_note: Option<String>,
+    /// Recent database errors, oldest first, with when they arrived
+    pub error_log: VecDeque<(std::time::SystemTime, String)>,
+
     // Channels
     pub req_tx: Sender<DBRequest>,
     pub resp_rx: Receiver<DBResponse>,
@@ -94,12 +628,33 @@ impl App {
     pub fn new(page_size: usize, req_tx: Sender<DBRequest>, resp_rx: Receiver<DBResponse>) -> Self {
         Self {
             should_quit: false,
+            quit_after_transaction: false,
             mode: AppMode::Normal,
+            read_only: false,
+            immutable: false,
+            in_transaction: false,
             status: "Press q to quit. Enter to open table. e to edit cell. PgUp/PgDn to paginate."
                 .into(),
             tables: vec![],
+            views: vec![],
+            indexes: vec![],
+            schemas: vec![],
+            fts_tables: vec![],
+            schema_loading: None,
+            table_loading: None,
+            group_view: None,
+            query_view: None,
+            query_input: None,
+            query_completion: None,
+            query_columns: HashMap::new(),
             selected_table: 0,
+            collapsed_sections: HashSet::from([SidebarSection::Indexes]),
+            sidebar_query: String::new(),
+            sidebar_query_input: false,
+            table_counts: HashMap::new(),
+            estimated_counts: HashSet::new(),
             focus: Focus::Tables,
+            loaded_table: None,
             columns: vec![],
             rows: vec![],
             page_size,
@@ -107,26 +662,93 @@ impl App {
             global_row_offset: 0,
             view_start: 0,
             buffer_rows: Vec::new(),
+            buffer_kinds: Vec::new(),
             buffer_offset: 0,
             last_requested_offset: 0,
+            page_cache: VecDeque::new(),
             page: 0,
             total_rows: None,
+            total_estimated: false,
+            approx_count: false,
+            result_limit: crate::db::DEFAULT_RESULT_LIMIT,
+            distinct_limit: crate::db::DEFAULT_RESULT_LIMIT,
             sel_row: 0,
             sel_col: 0,
+            sel_anchor_row: None,
             edit_buffer: String::new(),
             edit_is_null: false,
+            edit_expr: None,
+            last_edit: None,
+            edit_type: EditType::Auto,
             edit_rowid: None,
+            new_row: None,
             col_width_tiers: Vec::new(),
             col_abs_widths: Vec::new(),
+            col_scroll: 0,
             autosize_col_request: None,
             autosize_all_request: false,
             show_cell_viewer: false,
+            export_append: false,
+            export_bom: false,
+            export_crlf: false,
+            export_quote_all: false,
+            export_include_rowid: false,
+            export_delimiter: ',',
+            export_selection: false,
+            export_running: false,
+            export_target: String::new(),
+            export_total: None,
+            export_cancel: Arc::new(AtomicBool::new(false)),
+            viewer_pretty_json: true,
+            viewer_scroll: 0,
+            viewer_max_scroll: 0,
+            viewer_page: 1,
+            viewer_cell: None,
+            blob_head: None,
+            blob_head_requested: None,
             filter: None,
             filter_input: None,
             sort_by: None,
             sort_dir: None,
             select_last_row_on_load: false,
             show_help: false,
+            prompt: None,
+            overlay: None,
+            create_form: None,
+            pending_select_table: None,
+            restore_table: None,
+            pending_select_rowid: None,
+            views_key: String::new(),
+            bookmarks: HashSet::new(),
+            bookmark_jump: false,
+            edited_cells: HashSet::new(),
+            previous_values: HashMap::new(),
+            pending_fill: None,
+            db_size: None,
+            sqlite_version: None,
+            wal_size: None,
+            null_display: "NULL".into(),
+            copy_format: ExportFormat::Tsv,
+            clipboard_cmd: None,
+            bool_columns: HashSet::new(),
+            bool_style: BoolStyle::Words,
+            column_affinities: Vec::new(),
+            column_generated: Vec::new(),
+            align_numbers: true,
+            zebra: false,
+            show_row_numbers: false,
+            show_rowid: false,
+            sidebar_width: 30,
+            show_sidebar: true,
+            zen: false,
+            focus_before_zen: Focus::Tables,
+            col_display_widths: Vec::new(),
+            columns_width: 0,
+            header_hits: Vec::new(),
+            external_reload_pending: false,
+            external_change_deferred: false,
+            reload_note: None,
+            error_log: VecDeque::new(),
             req_tx,
             resp_rx,
         }
@@ -134,6 +756,9 @@ impl App {
 
     pub fn request_schema_refresh(&mut self) {
         let _ = self.req_tx.send(DBRequest::LoadSchema);
+        self.schema_loading = Some(std::time::Instant::now());
+        // Schema changes (and VACUUM-like maintenance) are what move the file size
+        let _ = self.req_tx.send(DBRequest::DatabaseInfo);
         self.status = "Loading schema...".into();
     }
 
@@ -141,33 +766,160 @@ impl App {
     pub fn toggle_focus(&mut self) {
         self.focus = match self.focus {
             Focus::Tables => Focus::Data,
-            Focus::Data => Focus::Tables,
+            Focus::Data if self.show_cell_viewer => Focus::Viewer,
+            Focus::Data | Focus::Viewer => Focus::Tables,
         };
+        // Focusing a hidden sidebar brings it back
+        if self.focus == Focus::Tables {
+            self.show_sidebar = true;
+            self.zen = false;
+        }
     }
 
     pub fn handle_db_response(&mut self, resp: DBResponse) {
+        logging::debug(|| format!("response {}", logging::brief(&resp)));
+        // Anything that changes rows or schema makes prefetched pages stale
+        if matches!(
+            resp,
+            DBResponse::CellUpdated { .. }
+                | DBResponse::RowsFilled { .. }
+                | DBResponse::RowInserted { .. }
+                | DBResponse::RowDeleted { .. }
+                | DBResponse::RowsDeleted { .. }
+                | DBResponse::Transaction { .. }
+                | DBResponse::TableDropped { .. }
+                | DBResponse::TableTruncated { .. }
+                | DBResponse::TableRenamed { .. }
+                | DBResponse::ColumnRenamed { .. }
+                | DBResponse::SchemaChanged { .. }
+                | DBResponse::ExternalChange { .. }
+        ) {
+            self.page_cache.clear();
+            self.blob_head = None;
+        }
+        // Writes grow the WAL (and checkpoints shrink it); re-read its size
+        if matches!(
+            resp,
+            DBResponse::CellUpdated { .. }
+                | DBResponse::RowsFilled { .. }
+                | DBResponse::RowInserted { .. }
+                | DBResponse::RowDeleted { .. }
+                | DBResponse::RowsDeleted { .. }
+                | DBResponse::TableTruncated { .. }
+                | DBResponse::Transaction { .. }
+                | DBResponse::ExternalChange { changed: true }
+                | DBResponse::MaintenanceDone { .. }
+        ) {
+            let _ = self.req_tx.send(DBRequest::DatabaseInfo);
+        }
+        // Edits, undo and rollback change what each cell was before
+        if matches!(
+            resp,
+            DBResponse::CellUpdated { ok: true, .. }
+                | DBResponse::RowsFilled { .. }
+                | DBResponse::Transaction { .. }
+        ) {
+            self.request_previous_values();
+        }
         match resp {
-            DBResponse::Schema { tables } => {
+            DBResponse::PagePrefetched { key, data } => {
+                self.page_cache.retain(|(k, _)| *k != key);
+                self.page_cache.push_back((key, *data));
+                if self.page_cache.len() > PAGE_CACHE_SIZE {
+                    self.page_cache.pop_front();
+                }
+            }
+            DBResponse::Schema {
+                tables,
+                views,
+                indexes,
+                schemas,
+                fts_tables,
+            } => {
+                self.schema_loading = None;
+                self.query_columns.clear();
+                let first_load = self.tables.is_empty() && self.views.is_empty();
                 self.tables = tables;
-                if self.selected_table >= self.tables.len() {
-                    self.selected_table = 0;
+                self.views = views;
+                self.indexes = indexes;
+                self.schemas = schemas;
+                self.fts_tables = fts_tables;
+                let entries = self.sidebar_entries();
+                if first_load || self.selected_table >= entries.len() {
+                    // Start on the first openable entry rather than a section header
+                    self.selected_table = entries
+                        .iter()
+                        .position(|e| matches!(e, SidebarEntry::Table(_) | SidebarEntry::View(_)))
+                        .unwrap_or(0);
+                }
+                self.status = format!(
+                    "Loaded {} tables, {} views, {} indexes",
+                    self.tables.len(),
+                    self.views.len(),
+                    self.indexes.len()
+                );
+                if let Some(name) = self.pending_select_table.take() {
+                    if self.select_table_by_name(&name) {
+                        self.global_row_offset = 0;
+                        self.load_selected_table_page(0);
+                    } else {
+                        // A --filter / --sort meant for it would land on whatever opens next
+                        self.filter = None;
+                        self.sort_by = None;
+                        self.sort_dir = None;
+                        self.focus = Focus::Tables;
+                        self.status =
+                            format!("Table '{}' not found — pick one from the sidebar", name);
+                    }
+                }
+                // A table that has since gone leaves the first table selected
+                if let Some((name, offset)) = self.restore_table.take()
+                    && self.select_table_by_name(&name)
+                {
+                    self.focus = Focus::Data;
+                    self.global_row_offset = offset;
+                    self.load_selected_table_page(offset / self.page_size.max(1));
+                }
+                // Counting can be slow on big tables; let the worker do it after the schema
+                // shows. A refresh for an outside change keeps the counts it has, so another
+                // process writing often doesn't keep the worker busy recounting
+                if !self.external_reload_pending || self.table_counts.is_empty() {
+                    let _ = self.req_tx.send(DBRequest::TableCounts {
+                        tables: self.tables.clone(),
+                    });
+                }
+                if self.external_reload_pending && self.columns.is_empty() {
+                    self.external_reload_pending = false;
+                    self.status.push_str(" — reloaded (external change)");
                 }
-                self.status = format!("Loaded {} tables", self.tables.len());
             }
             DBResponse::TableData {
                 table,
                 columns,
+                affinities,
+                generated,
                 rows,
+                kinds,
                 page,
                 total_rows,
+                total_estimated,
             } => {
+                self.table_loading = None;
                 // Update schema and page meta
+                if self.loaded_table.as_deref() != Some(table.as_str()) {
+                    self.sel_anchor_row = None;
+                }
+                self.loaded_table = Some(table.clone());
                 self.columns = columns;
+                self.column_affinities = affinities;
+                self.column_generated = generated;
                 self.page = page;
                 self.total_rows = total_rows;
+                self.total_estimated = total_estimated;
 
                 // Fill buffer with newly loaded rows and remember where they start
                 self.buffer_rows = rows;
+                self.buffer_kinds = kinds;
                 self.buffer_offset = self.last_requested_offset;
 
                 // Compute visible capacity and view window start
@@ -198,8 +950,14 @@ impl App {
                 } else {
                     self.sel_row = self.sel_row.min(cap.saturating_sub(1));
                 }
+                if let Some(rowid) = self.pending_select_rowid.take() {
+                    self.select_buffered_rowid(rowid, cap);
+                }
                 // Keep selected column within bounds
-                self.sel_col = self.sel_col.min(self.columns.len().saturating_sub(1));
+                self.sel_col = self
+                    .sel_col
+                    .min(self.columns.len().saturating_sub(1))
+                    .max(self.first_visible_col());
 
                 // Reset column width tiers and clear absolute widths for each visible column
                 self.col_width_tiers = vec![1; self.columns.len()];
@@ -207,35 +965,257 @@ impl App {
                 self.autosize_col_request = None;
                 self.autosize_all_request = false;
 
+                // "page N of M" once the row count is known (`total_rows` was stored above)
+                let approx = if total_estimated { "~" } else { "" };
+                let of_pages = self
+                    .page_count()
+                    .map(|pages| format!(" of {}{}", approx, pages))
+                    .unwrap_or_default();
                 self.status = format!(
-                    "Viewing {} — page {} ({} rows/page){}",
+                    "Viewing {} — page {}{} ({} rows/page){}",
                     table,
                     page + 1,
+                    of_pages,
                     self.page_size,
                     total_rows
-                        .map(|t| format!(", total ~{}", t))
+                        .map(|t| format!(", total {}{}", approx, t))
                         .unwrap_or_default()
                 );
+                if self.external_reload_pending {
+                    self.external_reload_pending = false;
+                    self.status.push_str(" — reloaded (external change)");
+                }
+                if let Some(note) = self.reload_note.take() {
+                    self.status.push_str(" — ");
+                    self.status.push_str(&note);
+                }
+                // A full page means there may be more: have the next one ready for PageDown
+                if self.buffer_rows.len() == self.page_size && self.group_view.is_none() {
+                    self.prefetch_page((page + 1).saturating_mul(self.page_size));
+                }
+            }
+            DBResponse::QueryResult {
+                sql,
+                columns,
+                rows,
+                kinds,
+                total_rows,
+            } => {
+                self.table_loading = None;
+                if let Some(g) = self.group_view.take() {
+                    (self.sort_by, self.sort_dir) = g.saved_sort;
+                }
+                let shown = rows.len();
+                self.query_view = Some(sql);
+                // Expressions have no declared type: a column holding only numbers is numeric
+                self.column_affinities = (0..columns.len())
+                    .map(|c| {
+                        let mut values = kinds
+                            .iter()
+                            .filter_map(|r| r.get(c))
+                            .filter(|k| **k != CellKind::Null)
+                            .peekable();
+                        if values.peek().is_some()
+                            && values.all(|k| matches!(k, CellKind::Integer | CellKind::Real))
+                        {
+                            Affinity::Numeric
+                        } else {
+                            Affinity::Blob
+                        }
+                    })
+                    .collect();
+                self.column_generated = vec![false; columns.len()];
+                self.col_width_tiers = vec![1; columns.len()];
+                self.col_abs_widths = vec![0; columns.len()];
+                self.columns = columns;
+                self.buffer_rows = rows;
+                self.buffer_kinds = kinds;
+                self.buffer_offset = 0;
+                self.global_row_offset = 0;
+                self.view_start = 0;
+                self.page = 0;
+                // All the rows are in the buffer: one page, nothing to load
+                self.total_rows = Some(shown);
+                self.total_estimated = false;
+                self.sel_row = 0;
+                self.sel_col = self.first_visible_col();
+                self.sel_anchor_row = None;
+                if self.buffer_rows.is_empty() {
+                    self.rows.clear();
+                } else {
+                    self.refit_visible_rows();
+                }
+                let plural = if total_rows == 1 { "" } else { "s" };
+                self.status = if shown < total_rows {
+                    format!(
+                        "Query returned more than {} rows (showing the first {0}; --result-limit) — Esc returns to the table",
+                        group_thousands(shown as i64)
+                    )
+                } else {
+                    format!(
+                        "Query returned {} row{} — Esc returns to the table",
+                        group_thousands(total_rows as i64),
+                        plural
+                    )
+                };
+            }
+            DBResponse::ColumnStats {
+                table,
+                column,
+                stats,
+            } => {
+                self.overlay = Some(Overlay::ColumnStats {
+                    table,
+                    column,
+                    filtered: self.filter.is_some(),
+                    stats,
+                });
+                self.status = "Column stats (any key to close)".into();
+            }
+            DBResponse::DistinctValues {
+                table,
+                column,
+                values,
+                total,
+            } => {
+                if values.is_empty() {
+                    self.status = format!("{} has no values", column);
+                    return;
+                }
+                self.status = if total > values.len() {
+                    format!(
+                        "Showing the {} most frequent of {} distinct values — + shows more, Enter filters on the selected value",
+                        values.len(),
+                        group_thousands(total as i64)
+                    )
+                } else {
+                    format!(
+                        "{} distinct values — Enter filters on the selected value",
+                        values.len()
+                    )
+                };
+                // Raising the limit keeps the picked entry
+                let selected = match &self.overlay {
+                    Some(Overlay::DistinctValues {
+                        table: t,
+                        column: c,
+                        selected,
+                        ..
+                    }) if *t == table && *c == column => *selected,
+                    _ => 0,
+                };
+                self.overlay = Some(Overlay::DistinctValues {
+                    table,
+                    column,
+                    values,
+                    total,
+                    limit: self.distinct_limit,
+                    selected,
+                });
             }
-            DBResponse::CellUpdated { ok, message } => {
+            DBResponse::Transaction { open, rolled_back } => {
+                self.in_transaction = open;
+                if !open && self.quit_after_transaction {
+                    self.should_quit = true;
+                    return;
+                }
+                self.status = if open {
+                    "Transaction started — changes stay uncommitted until t → c".into()
+                } else if rolled_back {
+                    "Rolled back".into()
+                } else {
+                    "Committed".into()
+                };
+                if rolled_back {
+                    // The view may show changes that no longer exist, and tables created,
+                    // dropped or renamed in the transaction are back as they were (the
+                    // schema refresh recounts rows too)
+                    self.reload_note = Some("rolled back".into());
+                    self.reload_current_table();
+                    self.request_schema_refresh();
+                }
+            }
+            DBResponse::RowDeleted { table, rowid } => {
+                self.status = format!("Deleted row {} (u undoes)", rowid);
+                self.edited_cells.retain(|(id, _)| *id != rowid);
+                if self.loaded_table.as_deref() == Some(table.as_str()) {
+                    self.sel_anchor_row = None;
+                    self.reload_note = Some(format!("deleted row {}", rowid));
+                    self.reload_current_table();
+                }
+                let _ = self.req_tx.send(DBRequest::TableCounts {
+                    tables: self.tables.clone(),
+                });
+            }
+            DBResponse::RowsDeleted { table, count } => {
+                self.status = format!("Deleted {} rows", count);
+                if self.loaded_table.as_deref() == Some(table.as_str()) {
+                    self.global_row_offset = 0;
+                    self.sel_row = 0;
+                    self.sel_anchor_row = None;
+                    self.reload_note = Some(format!("deleted {} rows", count));
+                    self.reload_current_table();
+                }
+                let _ = self.req_tx.send(DBRequest::TableCounts {
+                    tables: self.tables.clone(),
+                });
+            }
+            DBResponse::RowsFilled { table, count } => {
+                self.status = format!("Filled {} rows", count);
+                if self.loaded_table.as_deref() == Some(table.as_str()) {
+                    if let Some((column, rowids)) = self.pending_fill.take() {
+                        self.edited_cells
+                            .extend(rowids.into_iter().map(|id| (id, column.clone())));
+                    }
+                    self.reload_note = Some(format!("filled {} rows", count));
+                    self.reload_current_table();
+                }
+            }
+            DBResponse::CellUpdated { ok, rows, message } => {
                 if ok {
-                    // Show clearer status for undo operations and refresh the table
-                    let is_undo = matches!(message.as_deref(), Some(m) if m.contains("Undo"));
-                    self.status = if is_undo {
-                        "Undo: applied".into()
-                    } else {
-                        message.unwrap_or_else(|| "Cell updated".into())
+                    // Say how many rows changed (0 means the row is gone) and refresh the table
+                    let action = match message.as_deref() {
+                        Some(m) if m.starts_with("Undo") => "Undo: restored",
+                        Some(m) if m.starts_with("Redo") => "Redo: reapplied",
+                        _ => "Updated",
                     };
+                    let is_undo = action != "Updated";
+                    let mut note = format!(
+                        "{} {} row{}",
+                        action,
+                        rows,
+                        if rows == 1 { "" } else { "s" }
+                    );
+                    if rows == 0 {
+                        note.push_str(" (no row with that rowid; was it deleted?)");
+                    }
+                    self.status = note.clone();
+                    self.reload_note = Some(note);
+                    if let Some((rowid, column, _)) = self.last_edit.take()
+                        && !is_undo
+                        && rows > 0
+                    {
+                        self.edited_cells.insert((rowid, column));
+                    }
                     self.reload_current_table();
                 } else {
                     let msg = message.unwrap_or_default();
-                    if msg.contains("Undo") {
-                        self.status = format!("Undo failed: {}", msg);
+                    if ["Undo", "Redo", "Nothing to"]
+                        .iter()
+                        .any(|p| msg.starts_with(p))
+                    {
+                        self.status = msg;
                     } else {
-                        self.status = format!("Update failed: {}", msg);
+                        // Nothing changed, so no reload; `e` reopens the editor with the input
+                        self.status = format!("Update failed: {} (e to retry)", msg);
                     }
                 }
             }
+            DBResponse::ExportProgress { rows_written } => {
+                if self.export_running {
+                    self.status = self.export_progress_status(rows_written);
+                }
+            }
             DBResponse::ExportedCSV { ok, path, message } => {
                 if ok {
                     self.status = format!("Exported CSV to {}", path);
@@ -243,312 +1223,2961 @@ impl App {
                     self.status = format!("Export failed: {}", message.unwrap_or_default());
                 }
             }
+            DBResponse::TableDropped { table } => {
+                if self.loaded_table.as_deref() == Some(table.as_str()) {
+                    self.clear_data_view();
+                }
+                self.request_schema_refresh();
+                self.status = format!("Dropped table {}", table);
+            }
+            DBResponse::TableTruncated {
+                table,
+                count,
+                sequence_reset,
+            } => {
+                self.status = format!(
+                    "Deleted all {} rows of {}{}",
+                    count,
+                    table,
+                    if sequence_reset {
+                        " (AUTOINCREMENT restarted)"
+                    } else {
+                        ""
+                    }
+                );
+                if self.loaded_table.as_deref() == Some(table.as_str()) {
+                    self.global_row_offset = 0;
+                    self.sel_row = 0;
+                    self.sel_anchor_row = None;
+                    self.edited_cells.clear();
+                    self.reload_note = Some(format!("deleted {} rows", count));
+                    self.reload_current_table();
+                }
+                let _ = self.req_tx.send(DBRequest::TableCounts {
+                    tables: self.tables.clone(),
+                });
+            }
+            DBResponse::TableRenamed { table, new_name } => {
+                let was_loaded = self.loaded_table.as_deref() == Some(table.as_str());
+                if was_loaded {
+                    self.clear_data_view();
+                }
+                let was_selected = self.current_table_name() == Some(table.as_str());
+                if was_loaded || was_selected {
+                    self.pending_select_table = Some(new_name.clone());
+                }
+                self.request_schema_refresh();
+                self.status = format!("Renamed {} to {}", table, new_name);
+            }
+            DBResponse::ColumnRenamed {
+                table,
+                column,
+                new_name,
+            } => {
+                if self.sort_column() == Some(column.as_str()) {
+                    self.sort_by = Some(if self.sort_is_numeric() {
+                        format!("{}{}", NUMERIC_SORT_PREFIX, new_name)
+                    } else {
+                        new_name.clone()
+                    });
+                }
+                if self.loaded_table.as_deref() == Some(table.as_str()) {
+                    if let Some(c) = self.columns.iter_mut().find(|c| **c == column) {
+                        *c = new_name.clone();
+                    }
+                    self.reload_current_table();
+                }
+                self.status = format!("Renamed column {} to {}", column, new_name);
+            }
+            DBResponse::RowInserted { table, rowid } => {
+                if self.new_row.as_ref().is_some_and(|n| n.sent) {
+                    self.new_row = None;
+                }
+                self.status = format!("Inserted row {}", rowid);
+                let _ = self.req_tx.send(DBRequest::LocateRow {
+                    table,
+                    rowid,
+                    filter: self.filter.clone(),
+                    sort_by: self.sort_by.clone(),
+                    sort_dir: self.sort_dir,
+                });
+            }
+            DBResponse::RowLocated {
+                table,
+                rowid,
+                position,
+            } => {
+                if self.loaded_table.as_deref() != Some(table.as_str()) {
+                    return;
+                }
+                let jumping = std::mem::take(&mut self.bookmark_jump);
+                match position {
+                    None if jumping => {
+                        self.status =
+                            "Bookmarked rows are hidden by the current filter (or were deleted)"
+                                .into();
+                    }
+                    Some(pos) => {
+                        // Scroll just enough to bring the row into the visible window
+                        let visible = self.visible_rows_per_page.max(1);
+                        if pos < self.global_row_offset {
+                            self.global_row_offset = pos;
+                        } else if pos >= self.global_row_offset + visible {
+                            self.global_row_offset = pos + 1 - visible;
+                        }
+                        self.pending_select_rowid = Some(rowid);
+                        self.load_selected_table_page(pos / self.page_size.max(1));
+                    }
+                    None => {
+                        self.status = format!("Inserted row {} (hidden by current filter)", rowid);
+                        self.reload_current_table();
+                    }
+                }
+            }
+            DBResponse::SchemaChanged { message } => {
+                self.request_schema_refresh();
+                self.status = message;
+            }
+            DBResponse::TableCounts { counts, estimated } => {
+                self.table_counts = counts;
+                self.estimated_counts = estimated;
+            }
+            DBResponse::TableColumns { table, columns } => {
+                self.query_columns.insert(table, columns);
+            }
+            DBResponse::ExternalChange { changed } => {
+                if changed && self.inserting() {
+                    self.external_change_deferred = true;
+                } else if changed {
+                    self.reload_for_external_change();
+                }
+            }
+            DBResponse::Attached { alias, path } => {
+                self.request_schema_refresh();
+                self.status = format!("Attached {} as {}", path, alias);
+            }
+            DBResponse::BackupProgress {
+                done_pages,
+                total_pages,
+            } => {
+                let pct = if total_pages > 0 {
+                    done_pages as i64 * 100 / total_pages as i64
+                } else {
+                    100
+                };
+                self.status = format!("Backing up... {}%", pct);
+            }
+            DBResponse::BlobHead {
+                table,
+                rowid,
+                column,
+                head,
+            } => {
+                self.blob_head_requested = None;
+                self.blob_head = Some(((table, rowid, column), head));
+            }
+            DBResponse::BlobSaved { path, bytes } => {
+                self.status = format!("Saved {} to {}", format_bytes(bytes as u64), path);
+            }
+            DBResponse::BackupDone { path, size_bytes } => {
+                self.status = format!("Backed up to {} ({})", path, format_bytes(size_bytes));
+            }
+            DBResponse::MaintenanceDone { command } => {
+                self.status = format!("{} done", command);
+            }
+            DBResponse::PreviousValues { table, values } => {
+                if self.loaded_table.as_deref() == Some(table.as_str()) {
+                    self.previous_values = values;
+                }
+            }
+            DBResponse::IntegrityCheck { problems } => {
+                let ok = problems.is_empty();
+                self.status = if ok {
+                    "Integrity check: ok".into()
+                } else {
+                    format!("Integrity check: {} problem(s) found", problems.len())
+                };
+                let lines = if ok {
+                    vec!["ok — no problems found".to_string()]
+                } else {
+                    problems
+                };
+                self.overlay = Some(Overlay::Info {
+                    title: "Integrity check".into(),
+                    lines,
+                    scroll: 0,
+                });
+            }
+            DBResponse::DatabaseInfo {
+                size_bytes,
+                wal_bytes,
+                sqlite_version,
+            } => {
+                self.db_size = Some(size_bytes);
+                self.wal_size = wal_bytes;
+                self.sqlite_version = Some(sqlite_version);
+            }
+            DBResponse::Timing { elapsed } => {
+                self.status
+                    .push_str(&format!(" ({})", format_elapsed(elapsed)));
+            }
+            DBResponse::TransactionFailed { message, open } => {
+                // Quitting waited for this commit or rollback; stay so it can be retried
+                self.in_transaction = open;
+                self.quit_after_transaction = false;
+                self.handle_db_response(DBResponse::Error(message));
+            }
             DBResponse::Error(msg) => {
-                self.status = format!("Error: {msg}");
+                // Possibly the schema or table load itself failing; don't spin forever
+                self.schema_loading = None;
+                self.table_loading = None;
+                // A failed BlobHead may be asked for again
+                self.blob_head_requested = None;
+                // The status line shows the first line; `!` has the whole message
+                let first = msg.lines().next().unwrap_or("");
+                self.status = if first.len() < msg.len() {
+                    format!("Error: {first}… (! shows details)")
+                } else {
+                    format!("Error: {msg} (! error log)")
+                };
+                if let Some(n) = self.new_row.as_mut()
+                    && n.sent
+                {
+                    n.sent = false;
+                    self.status.push_str(" — o reopens the new row");
+                }
+                self.error_log
+                    .push_back((std::time::SystemTime::now(), msg));
+                if self.error_log.len() > ERROR_LOG_SIZE {
+                    self.error_log.pop_front();
+                }
             }
         }
     }
 
-    pub fn current_table_name(&self) -> Option<&str> {
-        self.tables.get(self.selected_table).map(|s| s.as_str())
+    /// !: show the recent database errors, newest first
+    pub fn show_error_log(&mut self) {
+        if self.error_log.is_empty() {
+            self.status = "No database errors this session".into();
+            return;
+        }
+        let mut lines = Vec::new();
+        for (at, msg) in self.error_log.iter().rev() {
+            let mut msg_lines = msg.lines();
+            lines.push(format!(
+                "[{}] {}",
+                format_clock_utc(*at),
+                msg_lines.next().unwrap_or("")
+            ));
+            lines.extend(msg_lines.map(|l| format!("    {}", l)));
+            lines.push(String::new());
+        }
+        self.overlay = Some(Overlay::Info {
+            title: format!("Errors ({}, newest first)", self.error_log.len()),
+            lines,
+            scroll: 0,
+        });
     }
 
-    pub fn load_selected_table_page(&mut self, page: usize) {
-        if let Some(table) = self.current_table_name().map(|s| s.to_string()) {
-            // Keep existing global_row_offset (smooth scroll base); do not reset on reloads
-            self.last_requested_offset = self.global_row_offset;
-            let _ = self.req_tx.send(DBRequest::LoadTable {
-                table,
-                page,
-                page_size: self.page_size,
-                offset_override: Some(self.global_row_offset),
-                filter: self.filter.clone(),
-                sort_by: self.sort_by.clone(),
-                sort_dir: self.sort_dir,
-            });
-            self.status = "Loading table...".into();
+    /// Storage class of a cell in the visible window
+    pub fn cell_kind(&self, row: usize, col: usize) -> Option<CellKind> {
+        self.buffer_kinds
+            .get(self.view_start + row)
+            .and_then(|r| r.get(col))
+            .copied()
+    }
+
+    /// Text to draw for a cell in the visible window: NULLs use `null_display` and 0/1 in
+    /// boolean columns become true/false. The stored value (and what gets edited) is unchanged
+    pub fn display_cell<'a>(&'a self, row: usize, col: usize, raw: &'a str) -> Cow<'a, str> {
+        match self.cell_kind(row, col) {
+            Some(CellKind::Null) => Cow::Borrowed(self.null_display.as_str()),
+            Some(CellKind::Integer)
+                if (raw == "0" || raw == "1")
+                    && self
+                        .columns
+                        .get(col)
+                        .is_some_and(|c| self.bool_columns.contains(c)) =>
+            {
+                let on = raw == "1";
+                Cow::Borrowed(match (self.bool_style, on) {
+                    (BoolStyle::Words, true) => "true",
+                    (BoolStyle::Words, false) => "false",
+                    (BoolStyle::Boxes, true) => "☑",
+                    (BoolStyle::Boxes, false) => "☐",
+                })
+            }
+            _ => Cow::Borrowed(raw),
         }
     }
 
-    pub fn reload_current_table(&mut self) {
-        self.load_selected_table_page(self.page);
+    /// Whether column `col` is drawn right-aligned
+    pub fn column_right_aligned(&self, col: usize) -> bool {
+        self.align_numbers
+            && self
+                .column_affinities
+                .get(col)
+                .is_some_and(|a| a.is_numeric())
     }
 
-    pub fn move_table_selection_up(&mut self) {
-        if self.tables.is_empty() {
-            return;
-        }
-        if self.selected_table == 0 {
-            self.selected_table = self.tables.len() - 1;
+    pub fn toggle_align_numbers(&mut self) {
+        self.align_numbers = !self.align_numbers;
+        self.status = if self.align_numbers {
+            "Numeric columns: right-aligned".into()
         } else {
-            self.selected_table -= 1;
-        }
+            "Numeric columns: left-aligned".into()
+        };
     }
 
-    pub fn move_table_selection_down(&mut self) {
-        if self.tables.is_empty() {
-            return;
-        }
-        self.selected_table = (self.selected_table + 1) % self.tables.len();
+    pub fn toggle_zebra(&mut self) {
+        self.zebra = !self.zebra;
+        self.status = format!("Zebra striping: {}", if self.zebra { "ON" } else { "OFF" });
     }
 
-    pub fn next_page(&mut self) {
-        // Jump by a full page: advance the smooth-scroll base accordingly
-        self.global_row_offset = (self.page + 1).saturating_mul(self.page_size);
-        self.load_selected_table_page(self.page + 1);
+    pub fn toggle_row_numbers(&mut self) {
+        self.show_row_numbers = !self.show_row_numbers;
+        self.status = format!(
+            "Row numbers: {}",
+            if self.show_row_numbers { "ON" } else { "OFF" }
+        );
     }
 
-    pub fn prev_page(&mut self) {
-        if self.page > 0 {
-            // Jump back by a full page: move the smooth-scroll base accordingly
-            self.global_row_offset = (self.page - 1).saturating_mul(self.page_size);
-            self.load_selected_table_page(self.page - 1);
+    pub fn toggle_rowid_column(&mut self) {
+        self.show_rowid = !self.show_rowid;
+        self.sel_col = self.sel_col.max(self.first_visible_col());
+        self.status = format!(
+            "__rowid__ column: {}",
+            if self.show_rowid { "shown" } else { "hidden" }
+        );
+    }
+
+    /// Ctrl+Left/Right: narrow or widen the sidebar (the UI also caps it at half the screen)
+    pub fn resize_sidebar(&mut self, delta: i16) {
+        self.show_sidebar = true;
+        self.sidebar_width = self
+            .sidebar_width
+            .saturating_add_signed(delta)
+            .clamp(SIDEBAR_MIN_WIDTH, SIDEBAR_MAX_WIDTH);
+        self.status = format!("Sidebar width: {}", self.sidebar_width);
+    }
+
+    pub fn toggle_sidebar(&mut self) {
+        self.show_sidebar = !self.show_sidebar;
+        if !self.show_sidebar && self.focus == Focus::Tables {
+            self.focus = Focus::Data;
         }
+        self.status = format!(
+            "Sidebar: {}",
+            if self.show_sidebar { "shown" } else { "hidden" }
+        );
     }
 
-    // P0: Filter helpers
-    pub fn set_filter_string(&mut self, filter: Option<String>) {
-        self.filter = filter;
-        // Reset to first page when filter changes
-        self.load_selected_table_page(0);
+    pub fn toggle_zen(&mut self) {
+        self.zen = !self.zen;
+        if self.zen {
+            self.focus_before_zen = self.focus;
+            self.focus = Focus::Data;
+        } else {
+            self.focus = self.focus_before_zen;
+        }
+        self.status = format!("Full-screen data: {}", if self.zen { "ON" } else { "OFF" });
     }
 
-    pub fn clear_filter(&mut self) {
-        self.set_filter_string(None);
+    /// Index of the first drawn column: 1 while `__rowid__` (always column 0) is hidden
+    pub fn first_visible_col(&self) -> usize {
+        if self.show_rowid {
+            0
+        } else {
+            1.min(self.columns.len().saturating_sub(1))
+        }
     }
 
-    // Inline filter input state (for visible entry in UI)
-    pub fn begin_filter_input(&mut self) {
-        self.filter_input = Some(String::new());
+    /// Forget the loaded table and empty the data pane
+    pub fn clear_data_view(&mut self) {
+        self.loaded_table = None;
+        self.edited_cells.clear();
+        self.col_scroll = 0;
+        self.previous_values.clear();
+        self.columns.clear();
+        self.column_affinities.clear();
+        self.column_generated.clear();
+        self.rows.clear();
+        self.buffer_rows.clear();
+        self.buffer_kinds.clear();
+        self.buffer_offset = 0;
+        self.global_row_offset = 0;
+        self.view_start = 0;
+        self.page = 0;
+        self.total_rows = None;
+        self.total_estimated = false;
+        self.sel_row = 0;
+        self.sel_col = 0;
+        self.sel_anchor_row = None;
     }
 
-    pub fn update_filter_input_char(&mut self, c: char) {
-        if let Some(buf) = self.filter_input.as_mut() {
-            buf.push(c);
+    /// The grouped-count view and query results show no real rows, so row and column
+    /// actions wait for `G` (or Esc)
+    pub fn refuse_if_grouped(&mut self, action: &str) -> bool {
+        if self.query_view.is_some() {
+            self.status = format!(
+                "Query results: cannot {} (Esc returns to the table)",
+                action
+            );
+            return true;
         }
+        if self.group_view.is_some() {
+            self.status = format!("Grouped view: cannot {} (G returns to rows)", action);
+        }
+        self.group_view.is_some()
     }
 
-    pub fn backspace_filter_input(&mut self) {
-        if let Some(buf) = self.filter_input.as_mut() {
-            buf.pop();
+    /// G: counts per distinct value of the selected column (filter applied); G again
+    /// returns to the rows with the previous sort, column and position
+    pub fn toggle_group_view(&mut self) {
+        if self.query_view.is_some() {
+            self.status = "Query results can't be grouped (Esc returns to the table)".into();
+            return;
+        }
+        self.page_cache.clear();
+        if let Some(g) = self.group_view.take() {
+            (self.sort_by, self.sort_dir) = g.saved_sort;
+            self.sel_col = g.saved_col;
+            self.global_row_offset = g.saved_offset;
+            self.sel_row = 0;
+            self.load_selected_table_page(g.saved_offset / self.page_size.max(1));
+            return;
         }
+        if self.loaded_table.is_none() {
+            self.status = "Open a table to group its rows".into();
+            return;
+        }
+        let column = match self.columns.get(self.sel_col) {
+            Some(c) if c != "__rowid__" => c.clone(),
+            _ => {
+                self.status = "Group: pick a data column".into();
+                return;
+            }
+        };
+        self.group_view = Some(GroupView {
+            column,
+            saved_sort: (self.sort_by.take(), self.sort_dir.take()),
+            saved_col: self.sel_col,
+            saved_offset: self.global_row_offset,
+        });
+        self.sel_anchor_row = None;
+        self.global_row_offset = 0;
+        self.sel_row = 0;
+        self.sel_col = 1;
+        self.load_selected_table_page(0);
     }
 
-    pub fn apply_filter_input(&mut self) {
-        let pending = self.filter_input.take();
-        match pending {
-            Some(s) if !s.is_empty() => self.set_filter_string(Some(s)),
-            _ => self.clear_filter(),
+    /// In read-only mode, explain why `action` is refused and return true
+    pub fn refuse_if_read_only(&mut self, action: &str) -> bool {
+        if self.read_only {
+            self.status = format!("Read-only mode: cannot {}", action);
         }
+        self.read_only
     }
 
-    pub fn cancel_filter_input(&mut self) {
-        self.filter_input = None;
+    /// Schema a table name belongs to ("alias.table" for attached databases, else "main")
+    pub fn table_schema<'a>(&'a self, table: &str) -> &'a str {
+        self.schemas
+            .iter()
+            .filter(|s| s.as_str() != "main")
+            .find(|s| {
+                table
+                    .strip_prefix(s.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .map(|s| s.as_str())
+            .unwrap_or("main")
     }
 
-    // Help overlay toggle
-    pub fn toggle_help(&mut self) {
-        self.show_help = !self.show_help;
+    // ===== Single-line prompts =====
+
+    pub fn begin_prompt(&mut self, kind: PromptKind) {
+        self.begin_prompt_with(kind, String::new());
     }
 
-    // P0: Sort helpers
-    // Cycle sort for the currently selected column: None -> ASC -> DESC -> None
-    pub fn sort_cycle_on_selection(&mut self) {
-        if self.columns.is_empty() {
+    /// Open a prompt pre-filled with `input` (e.g. the name being renamed)
+    pub fn begin_prompt_with(&mut self, kind: PromptKind, input: String) {
+        self.prompt = Some(Prompt { kind, input });
+    }
+
+    pub fn prompt_insert(&mut self, c: char) {
+        if let Some(p) = self.prompt.as_mut() {
+            p.input.push(c);
+        }
+    }
+
+    pub fn prompt_backspace(&mut self) {
+        if let Some(p) = self.prompt.as_mut() {
+            p.input.pop();
+        }
+    }
+
+    pub fn cancel_prompt(&mut self) {
+        if self.prompt.take().is_some() {
+            self.status = "Cancelled".into();
+        }
+    }
+
+    /// Act on the entered prompt text and close the prompt
+    pub fn submit_prompt(&mut self) {
+        let Some(Prompt { kind, input }) = self.prompt.take() else {
+            return;
+        };
+        let input = input.trim();
+        match kind {
+            PromptKind::Attach => {
+                if input.is_empty() {
+                    self.status = "Attach cancelled".into();
+                    return;
+                }
+                let (path, alias) = parse_attach_input(input);
+                if alias.is_empty() {
+                    self.status = "Attach: could not derive an alias; use 'path as alias'".into();
+                    return;
+                }
+                self.status = format!("Attaching {} as {}...", path, alias);
+                let _ = self.req_tx.send(DBRequest::AttachDatabase { path, alias });
+            }
+            PromptKind::ConfirmDrop { table } => {
+                if input != table {
+                    self.status = "Drop cancelled (name did not match)".into();
+                    return;
+                }
+                self.status = format!("Dropping {}...", table);
+                let _ = self.req_tx.send(DBRequest::DropTable { table });
+            }
+            PromptKind::ConfirmTruncate { table } => {
+                let reset_sequence = match input.strip_suffix(" reset") {
+                    Some(name) if name == table => true,
+                    _ if input == table => false,
+                    _ => {
+                        self.status = "Truncate cancelled (name did not match)".into();
+                        return;
+                    }
+                };
+                self.status = format!("Deleting all rows of {}...", table);
+                let _ = self.req_tx.send(DBRequest::TruncateTable {
+                    table,
+                    reset_sequence,
+                });
+            }
+            PromptKind::ConfirmDeleteRow { table, rowid } => {
+                if !input.eq_ignore_ascii_case("y") {
+                    self.status = "Delete cancelled".into();
+                    return;
+                }
+                self.status = format!("Deleting row {}...", rowid);
+                let _ = self.req_tx.send(DBRequest::DeleteRow { table, rowid });
+            }
+            PromptKind::SaveView => {
+                if input.is_empty() {
+                    self.status = "Save view cancelled".into();
+                    return;
+                }
+                self.save_current_view(input.to_string());
+            }
+            PromptKind::Maintenance { table } => match (input.to_ascii_lowercase().as_str(), table)
+            {
+                ("a" | "analyze", _) => {
+                    self.status = "Running ANALYZE…".into();
+                    let _ = self.req_tx.send(DBRequest::Analyze);
+                }
+                ("r" | "reindex", Some(table)) => {
+                    self.status = format!("Rebuilding the indexes of {}…", table);
+                    let _ = self.req_tx.send(DBRequest::Reindex { table });
+                }
+                _ => self.status = "Maintenance cancelled".into(),
+            },
+            PromptKind::EndTransaction => match input.to_ascii_lowercase().as_str() {
+                "c" | "commit" => {
+                    self.status = "Committing...".into();
+                    let _ = self.req_tx.send(DBRequest::Commit);
+                }
+                "r" | "rollback" => {
+                    self.status = "Rolling back...".into();
+                    let _ = self.req_tx.send(DBRequest::Rollback);
+                }
+                _ => self.status = "Transaction still open (t to commit or roll back)".into(),
+            },
+            PromptKind::ConfirmQuit => match input.to_ascii_lowercase().as_str() {
+                "c" | "commit" => {
+                    self.status = "Committing before quitting...".into();
+                    self.quit_after_transaction = true;
+                    let _ = self.req_tx.send(DBRequest::Commit);
+                }
+                "r" | "rollback" => {
+                    self.status = "Rolling back before quitting...".into();
+                    self.quit_after_transaction = true;
+                    let _ = self.req_tx.send(DBRequest::Rollback);
+                }
+                _ => self.status = "Quit cancelled; transaction still open".into(),
+            },
+            PromptKind::ConfirmDeleteMatching {
+                table,
+                filter,
+                count,
+            } => {
+                if input != count.to_string() {
+                    self.status = "Delete cancelled (count did not match)".into();
+                    return;
+                }
+                self.status = format!("Deleting {} rows...", count);
+                let _ = self
+                    .req_tx
+                    .send(DBRequest::DeleteMatching { table, filter });
+            }
+            PromptKind::RenameTable { table } => {
+                let old_base = table.rsplit('.').next().unwrap_or(&table);
+                if input.is_empty() || input == table || input == old_base {
+                    self.status = "Rename cancelled".into();
+                    return;
+                }
+                self.status = format!("Renaming {} to {}...", table, input);
+                let _ = self.req_tx.send(DBRequest::RenameTable {
+                    table,
+                    new_name: input.to_string(),
+                });
+            }
+            PromptKind::RenameColumn { table, column } => {
+                if input.is_empty() || input == column {
+                    self.status = "Rename cancelled".into();
+                    return;
+                }
+                self.status = format!("Renaming column {} to {}...", column, input);
+                let _ = self.req_tx.send(DBRequest::RenameColumn {
+                    table,
+                    column,
+                    new_name: input.to_string(),
+                });
+            }
+            PromptKind::Backup => {
+                if input.is_empty() {
+                    self.status = "Backup cancelled".into();
+                    return;
+                }
+                if std::path::Path::new(input).exists() {
+                    self.begin_prompt(PromptKind::ConfirmOverwriteBackup {
+                        path: input.to_string(),
+                    });
+                    return;
+                }
+                self.start_backup(input.to_string(), false);
+            }
+            PromptKind::SaveBlob {
+                table,
+                rowid,
+                column,
+            } => {
+                if input.is_empty() {
+                    self.status = "Save cancelled".into();
+                } else if std::path::Path::new(input).exists() {
+                    self.status = format!("{} already exists; pick another path", input);
+                } else {
+                    self.status = format!("Saving BLOB to {}...", input);
+                    let _ = self.req_tx.send(DBRequest::SaveBlob {
+                        table,
+                        rowid,
+                        column,
+                        path: input.to_string(),
+                    });
+                }
+            }
+            PromptKind::ConfirmOverwriteBackup { path } => {
+                if input.eq_ignore_ascii_case("y") {
+                    self.start_backup(path, true);
+                } else {
+                    self.status = "Backup cancelled".into();
+                }
+            }
+            PromptKind::GotoPage => match input.parse::<usize>() {
+                Ok(page) if page > 0 => self.goto_page(page),
+                _ if input.is_empty() => self.status = "Go to page cancelled".into(),
+                _ => self.status = format!("'{}' isn't a page number", input),
+            },
+            PromptKind::GotoColumn => {
+                if input.is_empty() {
+                    self.status = "Go to column cancelled".into();
+                } else {
+                    self.goto_column(input);
+                }
+            }
+        }
+    }
+
+    /// W: ask where to save the selected BLOB cell
+    pub fn begin_save_blob(&mut self) {
+        if self.refuse_if_grouped("save BLOBs") {
             return;
         }
-        let col_name = self.columns[self.sel_col].clone();
-        self.sort_by = Some(col_name);
-        self.sort_dir = match self.sort_dir {
-            None => Some(SortDir::Asc),
-            Some(SortDir::Asc) => Some(SortDir::Desc),
-            Some(SortDir::Desc) => None,
+        if self.cell_kind(self.sel_row, self.sel_col) != Some(CellKind::Blob) {
+            self.status = "W saves BLOB cells; this cell isn't one".into();
+            return;
+        }
+        let (Some(table), Some(rowid), Some(column)) = (
+            self.loaded_table.clone(),
+            self.rowid_at_abs(self.cursor_row_abs()),
+            self.columns.get(self.sel_col).cloned(),
+        ) else {
+            self.status = "This row has no rowid to read the BLOB from".into();
+            return;
         };
-        self.reload_current_table();
+        self.begin_prompt(PromptKind::SaveBlob {
+            table,
+            rowid,
+            column,
+        });
     }
 
-    // Explicitly toggle sort direction (defaults to ASC when not set)
-    pub fn sort_toggle_dir(&mut self) {
-        self.sort_dir = match self.sort_dir {
-            Some(SortDir::Asc) => Some(SortDir::Desc),
-            _ => Some(SortDir::Asc),
+    /// Size and leading bytes of the selected cell when it is a BLOB (for the cell viewer).
+    /// Large BLOBs come as a placeholder; their bytes are empty until `request_blob_head`
+    /// has fetched them
+    pub fn current_blob_preview(&self) -> Option<(usize, Vec<u8>)> {
+        if self.cell_kind(self.sel_row, self.sel_col) != Some(CellKind::Blob) {
+            return None;
+        }
+        let text = self.current_cell_text()?;
+        if let Some(len) = parse_blob_placeholder(text) {
+            let head = self
+                .blob_head
+                .as_ref()
+                .filter(|h| Some(&h.0) == self.current_blob_key().as_ref())
+                .map(|h| h.1.clone())
+                .unwrap_or_default();
+            return Some((len, head));
+        }
+        let hex = text.strip_prefix("0x")?;
+        let bytes = (0..hex.len().min(BLOB_PREVIEW_BYTES * 2))
+            .step_by(2)
+            .filter_map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect();
+        Some((hex.len() / 2, bytes))
+    }
+
+    fn current_blob_key(&self) -> Option<(String, i64, String)> {
+        Some((
+            self.loaded_table.clone()?,
+            self.rowid_at_abs(self.cursor_row_abs())?,
+            self.columns.get(self.sel_col)?.clone(),
+        ))
+    }
+
+    /// Fetch the first bytes of the selected BLOB if it only arrived as a placeholder
+    pub fn request_blob_head(&mut self) {
+        let placeholder = self.cell_kind(self.sel_row, self.sel_col) == Some(CellKind::Blob)
+            && self
+                .current_cell_text()
+                .is_some_and(|t| parse_blob_placeholder(t).is_some());
+        let Some(key) = self.current_blob_key().filter(|_| placeholder) else {
+            return;
         };
-        self.reload_current_table();
+        if self.blob_head.as_ref().is_some_and(|h| h.0 == key)
+            || self.blob_head_requested.as_ref() == Some(&key)
+        {
+            return;
+        }
+        self.blob_head_requested = Some(key.clone());
+        let (table, rowid, column) = key;
+        let _ = self.req_tx.send(DBRequest::BlobHead {
+            table,
+            rowid,
+            column,
+            bytes: BLOB_PREVIEW_BYTES,
+        });
     }
 
-    pub fn move_cell_left(&mut self) {
-        if self.columns.is_empty() {
+    fn start_backup(&mut self, path: String, overwrite: bool) {
+        self.status = format!("Backing up to {}...", path);
+        let _ = self.req_tx.send(DBRequest::Backup { path, overwrite });
+    }
+
+    /// Rename the selected table (sidebar focus) or the selected column (data focus)
+    pub fn begin_rename(&mut self) {
+        if self.refuse_if_grouped("rename") {
+            return;
+        }
+        if self.refuse_if_read_only("rename") {
             return;
         }
-        self.sel_col = self.sel_col.saturating_sub(1);
+        if self.focus == Focus::Data {
+            let Some(table) = self.loaded_table.clone() else {
+                self.status = "Open a table to rename its columns".into();
+                return;
+            };
+            if self.loaded_is_view() {
+                self.status = "Views are read-only".into();
+                return;
+            }
+            match self.columns.get(self.sel_col) {
+                Some(c) if c != "__rowid__" => {
+                    let column = c.clone();
+                    self.begin_prompt_with(
+                        PromptKind::RenameColumn {
+                            table,
+                            column: column.clone(),
+                        },
+                        column,
+                    );
+                }
+                _ => self.status = "Select a regular column to rename".into(),
+            }
+            return;
+        }
+        if self.current_is_view() {
+            self.status = "Select a table (not a view) to rename".into();
+            return;
+        }
+        let Some(table) = self.current_table_name().map(|s| s.to_string()) else {
+            self.status = "No table selected to rename".into();
+            return;
+        };
+        // Seed with the bare name; attached tables keep their schema
+        let seed = match self.table_schema(&table) {
+            "main" => table.clone(),
+            schema => table[schema.len() + 1..].to_string(),
+        };
+        self.begin_prompt_with(PromptKind::RenameTable { table }, seed);
     }
 
-    pub fn move_cell_right(&mut self) {
-        if self.columns.is_empty() {
+    /// t: BEGIN, or (while a transaction is open) ask whether to commit or roll back
+    pub fn transaction_key(&mut self) {
+        if self.in_transaction {
+            self.begin_prompt(PromptKind::EndTransaction);
             return;
         }
-        self.sel_col = min(self.sel_col + 1, self.columns.len().saturating_sub(1));
+        if self.refuse_if_read_only("start transactions") {
+            return;
+        }
+        let _ = self.req_tx.send(DBRequest::Begin);
     }
 
-    pub fn move_cell_up(&mut self) {
-        if self.sel_row > 0 {
-            self.sel_row = self.sel_row.saturating_sub(1);
+    /// M: ask whether to run ANALYZE or REINDEX the selected table
+    pub fn begin_maintenance(&mut self) {
+        if self.refuse_if_read_only("run ANALYZE or REINDEX") {
             return;
         }
-        // At top of visible window: try to scroll within current buffer first
-        if self.global_row_offset > self.buffer_offset {
-            self.global_row_offset = self.global_row_offset.saturating_sub(1);
-            self.view_start = self.view_start.saturating_sub(1);
-            let cap = self
-                .visible_rows_per_page
-                .min(self.buffer_rows.len())
-                .max(1);
-            if !self.rows.is_empty() && cap == self.rows.len() {
-                // shift window up by one: prepend new row and drop last
-                let new_row = self.buffer_rows[self.view_start].clone();
-                self.rows.pop();
-                self.rows.insert(0, new_row);
-            } else {
-                // fallback rebuild
-                self.rows = self
-                    .buffer_rows
-                    .iter()
-                    .skip(self.view_start)
-                    .take(cap)
-                    .cloned()
-                    .collect();
-            }
-            // Keep cursor at top
-            self.sel_row = 0;
+        let table = if self.current_is_view() {
+            None
+        } else {
+            self.current_table_name().map(|t| t.to_string())
+        };
+        self.begin_prompt(PromptKind::Maintenance { table });
+    }
+
+    /// K: checkpoint the WAL into the main database file
+    pub fn checkpoint_wal(&mut self) {
+        if self.refuse_if_read_only("checkpoint the WAL") {
             return;
         }
-        // Need to load previous buffer
-        if self.global_row_offset > 0 {
-            self.global_row_offset = self.global_row_offset.saturating_sub(1);
-            self.select_last_row_on_load = true;
-            self.status = "Loading previous page…".into();
-            self.load_selected_table_page(self.global_row_offset / self.page_size);
+        if self.in_transaction {
+            self.status = "Commit or roll back the open transaction before a checkpoint".into();
+            return;
         }
+        self.status = "Checkpointing the WAL…".into();
+        let _ = self.req_tx.send(DBRequest::Checkpoint);
     }
 
-    pub fn move_cell_down(&mut self) {
-        let last_visible = self
-            .visible_rows_per_page
-            .min(self.rows.len())
-            .saturating_sub(1);
-        if self.sel_row < last_visible {
-            self.sel_row = min(self.sel_row + 1, last_visible);
+    /// True once the `-wal` file has grown past WAL_WARN_BYTES
+    pub fn wal_oversized(&self) -> bool {
+        self.wal_size.is_some_and(|n| n > WAL_WARN_BYTES)
+    }
+
+    /// q: quit, unless a transaction is open; then ask whether to commit or roll back first
+    pub fn request_quit(&mut self) {
+        if self.in_transaction {
+            self.begin_prompt(PromptKind::ConfirmQuit);
+        } else {
+            self.should_quit = true;
+        }
+    }
+
+    /// X: ask for a typed confirmation before deleting every row the filter matches
+    pub fn begin_delete_matching(&mut self) {
+        if self.refuse_if_grouped("delete rows") {
             return;
         }
-        // At bottom of visible window: try to scroll within current buffer first
-        let buffer_end = self.buffer_offset.saturating_add(self.buffer_rows.len());
-        if self
-            .global_row_offset
-            .saturating_add(self.sel_row)
-            .saturating_add(1)
-            < buffer_end
-        {
-            self.global_row_offset = self.global_row_offset.saturating_add(1);
-            self.view_start = self.view_start.saturating_add(1);
-            let cap = self
-                .visible_rows_per_page
-                .min(self.buffer_rows.len())
-                .max(1);
-            if !self.rows.is_empty() && cap == self.rows.len() {
-                // shift window down by one: drop first and append new row
-                let new_row = self.buffer_rows[self.view_start + cap - 1].clone();
-                self.rows.remove(0);
-                self.rows.push(new_row);
-            } else {
-                // fallback rebuild
-                self.rows = self
-                    .buffer_rows
-                    .iter()
-                    .skip(self.view_start)
-                    .take(cap)
-                    .cloned()
-                    .collect();
-            }
-            // Keep cursor pinned at bottom row
-            self.sel_row = last_visible;
+        if self.refuse_if_read_only("delete rows") {
+            return;
+        }
+        if self.loaded_is_view() {
+            self.status = "Views are read-only".into();
+            return;
+        }
+        let Some(table) = self.loaded_table.clone() else {
+            self.status = "No table loaded".into();
+            return;
+        };
+        let Some(filter) = self.filter.clone().filter(|f| !f.is_empty()) else {
+            self.status =
+                "Delete matching: set a filter first (/) — refusing to empty the table".into();
+            return;
+        };
+        let Some(count) = self.total_rows else {
+            self.status = "Delete matching: row count unknown; reload (r) and try again".into();
+            return;
+        };
+        if count == 0 {
+            self.status = "No rows match the filter".into();
             return;
         }
-        // Need to load next buffer
-        self.global_row_offset = self.global_row_offset.saturating_add(1);
-        self.status = "Loading next page…".into();
-        self.load_selected_table_page(self.global_row_offset / self.page_size);
+        self.begin_prompt(PromptKind::ConfirmDeleteMatching {
+            table,
+            filter,
+            count,
+        });
+    }
+
+    /// Ask for a typed confirmation before dropping the selected table
+    pub fn begin_drop_table(&mut self) {
+        if self.refuse_if_read_only("drop tables") {
+            return;
+        }
+        if self.current_is_view() {
+            self.status = "Select a table (not a view) to drop".into();
+            return;
+        }
+        let Some(table) = self.current_table_name().map(|s| s.to_string()) else {
+            self.status = "No table selected to drop".into();
+            return;
+        };
+        self.begin_prompt(PromptKind::ConfirmDrop { table });
+    }
+
+    /// Ask for a typed confirmation before deleting every row of the selected table
+    pub fn begin_truncate_table(&mut self) {
+        if self.refuse_if_read_only("delete rows") {
+            return;
+        }
+        if self.current_is_view() {
+            self.status = "Select a table (not a view) to truncate".into();
+            return;
+        }
+        let Some(table) = self.current_table_name().map(|s| s.to_string()) else {
+            self.status = "No table selected to truncate".into();
+            return;
+        };
+        self.begin_prompt(PromptKind::ConfirmTruncate { table });
+    }
+
+    /// Name of the selected table or view (None when a header or index is selected)
+    pub fn current_table_name(&self) -> Option<&str> {
+        match self.sidebar_entries().get(self.selected_table)? {
+            SidebarEntry::Table(i) => self.tables.get(*i).map(|s| s.as_str()),
+            SidebarEntry::View(i) => self.views.get(*i).map(|s| s.as_str()),
+            _ => None,
+        }
+    }
+
+    /// The loaded table is FTS5, so `/` runs a MATCH query
+    pub fn loaded_is_fts(&self) -> bool {
+        self.loaded_table
+            .as_ref()
+            .is_some_and(|t| self.fts_tables.contains(t))
+    }
+
+    pub fn current_is_view(&self) -> bool {
+        matches!(
+            self.sidebar_entries().get(self.selected_table),
+            Some(SidebarEntry::View(_))
+        )
+    }
+
+    /// Whether the data pane shows a view; row actions go by this, not the sidebar cursor
+    pub fn loaded_is_view(&self) -> bool {
+        self.loaded_table
+            .as_ref()
+            .is_some_and(|t| self.views.contains(t))
+    }
+
+    /// Flattened sidebar: section headers, optional schema headers, then items
+    pub fn sidebar_entries(&self) -> Vec<SidebarEntry> {
+        let grouped = self.schemas.len() > 1;
+        let mut out = Vec::new();
+        let sections = [
+            (SidebarSection::Tables, self.tables.len()),
+            (SidebarSection::Views, self.views.len()),
+            (SidebarSection::Indexes, self.indexes.len()),
+        ];
+        for (section, len) in sections {
+            out.push(SidebarEntry::Section(section));
+            if self.collapsed_sections.contains(&section) {
+                continue;
+            }
+            for (si, schema) in self.schemas.iter().enumerate() {
+                let mut header_pushed = !grouped;
+                for i in 0..len {
+                    let (name, entry) = match section {
+                        SidebarSection::Tables => (&self.tables[i], SidebarEntry::Table(i)),
+                        SidebarSection::Views => (&self.views[i], SidebarEntry::View(i)),
+                        SidebarSection::Indexes => (&self.indexes[i].name, SidebarEntry::Index(i)),
+                    };
+                    if self.table_schema(name) != schema || !self.sidebar_matches(name) {
+                        continue;
+                    }
+                    if !header_pushed {
+                        out.push(SidebarEntry::Schema(si));
+                        header_pushed = true;
+                    }
+                    out.push(entry);
+                }
+            }
+        }
+        out
+    }
+
+    /// Fuzzy match against the sidebar filter (see `fuzzy_matches`)
+    pub fn sidebar_matches(&self, name: &str) -> bool {
+        fuzzy_matches(&self.sidebar_query, name)
+    }
+
+    pub fn begin_sidebar_query(&mut self) {
+        self.sidebar_query_input = true;
+        self.status = self.sidebar_query_status();
+    }
+
+    pub fn sidebar_query_status(&self) -> String {
+        format!(
+            "Find in sidebar: {}_ (Enter keeps it, Esc clears)",
+            self.sidebar_query
+        )
+    }
+
+    pub fn push_sidebar_query(&mut self, c: char) {
+        self.update_sidebar_query(|q| q.push(c));
+    }
+
+    pub fn pop_sidebar_query(&mut self) {
+        self.update_sidebar_query(|q| {
+            q.pop();
+        });
+    }
+
+    pub fn clear_sidebar_query(&mut self) {
+        self.sidebar_query_input = false;
+        self.update_sidebar_query(String::clear);
+    }
+
+    /// Change the query, keeping the selected entry if it still matches and otherwise
+    /// selecting the first match
+    fn update_sidebar_query(&mut self, edit: impl FnOnce(&mut String)) {
+        let before = self.sidebar_entries().get(self.selected_table).copied();
+        edit(&mut self.sidebar_query);
+        let entries = self.sidebar_entries();
+        self.selected_table = before
+            .and_then(|b| entries.iter().position(|e| *e == b))
+            .filter(|_| !matches!(before, Some(SidebarEntry::Section(_))))
+            .or_else(|| {
+                entries.iter().position(|e| {
+                    matches!(
+                        e,
+                        SidebarEntry::Table(_) | SidebarEntry::View(_) | SidebarEntry::Index(_)
+                    )
+                })
+            })
+            .unwrap_or(0);
+    }
+
+    /// Move the sidebar selection to a table or view by name (expanding its section)
+    pub fn select_table_by_name(&mut self, name: &str) -> bool {
+        let section = if self.tables.iter().any(|t| t == name) {
+            SidebarSection::Tables
+        } else if self.views.iter().any(|v| v == name) {
+            SidebarSection::Views
+        } else {
+            return false;
+        };
+        self.collapsed_sections.remove(&section);
+        let pos = self.sidebar_entries().iter().position(|e| match e {
+            SidebarEntry::Table(i) => self.tables[*i] == name,
+            SidebarEntry::View(i) => self.views[*i] == name,
+            _ => false,
+        });
+        match pos {
+            Some(p) => {
+                self.selected_table = p;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Enter on the sidebar: open tables/views, toggle sections, describe indexes
+    pub fn activate_sidebar_selection(&mut self) {
+        match self.sidebar_entries().get(self.selected_table).copied() {
+            Some(SidebarEntry::Section(section)) => {
+                if !self.collapsed_sections.remove(&section) {
+                    self.collapsed_sections.insert(section);
+                }
+            }
+            Some(SidebarEntry::Table(_)) | Some(SidebarEntry::View(_)) => {
+                self.query_view = None;
+                self.load_selected_table_page(0)
+            }
+            Some(SidebarEntry::Index(i)) => {
+                if let Some(idx) = self.indexes.get(i) {
+                    self.status = format!(
+                        "Index {} on {}: {}",
+                        idx.name,
+                        idx.table,
+                        idx.sql.as_deref().unwrap_or("(implicit)")
+                    );
+                }
+            }
+            Some(SidebarEntry::Schema(_)) | None => {}
+        }
+    }
+
+    fn page_key(&self, table: String, offset: usize) -> PageKey {
+        PageKey {
+            table,
+            offset,
+            page_size: self.page_size,
+            filter: self.filter.clone(),
+            sort_by: self.sort_by.clone(),
+            sort_dir: self.sort_dir,
+            approx_count: self.approx_count,
+        }
+    }
+
+    /// Ask the worker for the page at `offset` unless it is already cached
+    fn prefetch_page(&mut self, offset: usize) {
+        let Some(table) = self.loaded_table.clone() else {
+            return;
+        };
+        let key = self.page_key(table, offset);
+        if !self.page_cache.iter().any(|(k, _)| *k == key) {
+            let _ = self.req_tx.send(DBRequest::PrefetchPage(key));
+        }
+    }
+
+    pub fn load_selected_table_page(&mut self, page: usize) {
+        // Query results are one unpaged buffer; scrolling past it or reloading waits for Esc
+        if self.query_view.is_some() {
+            self.global_row_offset = self.buffer_offset + self.view_start;
+            self.status = "Showing query results (Esc returns to the table)".into();
+            return;
+        }
+        if let Some(table) = self.current_table_name().map(|s| s.to_string()) {
+            // Keep existing global_row_offset (smooth scroll base); do not reset on reloads
+            self.last_requested_offset = self.global_row_offset;
+            if self.loaded_table.as_ref() != Some(&table) {
+                self.edited_cells.clear();
+                self.col_scroll = 0;
+                self.previous_values.clear();
+                let _ = self.req_tx.send(DBRequest::PreviousValues {
+                    table: table.clone(),
+                });
+            }
+            if self.loaded_table.as_ref() != Some(&table)
+                && let Some(g) = self.group_view.take()
+            {
+                // Another table: its rows, not the old table's groups
+                (self.sort_by, self.sort_dir) = g.saved_sort;
+            }
+            if let Some(g) = &self.group_view {
+                let key = self.page_key(table, self.global_row_offset);
+                let _ = self.req_tx.send(DBRequest::GroupByCount {
+                    key,
+                    column: g.column.clone(),
+                });
+                self.status = "Counting groups...".into();
+                return;
+            }
+            // Show a prefetched page right away; the load below still refreshes it
+            let key = self.page_key(table.clone(), self.global_row_offset);
+            let cached = self
+                .page_cache
+                .iter()
+                .position(|(k, _)| *k == key)
+                .and_then(|i| self.page_cache.remove(i));
+            let hit = cached.is_some();
+            if let Some((_, data)) = cached {
+                self.handle_db_response(data);
+            }
+            let _ = self.req_tx.send(DBRequest::LoadTable {
+                table,
+                page,
+                page_size: self.page_size,
+                offset_override: Some(self.global_row_offset),
+                filter: self.filter.clone(),
+                sort_by: self.sort_by.clone(),
+                sort_dir: self.sort_dir,
+                approx_count: self.approx_count,
+            });
+            if !hit {
+                self.status = "Loading table...".into();
+                self.table_loading = Some(std::time::Instant::now());
+            }
+        }
+    }
+
+    fn reload_for_external_change(&mut self) {
+        self.external_change_deferred = false;
+        self.external_reload_pending = true;
+        self.request_schema_refresh();
+        if !self.columns.is_empty() {
+            self.reload_current_table();
+        }
+    }
+
+    /// Reload the page; the selection follows its row (by rowid) if the row is still loaded
+    pub fn reload_current_table(&mut self) {
+        if self.pending_select_rowid.is_none() {
+            self.pending_select_rowid = self.visible_rowid(self.sel_row);
+        }
+        self.load_selected_table_page(self.page);
+    }
+
+    // Select the row with `rowid` if the buffer holds it, sliding the window to show it;
+    // otherwise the selection stays at its index
+    fn select_buffered_rowid(&mut self, rowid: i64, cap: usize) {
+        let key = rowid.to_string();
+        let Some(idx) = self
+            .buffer_rows
+            .iter()
+            .position(|row| row.first() == Some(&key))
+        else {
+            return;
+        };
+        if idx < self.view_start || idx >= self.view_start + cap {
+            self.view_start = if idx < self.view_start {
+                idx
+            } else {
+                idx + 1 - cap
+            };
+            self.global_row_offset = self.buffer_offset + self.view_start;
+            self.rows = self
+                .buffer_rows
+                .iter()
+                .skip(self.view_start)
+                .take(cap)
+                .cloned()
+                .collect();
+        }
+        self.sel_row = idx - self.view_start;
+    }
+
+    /// r: reload from the database and forget the edit markers
+    pub fn refresh_current_table(&mut self) {
+        self.edited_cells.clear();
+        self.reload_current_table();
+    }
+
+    pub fn move_table_selection_up(&mut self) {
+        let len = self.sidebar_entries().len();
+        if len == 0 {
+            return;
+        }
+        if self.selected_table == 0 {
+            self.selected_table = len - 1;
+        } else {
+            self.selected_table = min(self.selected_table - 1, len - 1);
+        }
+    }
+
+    pub fn move_table_selection_down(&mut self) {
+        let len = self.sidebar_entries().len();
+        if len == 0 {
+            return;
+        }
+        self.selected_table = (self.selected_table + 1) % len;
+    }
+
+    pub fn next_page(&mut self) {
+        // Jump by a full page: advance the smooth-scroll base accordingly
+        self.global_row_offset = (self.page + 1).saturating_mul(self.page_size);
+        self.load_selected_table_page(self.page + 1);
+    }
+
+    pub fn prev_page(&mut self) {
+        if self.page > 0 {
+            // Jump back by a full page: move the smooth-scroll base accordingly
+            self.global_row_offset = (self.page - 1).saturating_mul(self.page_size);
+            self.load_selected_table_page(self.page - 1);
+        }
+    }
+
+    pub fn first_page(&mut self) {
+        self.global_row_offset = 0;
+        self.sel_row = 0;
+        self.load_selected_table_page(0);
+    }
+
+    /// Number of pages, when the row count is known
+    pub fn page_count(&self) -> Option<usize> {
+        self.total_rows
+            .map(|total| total.div_ceil(self.page_size.max(1)).max(1))
+    }
+
+    /// #: switch between exact row counts and estimates for big tables
+    pub fn toggle_approx_count(&mut self) {
+        self.approx_count = !self.approx_count;
+        self.page_cache.clear();
+        self.reload_current_table();
+        self.status = if self.approx_count {
+            "Row counts: estimated for big unfiltered tables (# for exact)".into()
+        } else {
+            "Row counts: exact (COUNT(*))".into()
+        };
+    }
+
+    /// g: ask for a page number
+    pub fn begin_goto_page(&mut self) {
+        if self.current_table_name().is_none() {
+            return;
+        }
+        self.begin_prompt(PromptKind::GotoPage);
+        self.status = match self.page_count() {
+            Some(pages) => format!("Go to page: 1–{} and Enter", pages),
+            None => "Go to page: type a page number and Enter (row count unknown)".into(),
+        };
+    }
+
+    /// The table's columns as the data pane shows them, when `table` is the one loaded;
+    /// exports write these (__rowid__ is the export prompt's own choice)
+    pub fn export_columns(&self, table: &str) -> Option<Vec<String>> {
+        if self.loaded_table.as_deref() != Some(table) || self.group_view.is_some() {
+            return None;
+        }
+        Some(
+            self.columns
+                .iter()
+                .filter(|c| *c != "__rowid__")
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// f: ask for a column to jump to
+    pub fn begin_goto_column(&mut self) {
+        if self.columns.len() <= self.first_visible_col() {
+            return;
+        }
+        self.begin_prompt(PromptKind::GotoColumn);
+    }
+
+    /// Select the best match for `query` among the shown columns; ties go to the leftmost
+    pub fn goto_column(&mut self, query: &str) {
+        let first = self.first_visible_col();
+        let ranked: Vec<(u8, usize)> = self
+            .columns
+            .iter()
+            .enumerate()
+            .skip(first)
+            .filter_map(|(i, c)| fuzzy_rank(query, c).map(|rank| (rank, i)))
+            .collect();
+        let Some(&(best, col)) = ranked.iter().min_by_key(|(rank, i)| (*rank, *i)) else {
+            self.status = format!("No column matches '{}'", query);
+            return;
+        };
+        self.sel_col = col;
+        // Scrolling left happens here; the draw slides right as far as it needs to
+        self.col_scroll = self.col_scroll.min(col);
+        let others = ranked.iter().filter(|(rank, _)| *rank == best).count() - 1;
+        self.status = match others {
+            0 => format!("Column {}", self.columns[col]),
+            n => format!(
+                "Column {} (first of {} equally good matches for '{}')",
+                self.columns[col],
+                n + 1,
+                query
+            ),
+        };
+    }
+
+    /// Load a 1-based page, clamped to the last page when the row count is known
+    pub fn goto_page(&mut self, page: usize) {
+        let page = match self.page_count() {
+            Some(pages) => page.clamp(1, pages),
+            None => page.max(1),
+        };
+        self.global_row_offset = (page - 1).saturating_mul(self.page_size);
+        self.sel_row = 0;
+        self.load_selected_table_page(page - 1);
+    }
+
+    /// Jump to the end: a window ending on the last row, with that row selected
+    pub fn last_page(&mut self) {
+        let Some(total) = self.total_rows else {
+            self.status = "End: the row count is unknown; use PageDown".into();
+            return;
+        };
+        if self.total_estimated {
+            self.status = "End: the row count is estimated (# counts exactly); use PageDown".into();
+            return;
+        }
+        if total == 0 {
+            return;
+        }
+        let cap = self.visible_rows_per_page.max(1);
+        self.global_row_offset = total.saturating_sub(cap);
+        self.select_last_row_on_load = true;
+        self.load_selected_table_page((total - 1) / self.page_size.max(1));
+    }
+
+    // P0: Filter helpers
+    pub fn set_filter_string(&mut self, filter: Option<String>) {
+        self.filter = filter;
+        self.page_cache.clear();
+        self.sel_anchor_row = None;
+        self.global_row_offset = 0;
+        // Reset to first page when filter changes
+        self.load_selected_table_page(0);
+    }
+
+    pub fn clear_filter(&mut self) {
+        self.set_filter_string(None);
+    }
+
+    // Inline filter input state (for visible entry in UI)
+    pub fn begin_filter_input(&mut self) {
+        self.filter_input = Some(String::new());
+    }
+
+    pub fn update_filter_input_char(&mut self, c: char) {
+        if let Some(buf) = self.filter_input.as_mut() {
+            buf.push(c);
+        }
+    }
+
+    /// Remove the last grapheme, so "é" typed as e + combining accent goes in one step
+    pub fn backspace_filter_input(&mut self) {
+        if let Some(buf) = self.filter_input.as_mut() {
+            let end = prev_grapheme(buf, buf.len());
+            buf.truncate(end);
+        }
+    }
+
+    pub fn apply_filter_input(&mut self) {
+        let pending = self.filter_input.take();
+        match pending {
+            Some(s) if !s.is_empty() => self.set_filter_string(Some(s)),
+            _ => self.clear_filter(),
+        }
+    }
+
+    /// How the applied filter was parsed, when it is a compound one (or names an unknown
+    /// column); FTS5 tables pass the filter to MATCH untouched
+    pub fn filter_summary(&self) -> Option<String> {
+        if self.loaded_is_fts() {
+            return None;
+        }
+        let cols_only: Vec<String> = self
+            .columns
+            .iter()
+            .filter(|c| *c != "__rowid__")
+            .cloned()
+            .collect();
+        crate::db::describe_filter(&cols_only, self.filter.as_deref()?)
+    }
+
+    /// Ctrl+s in the filter prompt: add or remove the `cs:` (case-sensitive) prefix
+    pub fn toggle_filter_case(&mut self) {
+        let Some(buf) = self.filter_input.as_mut() else {
+            return;
+        };
+        if let Some(rest) = buf.strip_prefix(CASE_SENSITIVE_PREFIX) {
+            *buf = rest.to_string();
+        } else {
+            buf.insert_str(0, CASE_SENSITIVE_PREFIX);
+        }
+    }
+
+    pub fn cancel_filter_input(&mut self) {
+        self.filter_input = None;
+    }
+
+    /// `:` opens the query bar, starting from the query shown (if any) to tweak it
+    pub fn begin_query_input(&mut self) {
+        self.query_input = Some(self.query_view.clone().unwrap_or_default());
+        self.query_completion = None;
+        self.status = "Query: SELECT / WITH, Enter to run (Esc to cancel)".into();
+    }
+
+    pub fn update_query_input_char(&mut self, c: char) {
+        self.query_completion = None;
+        if let Some(buf) = self.query_input.as_mut() {
+            buf.push(c);
+        }
+    }
+
+    pub fn backspace_query_input(&mut self) {
+        self.query_completion = None;
+        if let Some(buf) = self.query_input.as_mut() {
+            let end = prev_grapheme(buf, buf.len());
+            buf.truncate(end);
+        }
+    }
+
+    /// Tab in the `:` bar: complete the word before the cursor to a table name, or a column
+    /// of a table the query mentions (`t.` completes only t's columns); Tab again cycles
+    pub fn complete_query_input(&mut self) {
+        let Some(buf) = self.query_input.as_mut() else {
+            return;
+        };
+        if let Some(c) = self.query_completion.as_mut() {
+            c.index = (c.index + 1) % c.candidates.len();
+            buf.truncate(c.start);
+            buf.push_str(&c.candidates[c.index]);
+            self.status = format!(
+                "Query: {}_   ({}/{}, Tab for the next)",
+                buf,
+                c.index + 1,
+                c.candidates.len()
+            );
+            return;
+        }
+        let start = word_start(buf, buf.len());
+        let prefix = buf[start..].to_lowercase();
+        let qualifier = buf[..start]
+            .strip_suffix('.')
+            .map(|before| &before[word_start(before, before.len())..]);
+        let mentioned: Vec<String> = match qualifier {
+            Some(q) => self
+                .tables
+                .iter()
+                .filter(|t| t.eq_ignore_ascii_case(q))
+                .cloned()
+                .collect(),
+            None => {
+                let words: HashSet<String> = query_words(buf).map(str::to_lowercase).collect();
+                self.tables
+                    .iter()
+                    .filter(|t| words.contains(&t.to_lowercase()))
+                    .cloned()
+                    .collect()
+            }
+        };
+        let mut loading = false;
+        for table in &mentioned {
+            if !self.query_columns.contains_key(table) {
+                loading = true;
+                // Mark it requested; the response fills it in
+                self.query_columns.insert(table.clone(), Vec::new());
+                let _ = self.req_tx.send(DBRequest::TableColumns {
+                    table: table.clone(),
+                });
+            }
+        }
+        let mut names: Vec<&String> = mentioned
+            .iter()
+            .flat_map(|t| &self.query_columns[t])
+            .collect();
+        if qualifier.is_none() {
+            names.extend(&self.tables);
+        }
+        let mut candidates: Vec<String> = names
+            .into_iter()
+            .filter(|n| n.to_lowercase().starts_with(&prefix))
+            .map(|n| {
+                let plain = n.chars().next().is_some_and(|c| !c.is_ascii_digit())
+                    && n.chars().all(is_name_char);
+                if plain { n.clone() } else { ident(n) }
+            })
+            .collect();
+        candidates.sort_by_key(|n| n.to_lowercase());
+        candidates.dedup();
+        if candidates.is_empty() {
+            self.status = if loading {
+                "Loading column names... (Tab again)".into()
+            } else {
+                format!("Query: {}_   (no table or column starts with that)", buf)
+            };
+            return;
+        }
+        buf.truncate(start);
+        buf.push_str(&candidates[0]);
+        self.status = if candidates.len() > 1 {
+            format!(
+                "Query: {}_   (1/{}, Tab for the next)",
+                buf,
+                candidates.len()
+            )
+        } else {
+            format!("Query: {}_", buf)
+        };
+        self.query_completion = Some(QueryCompletion {
+            start,
+            candidates,
+            index: 0,
+        });
+    }
+
+    pub fn cancel_query_input(&mut self) {
+        self.query_input = None;
+        self.query_completion = None;
+        self.status = "Query cancelled".into();
+    }
+
+    pub fn submit_query_input(&mut self) {
+        self.query_completion = None;
+        let sql = self.query_input.take().unwrap_or_default();
+        let sql = sql.trim();
+        if sql.is_empty() {
+            self.status = "Query cancelled".into();
+            return;
+        }
+        let _ = self.req_tx.send(DBRequest::RunQuery {
+            sql: sql.to_string(),
+            limit: self.result_limit,
+        });
+        self.status = "Running query...".into();
+        self.table_loading = Some(std::time::Instant::now());
+    }
+
+    /// Esc on query results: back to the table they replaced
+    pub fn close_query_view(&mut self) {
+        self.query_view = None;
+        if self.loaded_table.is_some() {
+            self.global_row_offset = 0;
+            self.sel_row = 0;
+            self.reload_current_table();
+        } else {
+            self.clear_data_view();
+            self.status = "Query results closed".into();
+        }
+    }
+
+    // Help overlay toggle
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+    }
+
+    // P0: Sort helpers
+    // Cycle sort for the currently selected column: None -> ASC -> DESC -> None
+    pub fn sort_cycle_on_selection(&mut self) {
+        if self.columns.is_empty() {
+            return;
+        }
+        self.page_cache.clear();
+        let col_name = self.columns[self.sel_col].clone();
+        // Staying on the same column keeps numeric ordering
+        let numeric = self.sort_column() == Some(col_name.as_str()) && self.sort_is_numeric();
+        self.sort_by = Some(if numeric {
+            format!("{}{}", NUMERIC_SORT_PREFIX, col_name)
+        } else {
+            col_name
+        });
+        self.sort_dir = match self.sort_dir {
+            None => Some(SortDir::Asc),
+            Some(SortDir::Asc) => Some(SortDir::Desc),
+            Some(SortDir::Desc) => None,
+        };
+        self.sel_anchor_row = None;
+        self.reload_current_table();
+    }
+
+    /// Mouse click at (x, y): a click on a column header sorts by that column, ascending;
+    /// clicking the sorted column again flips the direction
+    pub fn click_at(&mut self, x: u16, y: u16) {
+        let Some(col) = self
+            .header_hits
+            .iter()
+            .find(|(row, xs, _)| *row == y && xs.contains(&x))
+            .map(|(_, _, col)| *col)
+        else {
+            return;
+        };
+        self.focus = Focus::Data;
+        self.sel_col = col;
+        if self.sort_column() == self.columns.get(col).map(|c| c.as_str()) {
+            self.sort_toggle_dir();
+        } else {
+            self.sort_dir = None;
+            self.sort_cycle_on_selection();
+        }
+    }
+
+    /// Column the rows are sorted by, without the numeric prefix
+    pub fn sort_column(&self) -> Option<&str> {
+        self.sort_by
+            .as_deref()
+            .map(|s| crate::db::split_numeric_sort(s).0)
+    }
+
+    pub fn sort_is_numeric(&self) -> bool {
+        self.sort_by
+            .as_deref()
+            .is_some_and(|s| crate::db::split_numeric_sort(s).1)
+    }
+
+    /// n: sort the current sort column as numbers or back as stored values
+    pub fn toggle_numeric_sort(&mut self) {
+        let Some(col) = self.sort_column().map(|c| c.to_string()) else {
+            self.status = "Sort by a column first (s), then n sorts it numerically".into();
+            return;
+        };
+        if self.refuse_if_grouped("sort numerically") {
+            return;
+        }
+        let numeric = !self.sort_is_numeric();
+        self.sort_by = Some(if numeric {
+            format!("{}{}", NUMERIC_SORT_PREFIX, col)
+        } else {
+            col.clone()
+        });
+        self.page_cache.clear();
+        self.sel_anchor_row = None;
+        self.status = if numeric {
+            format!("Sorting {} as numbers", col)
+        } else {
+            format!("Sorting {} as stored", col)
+        };
+        self.reload_current_table();
+    }
+
+    // Explicitly toggle sort direction (defaults to ASC when not set)
+    pub fn sort_toggle_dir(&mut self) {
+        self.page_cache.clear();
+        self.sort_dir = match self.sort_dir {
+            Some(SortDir::Asc) => Some(SortDir::Desc),
+            _ => Some(SortDir::Asc),
+        };
+        self.sel_anchor_row = None;
+        self.reload_current_table();
+    }
+
+    pub fn move_cell_left(&mut self) {
+        if self.columns.is_empty() {
+            return;
+        }
+        self.sel_col = self.sel_col.saturating_sub(1).max(self.first_visible_col());
+        // Scroll left onto it
+        self.col_scroll = self.col_scroll.min(self.sel_col);
+    }
+
+    pub fn move_cell_right(&mut self) {
+        if self.columns.is_empty() {
+            return;
+        }
+        self.sel_col = min(self.sel_col + 1, self.columns.len().saturating_sub(1));
+        // Scroll right until it fits, with the widths of the last frame
+        if self.columns_width > 0 {
+            self.col_scroll = self.shown_columns(self.columns_width).0;
+        }
+    }
+
+    /// First column drawn and how many fit in `width` (see `column_window`)
+    pub fn shown_columns(&self, width: u16) -> (usize, usize) {
+        column_window(
+            self.first_visible_col(),
+            self.columns.len(),
+            self.sel_col,
+            self.col_scroll,
+            &self.col_abs_widths,
+            width,
+        )
+    }
+
+    pub fn move_cell_up(&mut self) {
+        if self.sel_row > 0 {
+            self.sel_row = self.sel_row.saturating_sub(1);
+            return;
+        }
+        // At top of visible window: try to scroll within current buffer first
+        if self.global_row_offset > self.buffer_offset {
+            self.global_row_offset = self.global_row_offset.saturating_sub(1);
+            self.view_start = self.view_start.saturating_sub(1);
+            let cap = self
+                .visible_rows_per_page
+                .min(self.buffer_rows.len())
+                .max(1);
+            if !self.rows.is_empty() && cap == self.rows.len() {
+                // shift window up by one: prepend new row and drop last
+                let new_row = self.buffer_rows[self.view_start].clone();
+                self.rows.pop();
+                self.rows.insert(0, new_row);
+            } else {
+                // fallback rebuild
+                self.rows = self
+                    .buffer_rows
+                    .iter()
+                    .skip(self.view_start)
+                    .take(cap)
+                    .cloned()
+                    .collect();
+            }
+            // Keep cursor at top
+            self.sel_row = 0;
+            return;
+        }
+        // Need to load previous buffer
+        if self.global_row_offset > 0 {
+            self.global_row_offset = self.global_row_offset.saturating_sub(1);
+            self.select_last_row_on_load = true;
+            self.status = "Loading previous page…".into();
+            self.load_selected_table_page(self.global_row_offset / self.page_size);
+        }
+    }
+
+    pub fn move_cell_down(&mut self) {
+        let last_visible = self
+            .visible_rows_per_page
+            .min(self.rows.len())
+            .saturating_sub(1);
+        if self.sel_row < last_visible {
+            self.sel_row = min(self.sel_row + 1, last_visible);
+            return;
+        }
+        // At bottom of visible window: try to scroll within current buffer first
+        let buffer_end = self.buffer_offset.saturating_add(self.buffer_rows.len());
+        if self
+            .global_row_offset
+            .saturating_add(self.sel_row)
+            .saturating_add(1)
+            < buffer_end
+        {
+            self.global_row_offset = self.global_row_offset.saturating_add(1);
+            self.view_start = self.view_start.saturating_add(1);
+            let cap = self
+                .visible_rows_per_page
+                .min(self.buffer_rows.len())
+                .max(1);
+            if !self.rows.is_empty() && cap == self.rows.len() {
+                // shift window down by one: drop first and append new row
+                let new_row = self.buffer_rows[self.view_start + cap - 1].clone();
+                self.rows.remove(0);
+                self.rows.push(new_row);
+            } else {
+                // fallback rebuild
+                self.rows = self
+                    .buffer_rows
+                    .iter()
+                    .skip(self.view_start)
+                    .take(cap)
+                    .cloned()
+                    .collect();
+            }
+            // Keep cursor pinned at bottom row
+            self.sel_row = last_visible;
+            return;
+        }
+        // Need to load next buffer
+        self.global_row_offset = self.global_row_offset.saturating_add(1);
+        self.status = "Loading next page…".into();
+        self.load_selected_table_page(self.global_row_offset / self.page_size);
+    }
+
+    // ===== Overlays =====
+
+    pub fn close_overlay(&mut self) {
+        self.overlay = None;
+    }
+
+    /// Up/Down in an overlay: move the picker selection or scroll the text
+    pub fn move_overlay_selection(&mut self, down: bool) {
+        let (pos, len) = match self.overlay.as_mut() {
+            Some(Overlay::DistinctValues {
+                values, selected, ..
+            }) => (selected, values.len()),
+            Some(Overlay::SavedViews { views, selected }) => (selected, views.len()),
+            Some(Overlay::Info { lines, scroll, .. }) => (scroll, lines.len()),
+            _ => return,
+        };
+        *pos = if down {
+            (*pos + 1).min(len.saturating_sub(1))
+        } else {
+            pos.saturating_sub(1)
+        };
+    }
+
+    /// Enter in an overlay: apply the picked value as a `column=value` filter, or the
+    /// picked saved view
+    pub fn accept_overlay(&mut self) {
+        let Some(overlay) = self.overlay.take() else {
+            return;
+        };
+        if let Overlay::SavedViews { views, selected } = overlay {
+            if let Some(view) = views.into_iter().nth(selected) {
+                self.apply_saved_view(view);
+            }
+            return;
+        }
+        if let Overlay::DistinctValues {
+            table,
+            column,
+            values,
+            selected,
+            ..
+        } = overlay
+            && self.loaded_table.as_deref() == Some(table.as_str())
+            && let Some((value, _)) = values.get(selected)
+        {
+            let filter = format!("{}={}", column, value.as_deref().unwrap_or("NULL"));
+            self.status = format!("Filter applied: {}", filter);
+            self.set_filter_string(Some(filter));
+        }
+    }
+
+    // ===== Saved views =====
+
+    /// w: list the saved views of this database
+    pub fn open_saved_views(&mut self) {
+        let views = views::load(&self.views_key);
+        self.status = if views.is_empty() {
+            "No saved views yet — s saves the current filter and sort".into()
+        } else {
+            format!(
+                "{} saved view(s) — Enter apply, s save current, d delete",
+                views.len()
+            )
+        };
+        self.overlay = Some(Overlay::SavedViews { views, selected: 0 });
+    }
+
+    /// s in the saved views list: name the current filter and sort
+    pub fn begin_save_view(&mut self) {
+        self.overlay = None;
+        if self.refuse_if_grouped("save a view") {
+            return;
+        }
+        if self.loaded_table.is_none() {
+            self.status = "Open a table first".into();
+            return;
+        }
+        self.begin_prompt(PromptKind::SaveView);
+    }
+
+    fn save_current_view(&mut self, name: String) {
+        let Some(table) = self.loaded_table.clone() else {
+            return;
+        };
+        let view = SavedView {
+            name,
+            table,
+            filter: self.filter.clone(),
+            sort_by: self.sort_by.clone(),
+            sort_dir: self.sort_dir,
+        };
+        self.status = match views::save(&self.views_key, &view) {
+            Ok(()) => format!("Saved view '{}' (w lists saved views)", view.name),
+            Err(e) => format!("Could not save view: {}", e),
+        };
+    }
+
+    /// d in the saved views list: delete the selected view
+    pub fn delete_selected_view(&mut self) {
+        let Some(Overlay::SavedViews { views, selected }) = self.overlay.as_mut() else {
+            return;
+        };
+        if *selected >= views.len() {
+            return;
+        }
+        let view = views.remove(*selected);
+        *selected = (*selected).min(views.len().saturating_sub(1));
+        self.status = match views::delete(&self.views_key, &view.name) {
+            Ok(()) => format!("Deleted view '{}'", view.name),
+            Err(e) => format!("Could not delete view: {}", e),
+        };
+    }
+
+    /// Open the view's table with its filter and sort
+    fn apply_saved_view(&mut self, view: SavedView) {
+        if self.refuse_if_grouped("apply a view") {
+            return;
+        }
+        if !self.select_table_by_name(&view.table) {
+            self.status = format!("View '{}': table {} not found", view.name, view.table);
+            return;
+        }
+        self.filter = view.filter;
+        self.sort_by = view.sort_by;
+        self.sort_dir = view.sort_dir;
+        self.page_cache.clear();
+        self.sel_anchor_row = None;
+        self.global_row_offset = 0;
+        self.sel_row = 0;
+        self.load_selected_table_page(0);
+        self.status = format!("View '{}' applied", view.name);
+    }
+
+    /// Open the value picker for the selected column
+    pub fn request_distinct_values(&mut self) {
+        if self.refuse_if_grouped("pick values") {
+            return;
+        }
+        let Some(table) = self.loaded_table.clone() else {
+            self.status = "No table loaded".into();
+            return;
+        };
+        let column = match self.columns.get(self.sel_col) {
+            Some(c) if c != "__rowid__" => c.clone(),
+            _ => {
+                self.status = "Distinct values: pick a data column".into();
+                return;
+            }
+        };
+        self.distinct_limit = self.result_limit;
+        let _ = self.req_tx.send(DBRequest::DistinctValues {
+            table,
+            column: column.clone(),
+            limit: self.distinct_limit,
+        });
+        self.status = format!("Loading distinct values of {}...", column);
+    }
+
+    /// + in the distinct-values picker: load ten times as many values
+    pub fn raise_distinct_limit(&mut self) {
+        let Some(Overlay::DistinctValues {
+            table,
+            column,
+            values,
+            total,
+            limit,
+            ..
+        }) = &self.overlay
+        else {
+            return;
+        };
+        if *total <= values.len() {
+            self.status = format!("All {} distinct values are shown", total);
+            return;
+        }
+        self.distinct_limit = limit.saturating_mul(10);
+        let _ = self.req_tx.send(DBRequest::DistinctValues {
+            table: table.clone(),
+            column: column.clone(),
+            limit: self.distinct_limit,
+        });
+        self.status = format!(
+            "Loading up to {} distinct values of {}...",
+            group_thousands(self.distinct_limit as i64),
+            column
+        );
+    }
+
+    pub fn request_integrity_check(&mut self) {
+        let _ = self.req_tx.send(DBRequest::IntegrityCheck);
+        self.status = "Checking integrity…".into();
+    }
+
+    /// Ask the worker for statistics of the selected column (respecting the filter)
+    pub fn request_column_stats(&mut self) {
+        if self.refuse_if_grouped("show column stats") {
+            return;
+        }
+        let Some(table) = self.loaded_table.clone() else {
+            self.status = "No table loaded".into();
+            return;
+        };
+        let Some(column) = self.columns.get(self.sel_col).cloned() else {
+            return;
+        };
+        let _ = self.req_tx.send(DBRequest::ColumnStats {
+            table,
+            column: column.clone(),
+            filter: self.filter.clone(),
+        });
+        self.status = format!("Computing stats for {}...", column);
+    }
+
+    // ===== Bookmarks =====
+
+    // Rowid of a visible row (None for rows without one, e.g. the grouped view)
+    fn visible_rowid(&self, row: usize) -> Option<i64> {
+        self.rows
+            .get(row)
+            .and_then(|r| r.first())
+            .and_then(|s| s.parse::<i64>().ok())
+    }
+
+    pub fn is_bookmarked(&self, row: usize) -> bool {
+        match (self.loaded_table.as_ref(), self.visible_rowid(row)) {
+            (Some(table), Some(rowid)) => self.bookmarks.contains(&(table.clone(), rowid)),
+            _ => false,
+        }
+    }
+
+    fn request_previous_values(&self) {
+        if let Some(table) = self.loaded_table.clone() {
+            let _ = self.req_tx.send(DBRequest::PreviousValues { table });
+        }
+    }
+
+    /// What the selected cell held before its latest undoable change, if it has one
+    pub fn selected_previous_value(&self) -> Option<&Option<String>> {
+        let rowid = self.visible_rowid(self.sel_row)?;
+        let column = self.columns.get(self.sel_col)?;
+        self.previous_values.get(&(rowid, column.clone()))
+    }
+
+    /// The visible cell was edited this session
+    pub fn is_edited_cell(&self, row: usize, col: usize) -> bool {
+        match (self.visible_rowid(row), self.columns.get(col)) {
+            (Some(rowid), Some(column)) => self.edited_cells.contains(&(rowid, column.clone())),
+            _ => false,
+        }
+    }
+
+    /// Bookmarked rowids of the loaded table, ascending by rowid
+    fn table_bookmarks(&self) -> Vec<i64> {
+        let Some(table) = self.loaded_table.as_deref() else {
+            return Vec::new();
+        };
+        let mut ids: Vec<i64> = self
+            .bookmarks
+            .iter()
+            .filter(|(t, _)| t == table)
+            .map(|(_, id)| *id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    pub fn table_has_bookmarks(&self) -> bool {
+        self.loaded_table
+            .as_deref()
+            .is_some_and(|table| self.bookmarks.iter().any(|(t, _)| t == table))
+    }
+
+    /// m: bookmark the selected row, or remove its bookmark
+    pub fn toggle_bookmark(&mut self) {
+        let (Some(table), Some(rowid)) =
+            (self.loaded_table.clone(), self.visible_rowid(self.sel_row))
+        else {
+            self.status = "No row to bookmark".into();
+            return;
+        };
+        let key = (table, rowid);
+        let verb = if self.bookmarks.remove(&key) {
+            "Removed bookmark on"
+        } else {
+            self.bookmarks.insert(key);
+            "Bookmarked"
+        };
+        self.status = format!(
+            "{} row {} ({} in this table; ' next, \" previous)",
+            verb,
+            rowid,
+            self.table_bookmarks().len()
+        );
+    }
+
+    /// '/": jump to the bookmarked row shown after (or before) the selected one, in the
+    /// current filter and sort order, wrapping around; the page holding it is loaded as needed
+    pub fn jump_to_bookmark(&mut self, forward: bool) {
+        let rowids = self.table_bookmarks();
+        if rowids.is_empty() {
+            self.status = "No bookmarks in this table (m marks the selected row)".into();
+            return;
+        }
+        let Some(table) = self.loaded_table.clone() else {
+            return;
+        };
+        self.bookmark_jump = true;
+        let _ = self.req_tx.send(DBRequest::LocateBookmark {
+            table,
+            rowids,
+            from: self.cursor_row_abs(),
+            forward,
+            filter: self.filter.clone(),
+            sort_by: self.sort_by.clone(),
+            sort_dir: self.sort_dir,
+        });
+        self.status = format!(
+            "Jumping to the {} bookmarked row...",
+            if forward { "next" } else { "previous" }
+        );
+    }
+
+    // ===== Multi-row selection =====
+
+    /// After a resize: re-project the buffer into a window of the new capacity, keeping the
+    /// selected row selected and on screen. A taller window is filled from `buffer_rows`;
+    /// rows past the end of the buffer are loaded
+    pub fn refit_visible_rows(&mut self) {
+        if self.buffer_rows.is_empty() {
+            return;
+        }
+        let cap = self
+            .visible_rows_per_page
+            .min(self.buffer_rows.len())
+            .max(1);
+        let selected = min(self.view_start + self.sel_row, self.buffer_rows.len() - 1);
+        let buffer_end = self.buffer_offset + self.buffer_rows.len();
+        let more_after_buffer = self
+            .total_rows
+            .map_or(self.buffer_rows.len() == self.page_size, |t| buffer_end < t);
+        if self.view_start + cap > self.buffer_rows.len() && more_after_buffer {
+            // Load a buffer that starts at the window; the load keeps sel_row
+            self.load_selected_table_page(self.global_row_offset / self.page_size.max(1));
+            return;
+        }
+        let mut view_start = min(self.view_start, self.buffer_rows.len().saturating_sub(cap));
+        if selected >= view_start + cap {
+            view_start = selected + 1 - cap;
+        }
+        self.view_start = view_start;
+        self.global_row_offset = self.buffer_offset + view_start;
+        self.rows = self
+            .buffer_rows
+            .iter()
+            .skip(view_start)
+            .take(cap)
+            .cloned()
+            .collect();
+        self.sel_row = selected - view_start;
+    }
+
+    /// Absolute position of the cursor row within the filtered/sorted table
+    pub fn cursor_row_abs(&self) -> usize {
+        self.buffer_offset + self.view_start + self.sel_row
+    }
+
+    /// Inclusive absolute row range covered by the selection, if one is active
+    pub fn selected_row_range(&self) -> Option<(usize, usize)> {
+        let anchor = self.sel_anchor_row?;
+        let cur = self.cursor_row_abs();
+        Some((anchor.min(cur), anchor.max(cur)))
+    }
+
+    /// Shift+Up/Down: start a selection at the cursor (if needed) and move
+    pub fn extend_selection(&mut self, down: bool) {
+        if self.rows.is_empty() {
+            return;
+        }
+        if self.sel_anchor_row.is_none() {
+            self.sel_anchor_row = Some(self.cursor_row_abs());
+        }
+        if down {
+            self.move_cell_down();
+        } else {
+            self.move_cell_up();
+        }
+        if let Some((lo, hi)) = self.selected_row_range() {
+            self.status = format!("Selected {} rows", hi - lo + 1);
+        }
+    }
+
+    pub fn clear_row_selection(&mut self) {
+        self.sel_anchor_row = None;
+    }
+
+    /// Rowid of a row by absolute position, if it is in the loaded buffer
+    fn rowid_at_abs(&self, abs: usize) -> Option<i64> {
+        abs.checked_sub(self.buffer_offset)
+            .and_then(|i| self.buffer_rows.get(i))
+            .and_then(|r| r.first())
+            .and_then(|s| s.parse::<i64>().ok())
+    }
+
+    /// Rowids of the Shift+Up/Down selection; None when nothing is selected or part of it
+    /// is no longer loaded
+    pub fn selected_rowids(&self) -> Option<Vec<i64>> {
+        let (lo, hi) = self.selected_row_range()?;
+        (lo..=hi).map(|abs| self.rowid_at_abs(abs)).collect()
+    }
+
+    pub fn is_generated_column(&self, col: usize) -> bool {
+        self.column_generated.get(col).copied().unwrap_or(false)
+    }
+
+    /// Copy the anchored cell's value down (or up) across the selected rows
+    pub fn fill_down_selection(&mut self) {
+        if self.refuse_if_grouped("fill down") {
+            return;
+        }
+        if self.refuse_if_read_only("fill down") {
+            return;
+        }
+        if self.loaded_is_view() {
+            self.status = "Views are read-only".into();
+            return;
+        }
+        let (Some(anchor), Some((lo, hi))) = (self.sel_anchor_row, self.selected_row_range())
+        else {
+            self.status = "Fill-down: select rows with Shift+Up/Down first".into();
+            return;
+        };
+        let Some(table) = self.loaded_table.clone() else {
+            return;
+        };
+        let column = match self.columns.get(self.sel_col) {
+            Some(c) if c != "__rowid__" => c.clone(),
+            _ => {
+                self.status = "Fill-down: pick a data column".into();
+                return;
+            }
+        };
+        if self.is_generated_column(self.sel_col) {
+            self.status = "Column is generated (read-only)".into();
+            return;
+        }
+        // The selection can only cover rows that are still in the loaded buffer
+        let rowid_at = |abs: usize| self.rowid_at_abs(abs);
+        let Some(source_rowid) = rowid_at(anchor) else {
+            self.status = "Fill-down: selection extends beyond the loaded rows".into();
+            return;
+        };
+        let mut rowids = Vec::with_capacity(hi - lo);
+        for abs in (lo..=hi).filter(|&a| a != anchor) {
+            match rowid_at(abs) {
+                Some(id) => rowids.push(id),
+                None => {
+                    self.status = "Fill-down: selection extends beyond the loaded rows".into();
+                    return;
+                }
+            }
+        }
+        if rowids.is_empty() {
+            self.status = "Fill-down: select more than one row".into();
+            return;
+        }
+        let n = rowids.len();
+        self.pending_fill = Some((column.clone(), rowids.clone()));
+        let _ = self.req_tx.send(DBRequest::FillDown {
+            table,
+            column,
+            source_rowid,
+            rowids,
+        });
+        self.sel_anchor_row = None;
+        self.status = format!("Filling {} rows...", n);
+    }
+
+    /// Insert a copy of the selected row and jump to it once inserted
+    pub fn duplicate_selected_row(&mut self) {
+        if self.refuse_if_grouped("duplicate rows") {
+            return;
+        }
+        if self.refuse_if_read_only("duplicate rows") {
+            return;
+        }
+        if self.loaded_is_view() {
+            self.status = "Views are read-only".into();
+            return;
+        }
+        let Some(table) = self.loaded_table.clone() else {
+            self.status = "No table loaded".into();
+            return;
+        };
+        let Some(rowid) = self
+            .rows
+            .get(self.sel_row)
+            .and_then(|r| r.first())
+            .and_then(|s| s.parse::<i64>().ok())
+        else {
+            self.status = "No row selected to duplicate".into();
+            return;
+        };
+        let _ = self.req_tx.send(DBRequest::DuplicateRow { table, rowid });
+        self.status = format!("Duplicating row {}...", rowid);
+    }
+
+    /// u: revert the table's newest change; Ctrl+r (`redo`) applies the last undone one again
+    pub fn undo_or_redo(&mut self, redo: bool) {
+        let action = if redo { "redo changes" } else { "undo changes" };
+        if self.refuse_if_read_only(action) {
+            return;
+        }
+        // The table on screen, whose edits the user sees, not the sidebar cursor
+        let Some(table) = self.loaded_table.clone() else {
+            self.status = format!("No table loaded to {}", if redo { "redo" } else { "undo" });
+            return;
+        };
+        if redo {
+            let _ = self.req_tx.send(DBRequest::RedoLastChange { table });
+            self.status = "Redoing last undone change...".into();
+        } else {
+            let _ = self.req_tx.send(DBRequest::UndoLastChange { table });
+            self.status = "Undoing last change...".into();
+        }
+    }
+
+    /// Ask for a y/n confirmation before deleting the selected row
+    pub fn delete_current_row(&mut self) {
+        if self.refuse_if_grouped("delete rows") {
+            return;
+        }
+        if self.refuse_if_read_only("delete rows") {
+            return;
+        }
+        if self.loaded_is_view() {
+            self.status = "Views are read-only".into();
+            return;
+        }
+        let Some(table) = self.loaded_table.clone() else {
+            self.status = "No table loaded".into();
+            return;
+        };
+        if self.rows.is_empty() {
+            self.status = "No row selected to delete".into();
+            return;
+        }
+        let rowid = if self.columns.first().map(|c| c.as_str()) == Some("__rowid__") {
+            self.rows
+                .get(self.sel_row)
+                .and_then(|r| r.first())
+                .and_then(|s| s.parse::<i64>().ok())
+        } else {
+            None
+        };
+        let Some(rowid) = rowid else {
+            self.status = format!(
+                "{} has no rowid (WITHOUT ROWID table); rows can't be deleted here",
+                table
+            );
+            return;
+        };
+        self.begin_prompt(PromptKind::ConfirmDeleteRow { table, rowid });
+    }
+
+    pub fn begin_edit_cell(&mut self) {
+        if self.refuse_if_grouped("edit cells") {
+            return;
+        }
+        if self.rows.is_empty() || self.columns.is_empty() {
+            return;
+        }
+        let row = self.sel_row;
+        let col = self.sel_col;
+        if self.refuse_if_read_only("edit cells") {
+            return;
+        }
+        if self.loaded_is_view() {
+            self.status = "Views are read-only".into();
+            return;
+        }
+        // Prevent editing the __rowid__ column and provide a clear status message.
+        if self.columns.get(col).map(|s| s.as_str()) == Some("__rowid__") {
+            self.status = "Editing __rowid__ is not supported".into();
+            return;
+        }
+        if self.cell_kind(row, col) == Some(CellKind::Blob)
+            && self
+                .rows
+                .get(row)
+                .and_then(|r| r.get(col))
+                .is_some_and(|t| parse_blob_placeholder(t).is_some())
+        {
+            self.status = "Large BLOBs can't be edited here (W saves one to a file)".into();
+            return;
+        }
+        if self.is_generated_column(col) {
+            self.status = "Column is generated (read-only)".into();
+            return;
+        }
+        if self.cell_kind(row, col) == Some(CellKind::InvalidText) {
+            self.status =
+                "This text isn't valid UTF-8 (shown with �); editing it would corrupt it".into();
+            return;
+        }
+        if let AppMode::Editing {
+            row: erow,
+            col: ecol,
+            ..
+        } = self.mode
+            && erow == row
+            && ecol == col
+        {
+            // Already editing this cell; do not reset buffer or cursor
+            self.status = "Editing: Enter to save, Esc to cancel".into();
+            return;
+        }
+        // Capture a stable rowid for this edit session
+        let rowid = self
+            .rows
+            .get(row)
+            .and_then(|r| r.first())
+            .and_then(|s| s.parse::<i64>().ok())
+            .unwrap_or(-1);
+        if rowid < 0 {
+            self.status = "Invalid rowid; cannot edit this row".into();
+            return;
+        }
+        self.edit_rowid = Some(rowid);
+
+        let current = self
+            .rows
+            .get(row)
+            .and_then(|r| r.get(col))
+            .cloned()
+            .unwrap_or_default();
+        // Retrying a rejected edit starts from what was typed, not the stored value. A NULL
+        // starts as NULL with nothing typed; its "NULL" text must not be saved as a string
+        let was_null = self.cell_kind(row, col) == Some(CellKind::Null);
+        (self.edit_buffer, self.edit_is_null) = match self.last_edit.take() {
+            Some((r, c, text)) if r == rowid && self.columns.get(col) == Some(&c) => (text, false),
+            _ if was_null => (String::new(), true),
+            _ => (current, false),
+        };
+        self.edit_expr = None;
+        // AUTO follows the column's affinity; a strict type is picked with Tab
+        self.edit_type = EditType::Auto;
+        self.mode = AppMode::Editing {
+            row,
+            col,
+            cursor: self.edit_buffer.len(),
+        };
+        self.status = "Editing: Enter to save, Esc to cancel".into();
+    }
+
+    /// o: add a blank row below the loaded rows and fill it in column by column
+    pub fn begin_insert_row(&mut self) {
+        if self.refuse_if_grouped("insert rows") {
+            return;
+        }
+        if self.refuse_if_read_only("insert rows") {
+            return;
+        }
+        if self.loaded_is_view() {
+            self.status = "Views are read-only".into();
+            return;
+        }
+        if matches!(self.mode, AppMode::Editing { .. }) {
+            return;
+        }
+        let Some(table) = self.loaded_table.clone() else {
+            self.status = "No table loaded".into();
+            return;
+        };
+        if self.columns.first().map(|c| c.as_str()) != Some("__rowid__") {
+            self.status = format!(
+                "{} has no rowid (WITHOUT ROWID table); rows can't be inserted here",
+                table
+            );
+            return;
+        }
+        let Some(col) = self.next_insert_column(0, false) else {
+            self.status = format!("Every column of {} is generated; nothing to fill in", table);
+            return;
+        };
+        // A failed insert is reopened with what was typed
+        let values = match self.new_row.take() {
+            Some(n) if n.table == table && n.values.len() == self.columns.len() => n.values,
+            _ => vec![InsertValue::Default; self.columns.len()],
+        };
+        let row = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| match (i, v) {
+                (0, _) => NEW_ROW_ROWID.to_string(),
+                (_, InsertValue::Text(s)) => s.clone(),
+                _ => String::new(),
+            })
+            .collect();
+        let kinds = values
+            .iter()
+            .map(|v| match v {
+                InsertValue::Null => CellKind::Null,
+                _ => CellKind::Text,
+            })
+            .collect();
+        self.buffer_rows.push(row);
+        self.buffer_kinds.push(kinds);
+        // Select it; refitting scrolls the window down to it
+        self.sel_row = self.buffer_rows.len() - 1 - self.view_start;
+        self.sel_anchor_row = None;
+        self.refit_visible_rows();
+        self.new_row = Some(NewRow {
+            table,
+            values,
+            null_field: false,
+            editing: true,
+            sent: false,
+        });
+        self.open_insert_field(col);
+    }
+
+    /// The cell being edited belongs to the row `o` added
+    pub fn inserting(&self) -> bool {
+        matches!(self.mode, AppMode::Editing { .. })
+            && self.new_row.as_ref().is_some_and(|n| n.editing)
+    }
+
+    /// The open field of the row being inserted is set to an explicit NULL
+    pub fn insert_field_is_null(&self) -> bool {
+        self.edit_is_null && self.new_row.as_ref().is_some_and(|n| n.null_field)
+    }
+
+    /// Whether a cell of the row being inserted is left to its column DEFAULT
+    pub fn is_new_row_default(&self, row: usize, col: usize) -> bool {
+        match (self.mode, self.new_row.as_ref()) {
+            (AppMode::Editing { row: erow, .. }, Some(n)) if n.editing && row == erow => {
+                col > 0 && n.values.get(col) == Some(&InsertValue::Default)
+            }
+            _ => false,
+        }
+    }
+
+    /// Tab / Shift+Tab in the new row: keep this column's value and edit the next one
+    pub fn insert_next_field(&mut self, backwards: bool) {
+        let AppMode::Editing { col, .. } = self.mode else {
+            return;
+        };
+        self.store_insert_field();
+        let next = self.next_insert_column(col, backwards).unwrap_or(col);
+        self.open_insert_field(next);
+    }
+
+    /// Writable column after (or before) `from`, wrapping around; never `__rowid__`
+    fn next_insert_column(&self, from: usize, backwards: bool) -> Option<usize> {
+        let n = self.columns.len();
+        (1..n)
+            .map(|step| {
+                if backwards {
+                    (from + n - step) % n
+                } else {
+                    (from + step) % n
+                }
+            })
+            .find(|&c| c != 0 && !self.is_generated_column(c))
+    }
+
+    fn open_insert_field(&mut self, col: usize) {
+        let value = self
+            .new_row
+            .as_ref()
+            .and_then(|n| n.values.get(col).cloned())
+            .unwrap_or(InsertValue::Default);
+        if let Some(n) = self.new_row.as_mut() {
+            n.null_field = value == InsertValue::Null;
+        }
+        (self.edit_is_null, self.edit_buffer) = match value {
+            InsertValue::Text(s) => (false, s),
+            _ => (true, String::new()),
+        };
+        self.edit_expr = None;
+        self.edit_type = EditType::Auto;
+        self.edit_rowid = None;
+        self.sel_col = col;
+        self.mode = AppMode::Editing {
+            row: self.sel_row,
+            col,
+            cursor: self.edit_buffer.len(),
+        };
+        self.status = format!(
+            "New row, {}: Tab/Shift+Tab next/previous column | Ctrl+d DEFAULT/empty/NULL | Enter insert | Esc discard",
+            self.columns[col]
+        );
+    }
+
+    // Keep the value being edited in the draft and in the grid
+    fn store_insert_field(&mut self) {
+        let AppMode::Editing { row, col, .. } = self.mode else {
+            return;
+        };
+        let null_field = self.new_row.as_ref().is_some_and(|n| n.null_field);
+        let value = match (self.edit_is_null, null_field) {
+            (false, _) => InsertValue::Text(self.edit_buffer.clone()),
+            (true, true) => InsertValue::Null,
+            (true, false) => InsertValue::Default,
+        };
+        let (text, kind) = match &value {
+            InsertValue::Text(s) => (s.clone(), CellKind::Text),
+            InsertValue::Null => (String::new(), CellKind::Null),
+            InsertValue::Default => (String::new(), CellKind::Text),
+        };
+        if let Some(cell) = self.rows.get_mut(row).and_then(|r| r.get_mut(col)) {
+            cell.clone_from(&text);
+        }
+        if let Some(last) = self.buffer_rows.len().checked_sub(1)
+            && self.buffer_rows[last]
+                .first()
+                .is_some_and(|id| id == NEW_ROW_ROWID)
+        {
+            self.buffer_rows[last][col] = text;
+            self.buffer_kinds[last][col] = kind;
+        }
+        if let Some(slot) = self.new_row.as_mut().and_then(|n| n.values.get_mut(col)) {
+            *slot = value;
+        }
+    }
+
+    fn submit_insert_row(&mut self) {
+        self.store_insert_field();
+        self.mode = AppMode::Normal;
+        self.remove_new_row_from_grid();
+        let Some(n) = self.new_row.as_mut() else {
+            return;
+        };
+        n.editing = false;
+        n.sent = true;
+        let _ = self.req_tx.send(DBRequest::InsertRow {
+            table: n.table.clone(),
+            values: n.values[1..].to_vec(),
+        });
+        self.status = "Inserting row...".into();
+        if self.external_change_deferred {
+            self.reload_for_external_change();
+        }
+    }
+
+    // The blank row only lives in the grid while it is edited; the reload after the
+    // insert shows the real one
+    fn remove_new_row_from_grid(&mut self) {
+        if self
+            .buffer_rows
+            .last()
+            .and_then(|r| r.first())
+            .is_some_and(|id| id == NEW_ROW_ROWID)
+        {
+            self.buffer_rows.pop();
+            self.buffer_kinds.pop();
+        }
+        if self.buffer_rows.is_empty() {
+            self.rows.clear();
+            self.sel_row = 0;
+        } else {
+            self.refit_visible_rows();
+        }
+    }
+
+    pub fn cancel_edit_cell(&mut self) {
+        if self.inserting() {
+            self.new_row = None;
+            self.mode = AppMode::Normal;
+            self.remove_new_row_from_grid();
+            self.status = "New row discarded".into();
+            if self.external_change_deferred {
+                self.reload_for_external_change();
+            }
+            return;
+        }
+        self.mode = AppMode::Normal;
+        self.edit_rowid = None;
+        self.status = "Edit cancelled".into();
+    }
+
+    // ===== Create-table wizard =====
+
+    pub fn begin_create_table(&mut self) {
+        if self.refuse_if_read_only("create tables") {
+            return;
+        }
+        self.create_form = Some(CreateTableForm {
+            columns: vec![ColumnDraft::default()],
+            ..Default::default()
+        });
+        self.mode = AppMode::CreateTable;
+        self.status =
+            "New table: Up/Down row | Tab field | Left/Right type | Space toggle | Ctrl+a add/Ctrl+x remove column | Enter create | Esc cancel".into();
+    }
+
+    pub fn cancel_create_table(&mut self) {
+        self.create_form = None;
+        self.mode = AppMode::Normal;
+        self.status = "Create table cancelled".into();
+    }
+
+    pub fn submit_create_table(&mut self) {
+        let Some(form) = self.create_form.as_ref() else {
+            return;
+        };
+        match form.ddl() {
+            Ok(sql) => {
+                self.pending_select_table = Some(form.name.trim().to_string());
+                let _ = self.req_tx.send(DBRequest::CreateTable { sql });
+                self.create_form = None;
+                self.mode = AppMode::Normal;
+                self.status = "Creating table...".into();
+            }
+            Err(e) => self.status = format!("Cannot create table: {}", e),
+        }
+    }
+
+    pub fn create_form_move_row(&mut self, down: bool) {
+        if let Some(form) = self.create_form.as_mut() {
+            let last = form.columns.len();
+            form.row = if down {
+                min(form.row + 1, last)
+            } else {
+                form.row.saturating_sub(1)
+            };
+        }
+    }
+
+    pub fn create_form_next_field(&mut self, backwards: bool) {
+        if let Some(form) = self.create_form.as_mut() {
+            if form.row == 0 {
+                form.row = 1.min(form.columns.len());
+                form.field = 0;
+                return;
+            }
+            form.field = if backwards {
+                (form.field + CreateTableForm::FIELDS - 1) % CreateTableForm::FIELDS
+            } else {
+                (form.field + 1) % CreateTableForm::FIELDS
+            };
+        }
+    }
+
+    pub fn create_form_add_column(&mut self) {
+        if let Some(form) = self.create_form.as_mut() {
+            form.columns.push(ColumnDraft::default());
+            form.row = form.columns.len();
+            form.field = 0;
+        }
     }
 
-    pub fn begin_edit_cell(&mut self) {
-        if self.rows.is_empty() || self.columns.is_empty() {
-            return;
+    pub fn create_form_remove_column(&mut self) {
+        if let Some(form) = self.create_form.as_mut()
+            && form.row > 0
+            && form.row <= form.columns.len()
+        {
+            form.columns.remove(form.row - 1);
+            form.row = min(form.row, form.columns.len());
         }
-        let row = self.sel_row;
-        let col = self.sel_col;
-        // Prevent editing the __rowid__ column and provide a clear status message.
-        if self.columns.get(col).map(|s| s.as_str()) == Some("__rowid__") {
-            self.status = "Editing __rowid__ is not supported".into();
+    }
+
+    /// Left/Right on the type field cycles through the common SQLite types
+    pub fn create_form_cycle_type(&mut self, forward: bool) {
+        let Some(form) = self.create_form.as_mut() else {
             return;
-        }
-        if let AppMode::Editing {
-            row: erow,
-            col: ecol,
-            ..
-        } = self.mode
-            && erow == row
-            && ecol == col
-        {
-            // Already editing this cell; do not reset buffer or cursor
-            self.status = "Editing: Enter to save, Esc to cancel".into();
+        };
+        if form.row == 0 || form.field != 1 {
             return;
         }
-        // Capture a stable rowid for this edit session
-        let rowid = self
-            .rows
-            .get(row)
-            .and_then(|r| r.first())
-            .and_then(|s| s.parse::<i64>().ok())
-            .unwrap_or(-1);
-        if rowid < 0 {
-            self.status = "Invalid rowid; cannot edit this row".into();
-            return;
+        if let Some(col) = form.columns.get_mut(form.row - 1) {
+            let types = CreateTableForm::TYPES;
+            let cur = types
+                .iter()
+                .position(|t| t.eq_ignore_ascii_case(col.col_type.trim()));
+            let next = match (cur, forward) {
+                (Some(i), true) => (i + 1) % types.len(),
+                (Some(i), false) => (i + types.len() - 1) % types.len(),
+                (None, _) => 0,
+            };
+            col.col_type = types[next].to_string();
         }
-        self.edit_rowid = Some(rowid);
+    }
 
-        let current = self
-            .rows
-            .get(row)
-            .and_then(|r| r.get(col))
-            .cloned()
-            .unwrap_or_default();
-        self.edit_buffer = current;
-        self.edit_is_null = false;
-        self.mode = AppMode::Editing {
-            row,
-            col,
-            cursor: self.edit_buffer.len(),
+    /// Space on a checkbox field toggles it; elsewhere it is typed as text
+    pub fn create_form_toggle_or_space(&mut self) {
+        let Some(form) = self.create_form.as_mut() else {
+            return;
         };
-        self.status = "Editing: Enter to save, Esc to cancel".into();
+        if form.row > 0
+            && let Some(col) = form.columns.get_mut(form.row - 1)
+        {
+            match form.field {
+                2 => {
+                    col.nullable = !col.nullable;
+                    return;
+                }
+                3 => {
+                    col.pk = !col.pk;
+                    return;
+                }
+                _ => {}
+            }
+        }
+        self.create_form_insert(' ');
     }
 
-    pub fn cancel_edit_cell(&mut self) {
-        self.mode = AppMode::Normal;
-        self.edit_rowid = None;
-        self.status = "Edit cancelled".into();
+    pub fn create_form_insert(&mut self, c: char) {
+        if let Some(buf) = self.create_form.as_mut().and_then(|f| f.focused_text()) {
+            buf.push(c);
+        }
+    }
+
+    pub fn create_form_backspace(&mut self) {
+        if let Some(buf) = self.create_form.as_mut().and_then(|f| f.focused_text()) {
+            buf.pop();
+        }
     }
 
-    // P0: Mark current edit to set NULL on submit
+    /// Ctrl+d while editing: set NULL on submit; pressed again, an empty string instead
     pub fn edit_mark_null(&mut self) {
-        if let AppMode::Editing { .. } = self.mode {
+        let inserting = self.inserting();
+        if let AppMode::Editing { ref mut cursor, .. } = self.mode {
+            self.edit_expr = None;
+            if inserting {
+                // DEFAULT → empty string → NULL → DEFAULT; typed text goes on to NULL
+                let Some(n) = self.new_row.as_mut() else {
+                    return;
+                };
+                let to_null = !self.edit_is_null;
+                (self.edit_is_null, n.null_field) = match (self.edit_is_null, n.null_field) {
+                    (true, false) => (false, false),
+                    (false, _) => (true, true),
+                    (true, true) => (true, false),
+                };
+                self.edit_buffer.clear();
+                *cursor = 0;
+                self.status = if !self.edit_is_null {
+                    "Will insert an empty string (Ctrl+d NULL)".into()
+                } else if to_null {
+                    "Will insert NULL (Ctrl+d DEFAULT)".into()
+                } else {
+                    "Will leave the column to its DEFAULT (Ctrl+d empty string)".into()
+                };
+            } else if self.edit_is_null {
+                self.edit_is_null = false;
+                self.edit_buffer.clear();
+                *cursor = 0;
+                self.status =
+                    "Will set an empty string (Enter to save, Ctrl+d NULL, Esc to cancel)".into();
+            } else {
+                self.edit_is_null = true;
+                self.status =
+                    "Will set NULL (Enter to save, Ctrl+d empty string, Esc to cancel)".into();
+            }
+        }
+    }
+
+    /// Cycle the value written through CURRENT_TIMESTAMP → CURRENT_DATE → CURRENT_TIME → typed
+    pub fn cycle_edit_expr(&mut self) {
+        if !matches!(self.mode, AppMode::Editing { .. }) {
+            return;
+        }
+        if self.inserting() {
+            self.status = "A new row takes typed values only".into();
+            return;
+        }
+        self.edit_expr = SqlExpr::next(self.edit_expr);
+        self.status = match self.edit_expr {
+            Some(expr) => format!(
+                "Will set {} (Enter to save, Ctrl+t next, Esc to cancel)",
+                expr.sql()
+            ),
+            None => format!("Edit as {} (Tab cycles)", self.edit_type_label()),
+        };
+    }
+
+    /// Cycle the type the edit is written as: AUTO → TEXT → INTEGER → REAL → NULL
+    pub fn cycle_edit_type(&mut self) {
+        if !matches!(self.mode, AppMode::Editing { .. }) {
+            return;
+        }
+        self.edit_expr = None;
+        if self.edit_is_null {
+            self.edit_is_null = false;
+            self.edit_type = EditType::Auto;
+        } else if self.edit_type == EditType::Real {
             self.edit_is_null = true;
-            self.status = "Will set NULL (Enter to save, Esc to cancel)".into();
+        } else {
+            self.edit_type = match self.edit_type {
+                EditType::Auto => EditType::Text,
+                EditType::Text => EditType::Integer,
+                _ => EditType::Real,
+            };
+        }
+        self.status = format!("Edit as {} (Tab cycles)", self.edit_type_label());
+    }
+
+    /// Type tag shown while editing
+    pub fn edit_type_label(&self) -> &'static str {
+        if let Some(expr) = self.edit_expr {
+            expr.sql()
+        } else if self.edit_is_null {
+            "NULL"
+        } else {
+            self.edit_type.label()
         }
     }
 
     pub fn submit_cell_edit(&mut self) {
+        if self.inserting() {
+            self.submit_insert_row();
+            return;
+        }
         let (row, col) = match self.mode {
-            AppMode::Normal => return,
             AppMode::Editing { row, col, .. } => (row, col),
+            _ => return,
         };
         self.mode = AppMode::Normal;
 
@@ -589,12 +4218,21 @@ impl App {
         } else {
             Some(self.edit_buffer.clone())
         };
+        // Loaded columns lead with __rowid__, which isn't one of the table's own
+        let column_index = self.columns[..col]
+            .iter()
+            .filter(|c| *c != "__rowid__")
+            .count();
         let _ = self.req_tx.send(DBRequest::UpdateCell {
             table,
             rowid,
             column: col_name.clone(),
+            column_index,
             new_value: new_val,
+            value_type: self.edit_type,
+            expr: self.edit_expr,
         });
+        self.last_edit = Some((rowid, col_name.clone(), self.edit_buffer.clone()));
         // Clear the captured rowid after dispatch
         self.edit_rowid = None;
         self.status = "Updating cell...".into();
@@ -603,6 +4241,9 @@ impl App {
     // Editing buffer ops
     pub fn edit_input_insert(&mut self, ch: char) {
         if let AppMode::Editing { ref mut cursor, .. } = self.mode {
+            // Typing replaces a pending NULL or SQL expression
+            self.edit_is_null = false;
+            self.edit_expr = None;
             self.edit_buffer.insert(*cursor, ch);
             *cursor += ch.len_utf8();
         }
@@ -645,9 +4286,66 @@ impl App {
         }
     }
 
-    // ===== Copy to clipboard/file helpers (TSV) =====
+    // ===== Copy to clipboard/file helpers (TSV, CSV or JSON) =====
+
+    /// T: cycle the copy format TSV → CSV → JSON
+    pub fn cycle_copy_format(&mut self) {
+        self.copy_format = match self.copy_format {
+            ExportFormat::Tsv => ExportFormat::Csv,
+            ExportFormat::Csv => ExportFormat::Json,
+            ExportFormat::Json => ExportFormat::Tsv,
+        };
+        self.status = format!(
+            "Copy format: {} (c cell | C row | Y column | Ctrl+C page)",
+            copy_format_name(self.copy_format)
+        );
+    }
+
+    // `cols` of the loaded rows at buffer positions `rows`, in the copy format (see
+    // `db::format_cells`)
+    fn format_copy(&self, rows: &[usize], cols: &[usize], one_record: bool) -> String {
+        let header = cols.iter().map(|&c| self.columns[c].clone()).collect();
+        let cells: Vec<Vec<(&str, Option<CellKind>)>> = rows
+            .iter()
+            .map(|&r| {
+                cols.iter()
+                    .map(|&c| {
+                        let text = self.buffer_rows[r].get(c).map(|s| s.as_str()).unwrap_or("");
+                        let kind = self.buffer_kinds.get(r).and_then(|k| k.get(c)).copied();
+                        (text, kind)
+                    })
+                    .collect()
+            })
+            .collect();
+        format_cells(self.copy_format, header, &cells, one_record)
+    }
+
+    // Large BLOBs are only a "<BLOB n bytes>" placeholder in the grid; copying that text
+    // would pass for the value
+    fn refuse_if_large_blobs(&mut self, rows: &[usize], cols: &[usize]) -> bool {
+        let placeholder = rows.iter().any(|&r| {
+            cols.iter().any(|&c| {
+                self.buffer_kinds.get(r).and_then(|k| k.get(c)) == Some(&CellKind::Blob)
+                    && self
+                        .buffer_rows
+                        .get(r)
+                        .and_then(|row| row.get(c))
+                        .is_some_and(|t| parse_blob_placeholder(t).is_some())
+            })
+        });
+        if placeholder {
+            self.status = "Large BLOBs can't be copied (W saves one to a file)".into();
+        }
+        placeholder
+    }
+
+    // Buffer positions of the visible window's rows
+    fn visible_buffer_rows(&self) -> Vec<usize> {
+        (self.view_start..self.view_start + self.rows.len()).collect()
+    }
 
-    /// Copy the currently selected cell as text to clipboard; fallback to a temp .tsv file.
+    /// Copy the currently selected cell to clipboard; fallback to a temp file. TSV copies the
+    /// raw text, CSV a quoted field and JSON a one-key object
     pub fn copy_current_cell_tsv(&mut self) {
         if self.rows.is_empty() || self.columns.is_empty() {
             self.status = "Nothing to copy (no data)".into();
@@ -655,49 +4353,92 @@ impl App {
         }
         let r = self.sel_row.min(self.rows.len().saturating_sub(1));
         let c = self.sel_col.min(self.columns.len().saturating_sub(1));
-        let cell = self
-            .rows
-            .get(r)
-            .and_then(|row| row.get(c))
-            .cloned()
-            .unwrap_or_default();
+        if self.refuse_if_large_blobs(&[self.view_start + r], &[c]) {
+            return;
+        }
+        let cell = if self.copy_format == ExportFormat::Tsv {
+            self.rows
+                .get(r)
+                .and_then(|row| row.get(c))
+                .cloned()
+                .unwrap_or_default()
+        } else {
+            self.format_copy(&[self.view_start + r], &[c], true)
+        };
         self.copy_to_clipboard_or_file(cell, "cell");
     }
 
-    /// Copy the currently selected row as TSV to clipboard; fallback to a temp .tsv file.
+    /// Copy the currently selected row (one record, no header) to clipboard; fallback to a
+    /// temp file. With a Shift+Up/Down selection, copy the selected rows with a header instead.
     pub fn copy_current_row_tsv(&mut self) {
         if self.rows.is_empty() || self.columns.is_empty() {
             self.status = "Nothing to copy (no data)".into();
             return;
         }
+        let cols: Vec<usize> = (0..self.columns.len()).collect();
+        if let Some((lo, hi)) = self.selected_row_range() {
+            let rows: Option<Vec<usize>> = (lo..=hi)
+                .map(|abs| {
+                    abs.checked_sub(self.buffer_offset)
+                        .filter(|&i| i < self.buffer_rows.len())
+                })
+                .collect();
+            let Some(rows) = rows else {
+                self.status = "Copy: selection extends beyond the loaded rows".into();
+                return;
+            };
+            if self.refuse_if_large_blobs(&rows, &cols) {
+                return;
+            }
+            let out = self.format_copy(&rows, &cols, false);
+            let label = format!("{} rows", rows.len());
+            self.copy_to_clipboard_or_file(out, &label);
+            return;
+        }
         let r = self.sel_row.min(self.rows.len().saturating_sub(1));
-        let line = self
-            .rows
-            .get(r)
-            .map(|row| row.join("\t"))
-            .unwrap_or_default();
+        if self.refuse_if_large_blobs(&[self.view_start + r], &cols) {
+            return;
+        }
+        let line = self.format_copy(&[self.view_start + r], &cols, true);
         self.copy_to_clipboard_or_file(line, "row");
     }
 
-    /// Copy the current page (with header) as TSV to clipboard; fallback to a temp .tsv file.
+    /// Copy the selected column of every visible row, header first.
+    pub fn copy_current_column_tsv(&mut self) {
+        if self.rows.is_empty() || self.columns.is_empty() {
+            self.status = "Nothing to copy (no data)".into();
+            return;
+        }
+        // The same column the grid highlights (a hidden __rowid__ is never selected)
+        let c = self
+            .sel_col
+            .min(self.columns.len().saturating_sub(1))
+            .max(self.first_visible_col());
+        let rows = self.visible_buffer_rows();
+        if self.refuse_if_large_blobs(&rows, &[c]) {
+            return;
+        }
+        let out = self.format_copy(&rows, &[c], false);
+        let label = format!("column {}", self.columns[c]);
+        self.copy_to_clipboard_or_file(out, &label);
+    }
+
+    /// Copy the current page (with header) to clipboard; fallback to a temp file.
     pub fn copy_current_page_tsv(&mut self) {
         if self.rows.is_empty() || self.columns.is_empty() {
             self.status = "Nothing to copy (no data)".into();
             return;
         }
-        let mut out = String::new();
-        // header
-        out.push_str(&self.columns.join("\t"));
-        out.push('\n');
-        // rows
-        for row in &self.rows {
-            out.push_str(&row.join("\t"));
-            out.push('\n');
+        let cols: Vec<usize> = (0..self.columns.len()).collect();
+        let rows = self.visible_buffer_rows();
+        if self.refuse_if_large_blobs(&rows, &cols) {
+            return;
         }
+        let out = self.format_copy(&rows, &cols, false);
         self.copy_to_clipboard_or_file(out, "page");
     }
 
-    /// Best-effort clipboard copy; falls back to writing a temp .tsv file on failure.
+    /// Best-effort clipboard copy; falls back to writing a temp .tsv/.csv/.json file on failure.
     fn copy_to_clipboard_or_file(&mut self, content: String, label: &str) {
         // Try platform clipboards in order
         let candidates: &[(&str, &[&str])] = &[
@@ -711,9 +4452,22 @@ impl App {
             // Windows
             ("clip", &[]),
         ];
-        for (prog, args) in candidates {
+        let custom = self.clipboard_cmd.as_ref().and_then(|cmd| {
+            let (prog, args) = cmd.split_first()?;
+            let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+            Some((prog.clone(), args))
+        });
+        let custom = custom
+            .as_ref()
+            .map(|(prog, args)| (prog.as_str(), args.as_slice()));
+        for (prog, args) in custom.iter().chain(candidates) {
             if self.try_clipboard_prog(prog, args, &content) {
-                self.status = format!("Copied {} to clipboard via {}", label, prog);
+                self.status = format!(
+                    "Copied {} ({}) to clipboard via {}",
+                    label,
+                    copy_format_name(self.copy_format),
+                    prog
+                );
                 return;
             }
         }
@@ -723,19 +4477,125 @@ impl App {
             .duration_since(std::time::UNIX_EPOCH)
             .map(|d| d.as_millis())
             .unwrap_or(0);
-        file_path.push(format!("sqlite-editor-{}.tsv", ts));
+        let name = copy_format_name(self.copy_format);
+        file_path.push(format!(
+            "sqlite-editor-{}.{}",
+            ts,
+            name.to_ascii_lowercase()
+        ));
         match std::fs::write(&file_path, content.as_bytes()) {
             Ok(_) => {
                 self.status = format!(
-                    "Wrote {} TSV to {} (no clipboard tool found)",
+                    "Wrote {} {} to {} (no clipboard tool found)",
                     label,
+                    name,
                     file_path.display()
                 );
             }
             Err(e) => {
-                self.status = format!("Failed to write {} TSV: {}", label, e);
+                self.status = format!("Failed to write {} {}: {}", label, name, e);
+            }
+        }
+    }
+
+    /// Ctrl+v while editing: insert the clipboard text at the cursor. The editor is one
+    /// line, so line breaks become spaces
+    pub fn paste_clipboard_into_edit(&mut self) {
+        if !matches!(self.mode, AppMode::Editing { .. }) {
+            return;
+        }
+        let Some((prog, text)) = self.read_clipboard() else {
+            self.status = "Paste: no clipboard tool found (pbpaste, wl-paste, xclip, xsel)".into();
+            return;
+        };
+        let text = text.trim_end_matches(['\r', '\n']);
+        let lines = text.lines().count();
+        let text = text.lines().collect::<Vec<_>>().join(" ");
+        if let AppMode::Editing { ref mut cursor, .. } = self.mode {
+            self.edit_is_null = false;
+            self.edit_expr = None;
+            self.edit_buffer.insert_str(*cursor, &text);
+            *cursor += text.len();
+        }
+        self.status = if lines > 1 {
+            format!(
+                "Pasted {} lines via {}; line breaks were replaced by spaces",
+                lines, prog
+            )
+        } else {
+            format!("Pasted {} chars via {}", text.chars().count(), prog)
+        };
+    }
+
+    /// p: insert the clipboard's TSV or CSV row (as copied with C) as a new record. A leading
+    /// __rowid__ field from a copy with the rowid column is dropped; generated columns are skipped
+    pub fn paste_row_from_clipboard(&mut self) {
+        if self.refuse_if_grouped("paste rows") {
+            return;
+        }
+        if self.refuse_if_read_only("paste rows") {
+            return;
+        }
+        if self.loaded_is_view() {
+            self.status = "Views are read-only".into();
+            return;
+        }
+        let Some(table) = self.loaded_table.clone() else {
+            self.status = "No table loaded".into();
+            return;
+        };
+        let Some((_, text)) = self.read_clipboard() else {
+            self.status = "Paste: no clipboard tool found (pbpaste, wl-paste, xclip, xsel)".into();
+            return;
+        };
+        // Data columns, plus one when the copy came with the __rowid__ column
+        let with_rowid = self.columns.len();
+        let counts = [with_rowid.saturating_sub(1), with_rowid];
+        let mut fields = match parse_clipboard_row(&text, &counts) {
+            Ok(fields) => fields,
+            Err(e) => {
+                self.status = format!("Paste row: {}", e);
+                return;
             }
+        };
+        if self.columns.first().map(|s| s.as_str()) == Some("__rowid__")
+            && fields.len() == self.columns.len()
+        {
+            fields.remove(0);
+        }
+        let _ = self.req_tx.send(DBRequest::PasteRow { table, fields });
+        self.status = "Pasting row...".into();
+    }
+
+    // First clipboard tool that answers: (program, text)
+    fn read_clipboard(&self) -> Option<(&'static str, String)> {
+        let candidates: &[(&'static str, &[&str])] = &[
+            // macOS
+            ("pbpaste", &[]),
+            // Wayland
+            ("wl-paste", &["--no-newline"]),
+            // X11
+            ("xclip", &["-selection", "clipboard", "-o"]),
+            ("xsel", &["--clipboard", "--output"]),
+            // Windows
+            ("powershell", &["-NoProfile", "-Command", "Get-Clipboard"]),
+        ];
+        candidates
+            .iter()
+            .find_map(|(prog, args)| Some((*prog, self.read_clipboard_prog(prog, args)?)))
+    }
+
+    fn read_clipboard_prog(&self, prog: &str, args: &[&str]) -> Option<String> {
+        let out = std::process::Command::new(prog)
+            .args(args)
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()?;
+        if !out.status.success() {
+            return None;
         }
+        Some(String::from_utf8_lossy(&out.stdout).into_owned())
     }
 
     fn try_clipboard_prog(&self, prog: &str, args: &[&str], content: &str) -> bool {
@@ -809,9 +4669,113 @@ impl App {
         self.autosize_col_request = None;
     }
 
+    /// Selected rows can be exported on their own (Ctrl+s); views have no rowids to pick
+    /// them by
+    pub fn can_export_selection(&self) -> bool {
+        self.selected_row_range().is_some() && !self.loaded_is_view()
+    }
+
+    /// Status line text while the CSV export path is being typed
+    pub fn export_prompt_status(&self, path: &str) -> String {
+        let on_off = |b: bool| if b { "on" } else { "off" };
+        format!(
+            "Export CSV: {}_   (Enter save, Esc cancel |{} Ctrl+a {} | Ctrl+d delimiter {} | Ctrl+b BOM {} | Ctrl+e CRLF {} | Ctrl+q quote all {} | Ctrl+r rowid {})",
+            path,
+            match self.selected_row_range() {
+                Some((lo, hi)) if self.export_selection => {
+                    format!(" Ctrl+s rows: {} selected |", hi - lo + 1)
+                }
+                Some(_) if self.can_export_selection() => " Ctrl+s rows: all |".to_string(),
+                _ => String::new(),
+            },
+            if self.export_append {
+                "append to file"
+            } else {
+                "replace file"
+            },
+            match self.export_delimiter {
+                '\t' => "tab".to_string(),
+                c => c.to_string(),
+            },
+            on_off(self.export_bom),
+            on_off(self.export_crlf),
+            on_off(self.export_quote_all),
+            on_off(self.export_include_rowid),
+        )
+    }
+
+    /// Status line for a running export: rows written, plus a percentage when the
+    /// row count is known
+    pub fn export_progress_status(&self, rows_written: usize) -> String {
+        let done = match self.export_total {
+            Some(total) if total > 0 => format!(
+                "{}/{} rows ({}%)",
+                rows_written,
+                total,
+                (rows_written * 100 / total).min(100)
+            ),
+            _ => format!("{} rows", rows_written),
+        };
+        format!(
+            "Exporting CSV to {}: {}... (Esc cancels)",
+            self.export_target, done
+        )
+    }
+
+    pub fn cancel_export(&mut self) {
+        self.export_cancel.store(true, Ordering::Relaxed);
+        self.status = "Cancelling export...".into();
+    }
+
+    pub fn cycle_export_delimiter(&mut self) {
+        self.export_delimiter = match self.export_delimiter {
+            ',' => ';',
+            ';' => '\t',
+            '\t' => '|',
+            _ => ',',
+        };
+    }
+
     // Toggle a full cell viewer pane to show the complete text of the current cell.
     pub fn toggle_cell_viewer(&mut self) {
         self.show_cell_viewer = !self.show_cell_viewer;
+        if !self.show_cell_viewer && self.focus == Focus::Viewer {
+            self.focus = Focus::Data;
+        }
+    }
+
+    /// Scroll the cell viewer by whole pages (negative scrolls up)
+    pub fn scroll_viewer(&mut self, pages: i32) {
+        if !self.show_cell_viewer {
+            self.status = "Cell viewer is closed (v opens it)".into();
+            return;
+        }
+        self.scroll_viewer_lines(self.viewer_page.max(1) as i32 * pages);
+    }
+
+    /// Scroll the cell viewer by lines (negative scrolls up)
+    pub fn scroll_viewer_lines(&mut self, lines: i32) {
+        let next = (self.viewer_scroll as i32 + lines).clamp(0, self.viewer_max_scroll as i32);
+        self.viewer_scroll = next as u16;
+    }
+
+    pub fn toggle_viewer_pretty_json(&mut self) {
+        self.viewer_pretty_json = !self.viewer_pretty_json;
+        self.status = if self.viewer_pretty_json {
+            "Cell viewer: JSON pretty-printed".into()
+        } else {
+            "Cell viewer: raw text".into()
+        };
+    }
+
+    /// Display text of the selected cell when it doesn't fit its column (shown in the status line)
+    pub fn clipped_cell_text(&self) -> Option<Cow<'_, str>> {
+        let raw = self.current_cell_text()?;
+        let r = self.sel_row.min(self.rows.len().saturating_sub(1));
+        let c = self.sel_col.min(self.columns.len().saturating_sub(1));
+        let width = *self.col_display_widths.get(c)? as usize;
+        let text = self.display_cell(r, c, raw);
+        (text.chars().count() > width || text.contains('\n')).then_some(text)
     }
 
     // Get the current cell's text (for viewer panes).
@@ -828,11 +4792,280 @@ impl App {
     }
 }
 
-// Simplified grapheme stepping without unicode-segmentation:
-// moves by bytes; acceptable for a PoC.
-fn prev_grapheme(_s: &str, idx: usize) -> usize {
-    idx.saturating_sub(1)
+/// Room a column without an absolute width needs to count as shown when scrolling
+const FLEX_COLUMN_MIN_WIDTH: u32 = 8;
+
+/// Horizontal scrolling: the first column drawn and how many are drawn, of `total` columns
+/// from `base` on. Columns sized only by tiers are all squeezed in; once some have absolute
+/// widths (+/-, a/A) the window starts at `scroll` and slides right until `sel` fits
+pub fn column_window(
+    base: usize,
+    total: usize,
+    sel: usize,
+    scroll: usize,
+    abs_widths: &[u16],
+    width: u16,
+) -> (usize, usize) {
+    if total <= base || !abs_widths.iter().any(|&w| w > 0) {
+        return (base, total.saturating_sub(base));
+    }
+    // Each column plus the one-cell gap after it; the last gap isn't drawn
+    let needs = |i: usize| match abs_widths.get(i) {
+        Some(&w) if w > 0 => u32::from(w.max(3)) + 1,
+        _ => FLEX_COLUMN_MIN_WIDTH + 1,
+    };
+    let room = u32::from(width) + 1;
+    let sel = sel.clamp(base, total - 1);
+    let mut start = scroll.clamp(base, sel);
+    while start < sel && (start..=sel).map(needs).sum::<u32>() > room {
+        start += 1;
+    }
+    let mut end = start;
+    let mut used = 0;
+    while end < total && (end == start || used + needs(end) <= room) {
+        used += needs(end);
+        end += 1;
+    }
+    (start, end - start)
+}
+
+/// 1234567 -> "1,234,567"
+pub fn group_thousands(n: i64) -> String {
+    let digits = n.unsigned_abs().to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(ch);
+    }
+    if n < 0 { format!("-{}", out) } else { out }
+}
+
+/// 512 B, 4.0 KiB, 1.2 MiB, ...
+pub fn format_bytes(n: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = n as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", n, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// "HH:MM:SS UTC" for an error log entry
+fn format_clock_utc(t: std::time::SystemTime) -> String {
+    let secs = t
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+// 0.4ms, 12ms, 3.2s
+fn format_elapsed(d: std::time::Duration) -> String {
+    let ms = d.as_secs_f64() * 1000.0;
+    if ms < 10.0 {
+        format!("{:.1}ms", ms)
+    } else if ms < 10_000.0 {
+        format!("{:.0}ms", ms)
+    } else {
+        format!("{:.1}s", ms / 1000.0)
+    }
+}
+
+// Split "path as alias"; without an explicit alias use the file stem (identifier-safe).
+fn parse_attach_input(input: &str) -> (String, String) {
+    if let Some(idx) = input.to_lowercase().rfind(" as ") {
+        let path = input[..idx].trim().to_string();
+        let alias = input[idx + 4..].trim().to_string();
+        if !path.is_empty() && !alias.is_empty() {
+            return (path, alias);
+        }
+    }
+    let stem = std::path::Path::new(input)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let alias: String = stem
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    (input.to_string(), alias)
+}
+
+/// The query's characters appear in `name` in order (case-insensitive), so `usrrl` finds
+/// `user_roles`
+fn fuzzy_matches(query: &str, name: &str) -> bool {
+    let mut chars = name.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| chars.any(|c| c == q))
+}
+
+/// How well `name` matches `query`, best first: exact, prefix, substring, then fuzzy
+/// (all case-insensitive); None when it doesn't match at all
+fn fuzzy_rank(query: &str, name: &str) -> Option<u8> {
+    let (q, n) = (query.to_lowercase(), name.to_lowercase());
+    if n == q {
+        Some(0)
+    } else if n.starts_with(&q) {
+        Some(1)
+    } else if n.contains(&q) {
+        Some(2)
+    } else if fuzzy_matches(&q, &n) {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+/// Byte index where the grapheme before `idx` starts
+fn prev_grapheme(s: &str, idx: usize) -> usize {
+    s[..idx.min(s.len())]
+        .grapheme_indices(true)
+        .next_back()
+        .map(|(i, _)| i)
+        .unwrap_or(0)
 }
+/// Byte index just past the grapheme at `idx`
 fn next_grapheme(s: &str, idx: usize) -> usize {
-    min(idx + 1, s.len())
+    s.get(idx..)
+        .and_then(|rest| rest.graphemes(true).next())
+        .map(|g| idx + g.len())
+        .unwrap_or(s.len())
+}
+
+/// Characters of an unquoted SQL name
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Byte index where the SQL name ending at `idx` starts (`idx` when there is none)
+fn word_start(s: &str, idx: usize) -> usize {
+    s[..idx]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_name_char(c))
+        .last()
+        .map_or(idx, |(i, _)| i)
+}
+
+/// Unquoted names in a query, in order
+fn query_words(sql: &str) -> impl Iterator<Item = &str> {
+    sql.split(|c: char| !is_name_char(c))
+        .filter(|w| !w.is_empty())
+}
+
+/// (characters, words, lines) of a cell's text; characters are graphemes, as the editor's
+/// cursor moves
+pub fn text_counts(s: &str) -> (usize, usize, usize) {
+    (
+        s.graphemes(true).count(),
+        s.unicode_words().count(),
+        s.lines().count(),
+    )
+}
+
+fn copy_format_name(format: ExportFormat) -> &'static str {
+    match format {
+        ExportFormat::Tsv => "TSV",
+        ExportFormat::Csv => "CSV",
+        ExportFormat::Json => "JSON",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_window_follows_the_selection_across_a_wide_table() {
+        // __rowid__ hidden, then 12 columns of 10 cells; three fit in 35 cells with gaps
+        let widths = [vec![0], vec![10; 12]].concat();
+        let total = widths.len();
+        let mut scroll = 1;
+        for sel in 1..total {
+            let (start, shown) = column_window(1, total, sel, scroll, &widths, 35);
+            assert!(
+                start <= sel && sel < start + shown,
+                "{sel} not in {start}+{shown}"
+            );
+            assert_eq!(shown, 3);
+            scroll = start;
+        }
+        assert_eq!(scroll, total - 3);
+        // Back to the left, as move_cell_left clamps the scroll onto the selection
+        for sel in (1..total).rev() {
+            scroll = scroll.min(sel);
+            let (start, shown) = column_window(1, total, sel, scroll, &widths, 35);
+            assert!(
+                start <= sel && sel < start + shown,
+                "{sel} not in {start}+{shown}"
+            );
+            scroll = start;
+        }
+        assert_eq!(scroll, 1);
+    }
+
+    #[test]
+    fn column_window_without_absolute_widths_shows_everything() {
+        assert_eq!(column_window(1, 13, 12, 5, &[0; 13], 35), (1, 12));
+    }
+
+    #[test]
+    fn tab_cycles_table_then_column_names_in_the_query_bar() {
+        let (req_tx, req_rx) = crossbeam_channel::unbounded();
+        let (_resp_tx, resp_rx) = crossbeam_channel::unbounded();
+        let mut app = App::new(100, req_tx, resp_rx);
+        app.tables = vec!["orders".into(), "order_items".into(), "users".into()];
+        app.begin_query_input();
+        "SELECT * FROM ord"
+            .chars()
+            .for_each(|c| app.update_query_input_char(c));
+
+        app.complete_query_input();
+        assert_eq!(
+            app.query_input.as_deref(),
+            Some("SELECT * FROM order_items")
+        );
+        app.complete_query_input();
+        assert_eq!(app.query_input.as_deref(), Some("SELECT * FROM orders"));
+        app.complete_query_input();
+        assert_eq!(
+            app.query_input.as_deref(),
+            Some("SELECT * FROM order_items")
+        );
+
+        // The columns of a mentioned table are fetched on the first Tab
+        app.cancel_query_input();
+        app.begin_query_input();
+        "SELECT * FROM orders WHERE orders.to"
+            .chars()
+            .for_each(|c| app.update_query_input_char(c));
+        app.complete_query_input();
+        assert!(
+            req_rx
+                .try_iter()
+                .any(|r| matches!(r, DBRequest::TableColumns { table } if table == "orders"))
+        );
+        app.query_columns
+            .insert("orders".into(), vec!["id".into(), "total".into()]);
+        app.complete_query_input();
+        assert_eq!(
+            app.query_input.as_deref(),
+            Some("SELECT * FROM orders WHERE orders.total")
+        );
+    }
 }
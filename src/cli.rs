@@ -0,0 +1,169 @@
+//! Non-interactive modes (`--export`, `--query`): print to stdout or a file and exit.
+
+use std::io::{self, Write};
+
+use anyhow::Result;
+
+use crate::Args;
+use crate::db;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum OutputFormat {
+    Csv,
+    Tsv,
+    Json,
+}
+
+impl From<OutputFormat> for db::ExportFormat {
+    fn from(f: OutputFormat) -> Self {
+        match f {
+            OutputFormat::Csv => db::ExportFormat::Csv,
+            OutputFormat::Tsv => db::ExportFormat::Tsv,
+            OutputFormat::Json => db::ExportFormat::Json,
+        }
+    }
+}
+
+/// Non-interactive `--export`: stream one table and exit
+pub fn run_export(args: &Args, table: &str) -> Result<()> {
+    // Exporting never writes, so don't create or lock the file for writing
+    let mode = db::OpenMode::new(true, args.immutable);
+    let conn = db::open_connection(&args.db_path, mode)?;
+    let sort_dir = args.sort.as_ref().map(|_| {
+        if args.desc {
+            db::SortDir::Desc
+        } else {
+            db::SortDir::Asc
+        }
+    });
+    // A typo in the table or --sort must not truncate --out first
+    db::check_export(&conn, table, args.sort.as_deref())?;
+    let mut out = open_output(args)?;
+    let written = db::export_table(
+        &conn,
+        table,
+        db::ExportOptions::from(db::ExportFormat::from(
+            args.format.unwrap_or(OutputFormat::Csv),
+        )),
+        db::RowScope {
+            filter: args.filter.as_deref(),
+            sort_by: args.sort.as_deref(),
+            sort_dir,
+            rowids: None,
+            columns: None,
+            include_rowid: args.include_rowid,
+        },
+        &mut out,
+        &mut |_| true,
+    )
+    .and_then(|n| {
+        out.flush()?;
+        Ok(n)
+    });
+    match written {
+        Ok(n) => {
+            if let Some(path) = &args.out {
+                eprintln!("Exported {} rows to {}", n, path);
+            }
+            Ok(())
+        }
+        Err(e) if is_broken_pipe(&e) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Non-interactive `--query`: run one statement and print its result
+pub fn run_query(args: &Args, sql: &str) -> Result<()> {
+    let mode = db::OpenMode::new(args.read_only, args.immutable);
+    let conn = db::open_connection(&args.db_path, mode)?;
+    // Only the text table is for reading; csv/tsv/json output is complete
+    let limit = (args.format.is_none() && args.result_limit > 0).then_some(args.result_limit);
+    let output = db::run_query(&conn, sql, limit, true)?;
+    if output.columns.is_empty() {
+        eprintln!("{} rows affected", output.changes);
+        return Ok(());
+    }
+    let mut out = open_output(args)?;
+    let res = match args.format {
+        None => write_table(&mut out, &output),
+        Some(format) => {
+            let mut w = db::RecordWriter::new(
+                &mut out,
+                db::ExportFormat::from(format),
+                output.columns.clone(),
+            )?;
+            for row in &output.rows {
+                let values: Vec<_> = row.iter().map(|v| v.into()).collect();
+                w.write(&values)?;
+            }
+            w.finish().map(|_| ())
+        }
+    }
+    .and_then(|_| out.flush());
+    match res {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn open_output(args: &Args) -> Result<Box<dyn Write>> {
+    Ok(match &args.out {
+        Some(path) => Box::new(io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(io::BufWriter::new(io::stdout().lock())),
+    })
+}
+
+// The reader went away (e.g. `| head`); that's not an error for us
+fn is_broken_pipe(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+// Aligned text table with a header rule and a row count footer
+fn write_table<W: Write>(w: &mut W, output: &db::QueryOutput) -> io::Result<()> {
+    // Keep each record on one line
+    let cell = |s: String| {
+        s.replace('\n', "\\n")
+            .replace('\r', "\\r")
+            .replace('\t', " ")
+    };
+    let rows: Vec<Vec<String>> = output
+        .rows
+        .iter()
+        .map(|r| r.iter().map(|v| cell(db::display_value(v))).collect())
+        .collect();
+    let mut widths: Vec<usize> = output.columns.iter().map(|c| c.chars().count()).collect();
+    for row in &rows {
+        for (width, v) in widths.iter_mut().zip(row) {
+            *width = (*width).max(v.chars().count());
+        }
+    }
+    let line = |values: &[String]| {
+        values
+            .iter()
+            .zip(&widths)
+            .map(|(v, &width)| format!("{:<width$}", v, width = width))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    };
+    writeln!(w, "{}", line(&output.columns))?;
+    let rule: Vec<String> = widths.iter().map(|&n| "-".repeat(n)).collect();
+    writeln!(w, "{}", rule.join("-+-"))?;
+    for row in &rows {
+        writeln!(w, "{}", line(row))?;
+    }
+    let n = output.total_rows;
+    if rows.len() < n {
+        writeln!(
+            w,
+            "(showing first {} of {} rows; --result-limit 0 shows all)",
+            rows.len(),
+            n
+        )
+    } else {
+        writeln!(w, "({} row{})", n, if n == 1 { "" } else { "s" })
+    }
+}
@@ -1,16 +1,244 @@
 use anyhow::Result;
 use crossbeam_channel::{Receiver, Sender};
-use rusqlite::{Connection, Row, types::ValueRef};
-use std::collections::HashMap;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Row, types::ValueRef};
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufWriter, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, Copy)]
+use crate::logging;
+
+/// Storage class of a loaded cell (rows themselves are sent as display strings)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellKind {
+    Null,
+    Integer,
+    Real,
+    Text,
+    /// TEXT whose bytes aren't valid UTF-8: shown with replacement characters, not editable
+    /// (saving the lossy text back would corrupt it)
+    InvalidText,
+    Blob,
+}
+
+/// One column of a row to insert
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertValue {
+    /// Left out of the INSERT, so the column takes its DEFAULT
+    Default,
+    Null,
+    Text(String),
+}
+
+impl From<ValueRef<'_>> for CellKind {
+    fn from(v: ValueRef<'_>) -> Self {
+        match v {
+            ValueRef::Null => CellKind::Null,
+            ValueRef::Integer(_) => CellKind::Integer,
+            ValueRef::Real(_) => CellKind::Real,
+            ValueRef::Text(t) if std::str::from_utf8(t).is_err() => CellKind::InvalidText,
+            ValueRef::Text(_) => CellKind::Text,
+            ValueRef::Blob(_) => CellKind::Blob,
+        }
+    }
+}
+
+/// Column type affinity, derived from the declared type like SQLite does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affinity {
+    Integer,
+    Real,
+    Numeric,
+    Text,
+    /// BLOB or no declared type
+    Blob,
+}
+
+impl Affinity {
+    /// Affinity rules from https://www.sqlite.org/datatype3.html (section 3.1)
+    pub fn from_decl_type(decl: &str) -> Self {
+        let t = decl.to_ascii_uppercase();
+        if t.contains("INT") {
+            Affinity::Integer
+        } else if t.contains("CHAR") || t.contains("CLOB") || t.contains("TEXT") {
+            Affinity::Text
+        } else if t.contains("BLOB") || t.trim().is_empty() {
+            Affinity::Blob
+        } else if t.contains("REAL") || t.contains("FLOA") || t.contains("DOUB") {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    pub fn is_numeric(self) -> bool {
+        matches!(self, Affinity::Integer | Affinity::Real | Affinity::Numeric)
+    }
+}
+
+/// How an edited value is bound when written back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditType {
+    /// INTEGER if it parses, then REAL, else TEXT
+    Auto,
+    Text,
+    Integer,
+    Real,
+}
+
+impl EditType {
+    pub fn label(self) -> &'static str {
+        match self {
+            EditType::Auto => "AUTO",
+            EditType::Text => "TEXT",
+            EditType::Integer => "INTEGER",
+            EditType::Real => "REAL",
+        }
+    }
+}
+
+/// SQL expressions an edit can write instead of a bound value. Their SQL is put into the
+/// UPDATE as-is, so only this fixed set is accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlExpr {
+    Timestamp,
+    Date,
+    Time,
+}
+
+impl SqlExpr {
+    pub fn sql(self) -> &'static str {
+        match self {
+            SqlExpr::Timestamp => "CURRENT_TIMESTAMP",
+            SqlExpr::Date => "CURRENT_DATE",
+            SqlExpr::Time => "CURRENT_TIME",
+        }
+    }
+
+    /// Next expression in the editor's cycle; None goes back to the typed value
+    pub fn next(current: Option<Self>) -> Option<Self> {
+        match current {
+            None => Some(SqlExpr::Timestamp),
+            Some(SqlExpr::Timestamp) => Some(SqlExpr::Date),
+            Some(SqlExpr::Date) => Some(SqlExpr::Time),
+            Some(SqlExpr::Time) => None,
+        }
+    }
+}
+
+/// What an edit writes to a cell
+enum CellWrite {
+    Null,
+    Value(String, EditType),
+    Expr(SqlExpr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SortDir {
     Asc,
     Desc,
 }
 
+/// An index as listed in `sqlite_master`
+#[derive(Debug, Clone)]
+pub struct IndexInfo {
+    pub name: String,
+    pub table: String,
+    /// CREATE INDEX statement (None for implicit indexes)
+    pub sql: Option<String>,
+}
+
+/// Summary of one column, as shown by the column statistics overlay
+#[derive(Debug, Clone)]
+pub struct ColumnStats {
+    pub rows: i64,
+    pub distinct: i64,
+    pub nulls: i64,
+    /// Min/max/avg are only filled in when every non-NULL value is numeric
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub avg: Option<f64>,
+}
+
+/// Columns and rows produced by an arbitrary SQL statement
+#[derive(Debug, Clone)]
+pub struct QueryOutput {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<rusqlite::types::Value>>,
+    /// Rows the statement returned; more than `rows` when the limit cut them off (only
+    /// one more if the rest weren't counted)
+    pub total_rows: usize,
+    /// Rows changed, for statements that return no columns
+    pub changes: usize,
+}
+
+/// File formats understood by `export_table`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Tsv,
+    Json,
+}
+
+/// How `export_table` and `RecordWriter` lay out their output
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Write the header row (CSV/TSV); off when appending to an earlier export
+    pub header: bool,
+    /// Start with a UTF-8 byte order mark (Excel needs it to detect UTF-8)
+    pub bom: bool,
+    /// End CSV records with \r\n instead of \n
+    pub crlf: bool,
+    /// Quote every CSV field, not just those holding the delimiter, quotes or line breaks
+    pub quote_all: bool,
+    /// Field separator for CSV
+    pub delimiter: char,
+}
+
+impl From<ExportFormat> for ExportOptions {
+    fn from(format: ExportFormat) -> Self {
+        Self {
+            format,
+            header: true,
+            bom: false,
+            crlf: false,
+            quote_all: false,
+            delimiter: ',',
+        }
+    }
+}
+
+/// Which rows `export_table` writes, and in what order
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RowScope<'a> {
+    pub filter: Option<&'a str>,
+    pub sort_by: Option<&'a str>,
+    pub sort_dir: Option<SortDir>,
+    /// Only these rows (the filter is ignored, the sort still applies)
+    pub rowids: Option<&'a [i64]>,
+    /// Columns to write, in this order; None writes every column in schema order
+    pub columns: Option<&'a [String]>,
+    /// Write `__rowid__` as the first column
+    pub include_rowid: bool,
+}
+
+/// One page of a table as the data pane would load it (same filter, sort and offset)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PageKey {
+    pub table: String,
+    pub offset: usize,
+    pub page_size: usize,
+    pub filter: Option<String>,
+    pub sort_by: Option<String>,
+    pub sort_dir: Option<SortDir>,
+    /// Estimate the unfiltered row count of big tables instead of running COUNT(*)
+    pub approx_count: bool,
+}
+
 #[derive(Debug)]
 pub enum DBRequest {
     LoadSchema,
@@ -26,13 +254,36 @@ pub enum DBRequest {
         sort_by: Option<String>,
         /// Optional sort direction (defaults to Asc when Some(sort_by) and None here)
         sort_dir: Option<SortDir>,
+        /// See `PageKey::approx_count`
+        approx_count: bool,
+    },
+    /// Load a page ahead of time; answered with `PagePrefetched` instead of `TableData`
+    PrefetchPage(PageKey),
+    /// One row per distinct value of `column` with its row count (filter applied), most
+    /// frequent first unless sorted by `column` or "COUNT(*)"; answered with a read-only
+    /// `TableData` whose `__rowid__` is NULL
+    GroupByCount {
+        key: PageKey,
+        column: String,
+    },
+    /// Run an ad-hoc SELECT / WITH query (anything that could write is refused); keeps
+    /// the first `limit` rows (0: all) and is answered with `QueryResult`
+    RunQuery {
+        sql: String,
+        limit: usize,
     },
     UpdateCell {
         table: String,
         rowid: i64,
         column: String,
+        /// Position of `column` among the table's columns when the page was loaded; the
+        /// update is refused if another process has changed the schema since
+        column_index: usize,
         /// None means set SQL NULL
         new_value: Option<String>,
+        value_type: EditType,
+        /// Written instead of `new_value` when set
+        expr: Option<SqlExpr>,
     },
     ExportCSV {
         table: String,
@@ -43,121 +294,2051 @@ pub enum DBRequest {
         sort_by: Option<String>,
         /// Optional sort direction (defaults to Asc when Some(sort_by) and None here)
         sort_dir: Option<SortDir>,
+        /// Add to the end of an existing file (without a second header) instead of replacing it
+        append: bool,
+        /// Excel-friendly output: UTF-8 BOM at the start of the file
+        bom: bool,
+        /// Excel-friendly output: \r\n line endings
+        crlf: bool,
+        /// Quote every field, for parsers that treat unquoted fields specially
+        quote_all: bool,
+        /// Field separator (`,` for plain CSV; `;`, tab or `|` also work)
+        delimiter: char,
+        /// Export just these rows (the selection) instead of everything matching the filter
+        rowids: Option<Vec<i64>>,
+        /// Columns in the order shown on screen, without hidden ones; None is schema order
+        columns: Option<Vec<String>>,
+        /// Write `__rowid__` as the first column (off: only the table's own columns)
+        include_rowid: bool,
     },
     /// Undo the last change applied to this table in this process
     UndoLastChange {
         table: String,
     },
+    /// Apply again the change the last undo reverted
+    RedoLastChange {
+        table: String,
+    },
+    /// Value each cell of `table` had before its latest undoable change
+    PreviousValues {
+        table: String,
+    },
+    /// Attach another database file under the given schema alias
+    AttachDatabase {
+        path: String,
+        alias: String,
+    },
+    /// Check whether another connection committed changes since the last check
+    CheckExternalChange,
+    /// Row counts for the sidebar (estimated from sqlite_stat1 when available)
+    TableCounts {
+        tables: Vec<String>,
+    },
+    /// Column names of one table (`PRAGMA table_info`), for query bar completion
+    TableColumns {
+        table: String,
+    },
+    /// Run a CREATE TABLE statement built by the wizard
+    CreateTable {
+        sql: String,
+    },
+    DropTable {
+        table: String,
+    },
+    /// DELETE every row (SQLite has no TRUNCATE); `reset_sequence` also restarts the
+    /// table's AUTOINCREMENT counter
+    TruncateTable {
+        table: String,
+        reset_sequence: bool,
+    },
+    RenameTable {
+        table: String,
+        new_name: String,
+    },
+    RenameColumn {
+        table: String,
+        column: String,
+        new_name: String,
+    },
+    /// Copy the live main database to `path` with the online backup API
+    Backup {
+        path: String,
+        /// Replace an existing file (the user confirmed)
+        overwrite: bool,
+    },
+    /// Run `PRAGMA integrity_check` over all attached databases
+    IntegrityCheck,
+    /// Refresh the query planner's statistics (`ANALYZE`) for all attached databases
+    Analyze,
+    /// Rebuild a table's indexes (`REINDEX`)
+    Reindex {
+        table: String,
+    },
+    /// Copy the WAL back into the database file and truncate it
+    /// (`PRAGMA wal_checkpoint(TRUNCATE)`)
+    Checkpoint,
+    /// Length and first `bytes` bytes of a BLOB cell (large BLOBs only reach the UI as a
+    /// placeholder)
+    BlobHead {
+        table: String,
+        rowid: i64,
+        column: String,
+        bytes: usize,
+    },
+    /// Write one BLOB cell's bytes to a file
+    SaveBlob {
+        table: String,
+        rowid: i64,
+        column: String,
+        path: String,
+    },
+    /// File size (page_count × page_size) of the main database, the size of its `-wal`
+    /// file and the SQLite version
+    DatabaseInfo,
+    /// Copy one cell's value into the same column of other rows, in a single transaction
+    FillDown {
+        table: String,
+        column: String,
+        source_rowid: i64,
+        rowids: Vec<i64>,
+    },
+    /// COUNT/DISTINCT/NULLs (plus MIN/MAX/AVG for numeric data) over the filtered rows
+    ColumnStats {
+        table: String,
+        column: String,
+        filter: Option<String>,
+    },
+    /// Most frequent values of a column with their counts (`limit` 0: all of them)
+    DistinctValues {
+        table: String,
+        column: String,
+        limit: usize,
+    },
+    /// Start an explicit transaction; edits stay uncommitted until Commit
+    Begin,
+    Commit,
+    /// Discard everything since Begin
+    Rollback,
+    /// Delete every row the filter matches (one transaction)
+    DeleteMatching {
+        table: String,
+        filter: String,
+    },
+    /// Insert a copy of the row; SQLite assigns the copy a new rowid
+    DuplicateRow {
+        table: String,
+        rowid: i64,
+    },
+    /// Delete one row; undo puts it back at the same rowid
+    DeleteRow {
+        table: String,
+        rowid: i64,
+    },
+    /// Insert one record from pasted fields, in table column order (generated columns'
+    /// fields are skipped). "NULL" becomes NULL
+    PasteRow {
+        table: String,
+        fields: Vec<String>,
+    },
+    /// Insert a new record, one value per table column in order (generated columns'
+    /// values are skipped). Answered with `RowInserted`
+    InsertRow {
+        table: String,
+        values: Vec<InsertValue>,
+    },
+    /// Find the position of a row within the filtered and sorted table
+    LocateRow {
+        table: String,
+        rowid: i64,
+        filter: Option<String>,
+        sort_by: Option<String>,
+        sort_dir: Option<SortDir>,
+    },
+    /// Find the first of `rowids` shown after position `from` (before it, if not
+    /// `forward`) in the filtered and sorted table, wrapping around. Answered with
+    /// `RowLocated`; its position is None when the filter hides them all
+    LocateBookmark {
+        table: String,
+        rowids: Vec<i64>,
+        from: usize,
+        forward: bool,
+        filter: Option<String>,
+        sort_by: Option<String>,
+        sort_dir: Option<SortDir>,
+    },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum DBResponse {
     Schema {
+        /// Table names; tables from attached databases are prefixed with "alias."
         tables: Vec<String>,
+        /// View names (prefixed like tables)
+        views: Vec<String>,
+        indexes: Vec<IndexInfo>,
+        /// Schema names in `PRAGMA database_list` order (always starts with "main")
+        schemas: Vec<String>,
+        /// Tables (named as in `tables`) that are FTS5 virtual tables
+        fts_tables: Vec<String>,
     },
     TableData {
         table: String,
         columns: Vec<String>,
+        /// Declared affinity per column (`__rowid__` counts as INTEGER)
+        affinities: Vec<Affinity>,
+        /// Generated (computed) columns; they can't be written
+        generated: Vec<bool>,
         rows: Vec<Vec<String>>,
+        /// Storage class of each cell in `rows`
+        kinds: Vec<Vec<CellKind>>,
         page: usize,
         total_rows: Option<usize>,
+        /// `total_rows` is an estimate (`approx_count` on a big table)
+        total_estimated: bool,
+    },
+    /// Rows of a `RunQuery`, led by a NULL `__rowid__` column like the grouped view
+    QueryResult {
+        sql: String,
+        columns: Vec<String>,
+        rows: Vec<Vec<String>>,
+        kinds: Vec<Vec<CellKind>>,
+        /// `rows.len()`, or one more when the limit cut the result off
+        total_rows: usize,
+    },
+    /// A `TableData` loaded for `PrefetchPage`
+    PagePrefetched {
+        key: PageKey,
+        data: Box<DBResponse>,
+    },
+    /// Answer to `PreviousValues`, by (rowid, column); None is NULL
+    PreviousValues {
+        table: String,
+        values: HashMap<(i64, String), Option<String>>,
     },
     CellUpdated {
         ok: bool,
+        /// Rows the UPDATE changed; 0 means the rowid no longer matched anything
+        rows: usize,
         message: Option<String>,
     },
+    /// Sent periodically while an export runs; `ExportedCSV` still marks the end
+    ExportProgress {
+        rows_written: usize,
+    },
     ExportedCSV {
         ok: bool,
         path: String,
         message: Option<String>,
     },
+    Attached {
+        alias: String,
+        path: String,
+    },
+    TableDropped {
+        table: String,
+    },
+    TableTruncated {
+        table: String,
+        count: usize,
+        sequence_reset: bool,
+    },
+    TableRenamed {
+        table: String,
+        /// New name as the app addresses it (keeps the "alias." prefix)
+        new_name: String,
+    },
+    ColumnRenamed {
+        table: String,
+        column: String,
+        new_name: String,
+    },
+    ColumnStats {
+        table: String,
+        column: String,
+        stats: ColumnStats,
+    },
+    DistinctValues {
+        table: String,
+        column: String,
+        /// (value, count) pairs, most frequent first; None is SQL NULL
+        values: Vec<(Option<String>, i64)>,
+        /// How many distinct values there are; more than `values` when the limit cut them off
+        total: usize,
+    },
+    /// Fill-down committed `count` cell updates
+    RowsFilled {
+        table: String,
+        count: usize,
+    },
+    RowInserted {
+        table: String,
+        rowid: i64,
+    },
+    RowDeleted {
+        table: String,
+        rowid: i64,
+    },
+    RowsDeleted {
+        table: String,
+        count: usize,
+    },
+    /// Explicit transaction state after Begin/Commit/Rollback
+    Transaction {
+        open: bool,
+        rolled_back: bool,
+    },
+    /// Commit or Rollback failed (e.g. the database is locked); `open` says whether the
+    /// transaction still is. Kept apart from `Error` so the app knows what failed
+    TransactionFailed {
+        message: String,
+        open: bool,
+    },
+    /// Result of `LocateRow`; `position` is None when the filter hides the row
+    RowLocated {
+        table: String,
+        rowid: i64,
+        position: Option<usize>,
+    },
+    /// DDL succeeded; the schema should be reloaded
+    SchemaChanged {
+        message: String,
+    },
+    TableCounts {
+        counts: HashMap<String, i64>,
+        /// Tables whose count is an estimate taken from sqlite_stat1
+        estimated: HashSet<String>,
+    },
+    TableColumns {
+        table: String,
+        columns: Vec<String>,
+    },
+    /// Result of `CheckExternalChange`; `changed` is false for our own writes
+    ExternalChange {
+        changed: bool,
+    },
+    /// Sent periodically while a backup runs
+    BackupProgress {
+        done_pages: i32,
+        total_pages: i32,
+    },
+    BackupDone {
+        path: String,
+        size_bytes: u64,
+    },
+    BlobSaved {
+        path: String,
+        bytes: usize,
+    },
+    BlobHead {
+        table: String,
+        rowid: i64,
+        column: String,
+        head: Vec<u8>,
+    },
+    /// Problems reported by `PRAGMA integrity_check`; empty when it said "ok"
+    IntegrityCheck {
+        problems: Vec<String>,
+    },
+    /// `ANALYZE`, `REINDEX` or a WAL checkpoint finished; `command` names what ran
+    MaintenanceDone {
+        command: String,
+    },
+    DatabaseInfo {
+        size_bytes: u64,
+        /// None when there is no `-wal` file (not in WAL mode, or fully checkpointed)
+        wal_bytes: Option<u64>,
+        sqlite_version: String,
+    },
+    /// How long the request answered just before this took
+    Timing {
+        elapsed: Duration,
+    },
     Error(String),
 }
 
-pub fn start_db_worker(path: String, req_rx: Receiver<DBRequest>, resp_tx: Sender<DBResponse>) {
-    let conn = match Connection::open(path) {
-        Ok(c) => c,
-        Err(e) => {
-            let _ = resp_tx.send(DBResponse::Error(format!("Failed to open DB: {e}")));
-            return;
+/// How the database file is opened (--read-only, --immutable)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    ReadWrite,
+    ReadOnly,
+    /// Read-only, and SQLite is told the file can't change: no locks and no journal
+    /// files, so it works on read-only and WORM media
+    Immutable,
+}
+
+impl OpenMode {
+    pub fn new(read_only: bool, immutable: bool) -> Self {
+        match (read_only, immutable) {
+            (_, true) => OpenMode::Immutable,
+            (true, false) => OpenMode::ReadOnly,
+            (false, false) => OpenMode::ReadWrite,
+        }
+    }
+
+    pub fn is_read_only(self) -> bool {
+        self != OpenMode::ReadWrite
+    }
+}
+
+/// Open the database; read-only connections never create the file
+pub fn open_connection(path: &str, mode: OpenMode) -> rusqlite::Result<Connection> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI;
+    let conn = match mode {
+        OpenMode::ReadWrite => Connection::open(path),
+        OpenMode::ReadOnly => Connection::open_with_flags(path, flags),
+        OpenMode::Immutable => {
+            Connection::open_with_flags(format!("file:{}?immutable=1", uri_path(path)), flags)
+        }
+    }?;
+    register_unicode_lower(&conn)?;
+    Ok(conn)
+}
+
+// SQLite's LOWER() and LIKE only fold ASCII, so "müller" would never find "Müller";
+// the filter lowercases both sides with this instead
+fn register_unicode_lower(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        "unicode_lower",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            Ok(match ctx.get_raw(0) {
+                ValueRef::Text(t) => Some(String::from_utf8_lossy(t).to_lowercase()),
+                _ => None,
+            })
+        },
+    )
+}
+
+// Escape the characters that would end or corrupt the path part of a file: URI
+fn uri_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '%' => out.push_str("%25"),
+            '?' => out.push_str("%3f"),
+            '#' => out.push_str("%23"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn start_db_worker(
+    path: String,
+    mode: OpenMode,
+    req_rx: Receiver<DBRequest>,
+    resp_tx: Sender<DBResponse>,
+    // Set by the UI thread (Esc) to stop an export; requests queue behind a running export,
+    // so this can't travel through the channel
+    export_cancel: Arc<AtomicBool>,
+    page_cache_size: usize,
+    // SQLite retries a locked database for this long before a write fails with SQLITE_BUSY
+    busy_timeout: Duration,
+) {
+    let conn = match open_connection(&path, mode) {
+        Ok(c) => c,
+        Err(e) => {
+            let _ = resp_tx.send(DBResponse::Error(format!("Failed to open DB: {e}")));
+            return;
+        }
+    };
+    let _ = conn.busy_timeout(busy_timeout);
+
+    // safemode: faster reading (switching journal mode needs write access)
+    if !mode.is_read_only() {
+        let _ = conn.pragma_update(None, "journal_mode", "WAL");
+        let _ = conn.pragma_update(None, "synchronous", "NORMAL");
+    }
+
+    // Per-table history of updates for undo functionality
+    let mut history: History = HashMap::new();
+
+    // Undo/redo stacks as they were at BEGIN, put back on ROLLBACK
+    let mut txn_history: Option<History> = None;
+
+    // PRAGMA data_version only changes when *other* connections commit
+    let mut data_version = read_data_version(&conn).ok();
+
+    let mut page_cache = PageCache::new(page_cache_size);
+
+    while let Ok(req) = req_rx.recv() {
+        // Background polls aren't timed; their timing would overwrite the status line
+        let timed = !matches!(
+            req,
+            DBRequest::CheckExternalChange
+                | DBRequest::TableCounts { .. }
+                | DBRequest::TableColumns { .. }
+                | DBRequest::DatabaseInfo
+                | DBRequest::PreviousValues { .. }
+                | DBRequest::PrefetchPage(_)
+                | DBRequest::BlobHead { .. }
+        );
+        // Our own writes don't bump data_version, so drop cached pages whenever a request
+        // may change rows. All of them: triggers, views and cascades reach other tables
+        if matches!(
+            req,
+            DBRequest::UpdateCell { .. }
+                | DBRequest::UndoLastChange { .. }
+                | DBRequest::RedoLastChange { .. }
+                | DBRequest::FillDown { .. }
+                | DBRequest::DuplicateRow { .. }
+                | DBRequest::DeleteRow { .. }
+                | DBRequest::PasteRow { .. }
+                | DBRequest::InsertRow { .. }
+                | DBRequest::DeleteMatching { .. }
+                | DBRequest::Rollback
+                | DBRequest::AttachDatabase { .. }
+                | DBRequest::CreateTable { .. }
+                | DBRequest::DropTable { .. }
+                | DBRequest::TruncateTable { .. }
+                | DBRequest::RenameTable { .. }
+                | DBRequest::RenameColumn { .. }
+        ) {
+            page_cache.clear();
+        }
+        // Kept for the log line after the match, which consumes the request
+        let logged_req = logging::enabled().then(|| logging::brief(&req));
+        logging::debug(|| format!("request {}", logged_req.as_deref().unwrap_or_default()));
+        let started = Instant::now();
+        let result = match req {
+            DBRequest::LoadSchema => load_schema(&conn),
+            DBRequest::LoadTable {
+                table,
+                page,
+                page_size,
+                offset_override,
+                filter,
+                sort_by,
+                sort_dir,
+                approx_count,
+            } => {
+                let key = PageKey {
+                    table,
+                    offset: offset_override.unwrap_or(page * page_size),
+                    page_size,
+                    filter,
+                    sort_by,
+                    sort_dir,
+                    approx_count,
+                };
+                load_page(&conn, &mut page_cache, key, page)
+            }
+            DBRequest::GroupByCount { key, column } => group_by_count(&conn, &key, &column),
+            DBRequest::RunQuery { sql, limit } => run_read_only_query(&conn, &sql, limit),
+            DBRequest::PrefetchPage(key) => {
+                let page = key.offset / key.page_size.max(1);
+                load_page(&conn, &mut page_cache, key.clone(), page).map(|data| {
+                    DBResponse::PagePrefetched {
+                        key,
+                        data: Box::new(data),
+                    }
+                })
+            }
+            DBRequest::UpdateCell {
+                table,
+                rowid,
+                column,
+                column_index,
+                new_value,
+                value_type,
+                expr,
+            } => {
+                let write = match (expr, new_value) {
+                    (Some(expr), _) => CellWrite::Expr(expr),
+                    (None, Some(v)) => CellWrite::Value(v, value_type),
+                    (None, None) => CellWrite::Null,
+                };
+                check_column_position(&conn, &table, &column, column_index)
+                    .and_then(|_| update_cell(&conn, &mut history, &table, rowid, &column, write))
+            }
+            DBRequest::UndoLastChange { table } => {
+                replay_last_change(&conn, &mut history, &table, false)
+            }
+            DBRequest::RedoLastChange { table } => {
+                replay_last_change(&conn, &mut history, &table, true)
+            }
+            DBRequest::PreviousValues { table } => {
+                // Later changes overwrite earlier ones, leaving the most recent per cell
+                let values = history
+                    .get(&table)
+                    .into_iter()
+                    .flat_map(|h| &h.undo)
+                    .flat_map(UndoStep::cells)
+                    .map(|c| {
+                        (
+                            (c.rowid, c.column.clone()),
+                            value_to_opt_string((&c.prev_value).into()),
+                        )
+                    })
+                    .collect();
+                Ok(DBResponse::PreviousValues { table, values })
+            }
+            DBRequest::ExportCSV {
+                table,
+                path,
+                filter,
+                sort_by,
+                sort_dir,
+                append,
+                bom,
+                crlf,
+                quote_all,
+                delimiter,
+                rowids,
+                columns,
+                include_rowid,
+            } => {
+                let params = ExportCsvParams {
+                    table,
+                    path,
+                    filter,
+                    sort_by,
+                    sort_dir,
+                    append,
+                    bom,
+                    crlf,
+                    quote_all,
+                    delimiter,
+                    rowids,
+                    columns,
+                    include_rowid,
+                };
+                export_cancel.store(false, Ordering::Relaxed);
+                // Always answer with ExportedCSV so the UI knows the export is over
+                export_csv(&conn, &params, &export_cancel, &resp_tx).or_else(|e| {
+                    Ok(DBResponse::ExportedCSV {
+                        ok: false,
+                        path: params.path.clone(),
+                        message: Some(e.to_string()),
+                    })
+                })
+            }
+            DBRequest::AttachDatabase { path, alias } => attach_database(&conn, &path, &alias),
+            DBRequest::CheckExternalChange => read_data_version(&conn).map(|v| {
+                let changed = data_version.is_some_and(|old| old != v);
+                data_version = Some(v);
+                DBResponse::ExternalChange { changed }
+            }),
+            DBRequest::TableCounts { tables } => table_counts(&conn, &tables),
+            DBRequest::TableColumns { table } => table_ref(&conn, &table)
+                .and_then(|tref| table_columns(&conn, &tref))
+                .map(|columns| DBResponse::TableColumns { table, columns }),
+            DBRequest::CreateTable { sql } => create_table(&conn, &sql),
+            DBRequest::DropTable { table } => drop_table(&conn, &mut history, &table),
+            DBRequest::TruncateTable {
+                table,
+                reset_sequence,
+            } => truncate_table(&conn, &mut history, &table, reset_sequence),
+            DBRequest::RenameTable { table, new_name } => {
+                rename_table(&conn, &mut history, &table, &new_name)
+            }
+            DBRequest::RenameColumn {
+                table,
+                column,
+                new_name,
+            } => rename_column(&conn, &mut history, &table, &column, &new_name),
+            DBRequest::Backup { path, overwrite } => backup(&conn, &path, overwrite, &resp_tx),
+            DBRequest::IntegrityCheck => integrity_check(&conn),
+            DBRequest::Analyze => conn.execute_batch("ANALYZE").map_err(Into::into).map(|_| {
+                DBResponse::MaintenanceDone {
+                    command: "ANALYZE".into(),
+                }
+            }),
+            DBRequest::Reindex { table } => reindex(&conn, &table),
+            DBRequest::Checkpoint => checkpoint(&conn),
+            DBRequest::BlobHead {
+                table,
+                rowid,
+                column,
+                bytes,
+            } => blob_head(&conn, table, rowid, column, bytes),
+            DBRequest::SaveBlob {
+                table,
+                rowid,
+                column,
+                path,
+            } => save_blob(&conn, &table, rowid, &column, &path),
+            DBRequest::DatabaseInfo => database_info(&conn),
+            DBRequest::FillDown {
+                table,
+                column,
+                source_rowid,
+                rowids,
+            } => fill_down(&conn, &mut history, &table, &column, source_rowid, &rowids),
+            DBRequest::ColumnStats {
+                table,
+                column,
+                filter,
+            } => column_stats(&conn, &table, &column, filter),
+            DBRequest::DistinctValues {
+                table,
+                column,
+                limit,
+            } => distinct_values(&conn, &table, &column, limit),
+            DBRequest::DuplicateRow { table, rowid } => {
+                duplicate_row(&conn, &table, rowid).inspect(|_| drop_redo(&mut history, &table))
+            }
+            DBRequest::DeleteRow { table, rowid } => delete_row(&conn, &mut history, &table, rowid),
+            DBRequest::PasteRow { table, fields } => {
+                paste_row(&conn, &table, &fields).inspect(|_| drop_redo(&mut history, &table))
+            }
+            DBRequest::InsertRow { table, values } => {
+                insert_row(&conn, &table, &values).inspect(|_| drop_redo(&mut history, &table))
+            }
+            DBRequest::Begin => begin_transaction(&conn, &history, &mut txn_history),
+            DBRequest::Commit | DBRequest::Rollback => {
+                let rollback = matches!(req, DBRequest::Rollback);
+                end_transaction(&conn, &mut history, &mut txn_history, rollback).or_else(|e| {
+                    Ok(DBResponse::TransactionFailed {
+                        message: error_message(&e),
+                        open: !conn.is_autocommit(),
+                    })
+                })
+            }
+            DBRequest::DeleteMatching { table, filter } => {
+                delete_matching(&conn, &table, &filter).inspect(|_| drop_redo(&mut history, &table))
+            }
+            DBRequest::LocateRow {
+                table,
+                rowid,
+                filter,
+                sort_by,
+                sort_dir,
+            } => locate_row(&conn, &table, rowid, filter, sort_by, sort_dir),
+            DBRequest::LocateBookmark {
+                table,
+                rowids,
+                from,
+                forward,
+                filter,
+                sort_by,
+                sort_dir,
+            } => {
+                row_positions(&conn, &table, &rowids, filter, sort_by, sort_dir).map(|positions| {
+                    let next = if forward {
+                        positions
+                            .iter()
+                            .find(|(_, p)| *p > from)
+                            .or(positions.first())
+                    } else {
+                        positions
+                            .iter()
+                            .rev()
+                            .find(|(_, p)| *p < from)
+                            .or(positions.last())
+                    };
+                    DBResponse::RowLocated {
+                        table,
+                        rowid: next.map_or(rowids.first().copied().unwrap_or_default(), |n| n.0),
+                        position: next.map(|n| n.1),
+                    }
+                })
+            }
+        };
+
+        match result {
+            Ok(resp) => {
+                let _ = resp_tx.send(resp);
+            }
+            Err(e) => {
+                let msg = error_message(&e);
+                logging::log(|| {
+                    format!(
+                        "error in {}: {}",
+                        logged_req.as_deref().unwrap_or_default(),
+                        msg.replace('\n', " | ")
+                    )
+                });
+                let _ = resp_tx.send(DBResponse::Error(msg));
+            }
+        }
+        if timed {
+            logging::log(|| {
+                format!(
+                    "{:.1?} {}",
+                    started.elapsed(),
+                    logged_req.as_deref().unwrap_or_default()
+                )
+            });
+            let _ = resp_tx.send(DBResponse::Timing {
+                elapsed: started.elapsed(),
+            });
+        }
+    }
+}
+
+fn load_schema(conn: &Connection) -> Result<DBResponse> {
+    let schemas = schema_names(conn)?;
+    let mut tables = Vec::new();
+    let mut views = Vec::new();
+    let mut indexes = Vec::new();
+    let mut fts_tables = Vec::new();
+    for schema in &schemas {
+        // Objects of attached databases are addressed as "alias.name"
+        let qualify = |name: String| {
+            if schema == "main" {
+                name
+            } else {
+                format!("{}.{}", schema, name)
+            }
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT type, name, tbl_name, sql FROM {}.sqlite_master \
+             WHERE type IN ('table', 'view', 'index') AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            ident(schema)
+        ))?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get(0)?;
+            let name: String = row.get(1)?;
+            match kind.as_str() {
+                "table" => {
+                    let sql: Option<String> = row.get(3)?;
+                    if sql.as_deref().is_some_and(is_fts5_sql) {
+                        fts_tables.push(qualify(name.clone()));
+                    }
+                    tables.push(qualify(name))
+                }
+                "view" => views.push(qualify(name)),
+                _ => indexes.push(IndexInfo {
+                    name: qualify(name),
+                    table: qualify(row.get(2)?),
+                    sql: row.get(3)?,
+                }),
+            }
+        }
+    }
+    Ok(DBResponse::Schema {
+        tables,
+        views,
+        indexes,
+        schemas,
+        fts_tables,
+    })
+}
+
+// Schemas visible on this connection ("main" first, then attached aliases); "temp" is skipped
+fn schema_names(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA database_list")?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(names.into_iter().filter(|n| n != "temp").collect())
+}
+
+fn attach_database(conn: &Connection, path: &str, alias: &str) -> Result<DBResponse> {
+    conn.execute("ATTACH DATABASE ?1 AS ?2", (path, alias))?;
+    Ok(DBResponse::Attached {
+        alias: alias.to_string(),
+        path: path.to_string(),
+    })
+}
+
+fn create_table(conn: &Connection, sql: &str) -> Result<DBResponse> {
+    if !sql.trim_start().to_uppercase().starts_with("CREATE TABLE") {
+        anyhow::bail!("Only CREATE TABLE statements are accepted here");
+    }
+    conn.execute(sql, [])?;
+    Ok(DBResponse::SchemaChanged {
+        message: "Table created".into(),
+    })
+}
+
+fn drop_table(conn: &Connection, history: &mut History, table: &str) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    conn.execute(&format!("DROP TABLE IF EXISTS {}", tref.sql()), [])?;
+    // Undo entries would point at a table that no longer exists
+    history.remove(table);
+    Ok(DBResponse::TableDropped {
+        table: table.to_string(),
+    })
+}
+
+fn truncate_table(
+    conn: &Connection,
+    history: &mut History,
+    table: &str,
+    reset_sequence: bool,
+) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    if tref.is_view {
+        anyhow::bail!("Views are read-only");
+    }
+    let schema = ident(tref.schema.as_deref().unwrap_or("main"));
+    let (count, sequence_reset) = with_savepoint(conn, || {
+        let count = conn.execute(&format!("DELETE FROM {}", tref.sql()), [])?;
+        // sqlite_sequence only exists once some table uses AUTOINCREMENT
+        let has_sequence = reset_sequence
+            && conn
+                .query_row(
+                    &format!(
+                        "SELECT 1 FROM {}.sqlite_master WHERE type = 'table' AND name = 'sqlite_sequence'",
+                        schema
+                    ),
+                    [],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+        let reset = has_sequence
+            && conn.execute(
+                &format!("DELETE FROM {}.sqlite_sequence WHERE name = ?1", schema),
+                [&tref.name],
+            )? > 0;
+        Ok((count, reset))
+    })?;
+    // The rows the undo entries point at are gone
+    history.remove(table);
+    Ok(DBResponse::TableTruncated {
+        table: table.to_string(),
+        count,
+        sequence_reset,
+    })
+}
+
+fn rename_table(
+    conn: &Connection,
+    history: &mut History,
+    table: &str,
+    new_name: &str,
+) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    conn.execute(
+        &format!("ALTER TABLE {} RENAME TO {}", tref.sql(), ident(new_name)),
+        [],
+    )?;
+    let qualified = match &tref.schema {
+        Some(s) => format!("{}.{}", s, new_name),
+        None => new_name.to_string(),
+    };
+    // Keep undo history addressable under the new name
+    if let Some(mut steps) = history.remove(table) {
+        for step in steps.undo.iter_mut().chain(&mut steps.redo) {
+            step.rename_table(&qualified);
+        }
+        history.insert(qualified.clone(), steps);
+    }
+    Ok(DBResponse::TableRenamed {
+        table: table.to_string(),
+        new_name: qualified,
+    })
+}
+
+fn rename_column(
+    conn: &Connection,
+    history: &mut History,
+    table: &str,
+    column: &str,
+    new_name: &str,
+) -> Result<DBResponse> {
+    // RENAME COLUMN arrived in SQLite 3.25.0
+    if rusqlite::version_number() < 3_025_000 {
+        anyhow::bail!(
+            "Renaming columns needs SQLite 3.25 or newer (linked: {})",
+            rusqlite::version()
+        );
+    }
+    let tref = table_ref(conn, table)?;
+    conn.execute(
+        &format!(
+            "ALTER TABLE {} RENAME COLUMN {} TO {}",
+            tref.sql(),
+            ident(column),
+            ident(new_name)
+        ),
+        [],
+    )?;
+    if let Some(steps) = history.get_mut(table) {
+        for step in steps.undo.iter_mut().chain(&mut steps.redo) {
+            step.rename_column(column, new_name);
+        }
+    }
+    Ok(DBResponse::ColumnRenamed {
+        table: table.to_string(),
+        column: column.to_string(),
+        new_name: new_name.to_string(),
+    })
+}
+
+fn table_counts(conn: &Connection, tables: &[String]) -> Result<DBResponse> {
+    let mut counts = HashMap::new();
+    let mut estimated = HashSet::new();
+    // A table that can't be counted (gone since the schema load, a virtual table whose
+    // module is missing, ...) is left without a count rather than losing all of them
+    for table in tables {
+        let Ok(tref) = table_ref(conn, table) else {
+            continue;
+        };
+        if let Some(n) = stat1_row_estimate(conn, &tref) {
+            counts.insert(table.clone(), n);
+            estimated.insert(table.clone());
+            continue;
+        }
+        let count = conn.query_row(&format!("SELECT COUNT(*) FROM {}", tref.sql()), [], |row| {
+            row.get::<_, i64>(0)
+        });
+        if let Ok(n) = count {
+            counts.insert(table.clone(), n);
+        }
+    }
+    Ok(DBResponse::TableCounts { counts, estimated })
+}
+
+// First field of a sqlite_stat1 `stat` entry is the approximate row count (needs ANALYZE)
+fn stat1_row_estimate(conn: &Connection, tref: &TableRef) -> Option<i64> {
+    let stat1 = match &tref.schema {
+        Some(s) => format!("{}.sqlite_stat1", ident(s)),
+        None => "sqlite_stat1".to_string(),
+    };
+    let stat: String = conn
+        .query_row(
+            &format!("SELECT stat FROM {} WHERE tbl = ?1 LIMIT 1", stat1),
+            [&tref.name],
+            |row| row.get(0),
+        )
+        .ok()?;
+    stat.split_whitespace().next()?.parse().ok()
+}
+
+fn read_data_version(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("PRAGMA data_version", [], |row| row.get(0))?)
+}
+
+/// Recently loaded pages, so scrolling back over a page boundary doesn't re-run its query.
+/// Entries are only valid for the `data_version` they were loaded at.
+struct PageCache {
+    capacity: usize,
+    /// Page and the tick it was last used at; the oldest tick is evicted first
+    entries: HashMap<PageKey, (DBResponse, u64)>,
+    tick: u64,
+    data_version: Option<i64>,
+}
+
+impl PageCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            tick: 0,
+            data_version: None,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Forget everything if another connection committed since the pages were loaded
+    fn check_version(&mut self, version: Option<i64>) {
+        if version.is_none() || version != self.data_version {
+            self.entries.clear();
+            self.data_version = version;
+        }
+    }
+
+    fn get(&mut self, key: &PageKey) -> Option<DBResponse> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(key).map(|(page, used)| {
+            *used = tick;
+            page.clone()
+        })
+    }
+
+    fn insert(&mut self, key: PageKey, page: DBResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(k, _)| k.clone());
+            if let Some(k) = oldest {
+                self.entries.remove(&k);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(key, (page, self.tick));
+    }
+}
+
+/// `load_table` through the page cache; `page` is only echoed back in the response
+fn load_page(
+    conn: &Connection,
+    cache: &mut PageCache,
+    key: PageKey,
+    page: usize,
+) -> Result<DBResponse> {
+    cache.check_version(read_data_version(conn).ok());
+    if let Some(mut hit) = cache.get(&key) {
+        if let DBResponse::TableData { page: p, .. } = &mut hit {
+            *p = page;
+        }
+        return Ok(hit);
+    }
+    let params = LoadTableParams {
+        table: key.table.clone(),
+        page,
+        page_size: key.page_size,
+        offset_override: Some(key.offset),
+        filter: key.filter.clone(),
+        sort_by: key.sort_by.clone(),
+        sort_dir: key.sort_dir,
+        approx_count: key.approx_count,
+    };
+    let data = load_table(conn, &params)?;
+    cache.insert(key, data.clone());
+    Ok(data)
+}
+
+/// A table (or view) name resolved against the attached schemas.
+struct TableRef {
+    schema: Option<String>,
+    name: String,
+    is_view: bool,
+    /// An FTS5 virtual table: filters are full-text MATCH queries
+    is_fts5: bool,
+}
+
+impl TableRef {
+    /// A WITHOUT ROWID table (`wr` of pragma_table_list, SQLite 3.37+); its rows can't be
+    /// addressed by rowid
+    fn is_without_rowid(&self, conn: &Connection) -> bool {
+        conn.query_row(
+            "SELECT wr FROM pragma_table_list WHERE schema = ?1 AND name = ?2",
+            [self.schema.as_deref().unwrap_or("main"), &self.name],
+            |row| row.get::<_, bool>(0),
+        )
+        .unwrap_or(false)
+    }
+
+    /// Expression selected as `__rowid__` (views have no rowid)
+    fn rowid_sql(&self) -> &'static str {
+        if self.is_view { "NULL" } else { "rowid" }
+    }
+
+    /// Quoted (and schema-qualified when attached) name for use in SQL statements
+    fn sql(&self) -> String {
+        match &self.schema {
+            Some(s) => format!("{}.{}", ident(s), ident(&self.name)),
+            None => ident(&self.name),
+        }
+    }
+
+    /// Schema-aware table pragma, e.g. `PRAGMA "aux".table_info("t")`
+    fn pragma(&self, pragma: &str) -> String {
+        match &self.schema {
+            Some(s) => format!("PRAGMA {}.{}({})", ident(s), pragma, ident(&self.name)),
+            None => format!("PRAGMA {}({})", pragma, ident(&self.name)),
+        }
+    }
+}
+
+// Split an "alias.table" name into schema and table when alias is an attached database
+fn table_ref(conn: &Connection, table: &str) -> Result<TableRef> {
+    let mut schema = None;
+    let mut name = table.to_string();
+    for s in schema_names(conn)? {
+        if s == "main" {
+            continue;
+        }
+        if let Some(rest) = table.strip_prefix(&format!("{}.", s)) {
+            name = rest.to_string();
+            schema = Some(s);
+            break;
+        }
+    }
+    let master = match &schema {
+        Some(s) => format!("{}.sqlite_master", ident(s)),
+        None => "sqlite_master".to_string(),
+    };
+    let (kind, sql) = conn
+        .query_row(
+            &format!("SELECT type, sql FROM {} WHERE name = ?1", master),
+            [&name],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+        )
+        .unwrap_or_default();
+    Ok(TableRef {
+        schema,
+        name,
+        is_view: kind == "view",
+        is_fts5: sql.as_deref().is_some_and(is_fts5_sql),
+    })
+}
+
+struct LoadTableParams {
+    table: String,
+    page: usize,
+    page_size: usize,
+    offset_override: Option<usize>,
+    filter: Option<String>,
+    sort_by: Option<String>,
+    sort_dir: Option<SortDir>,
+    approx_count: bool,
+}
+
+struct ExportCsvParams {
+    table: String,
+    path: String,
+    filter: Option<String>,
+    sort_by: Option<String>,
+    sort_dir: Option<SortDir>,
+    append: bool,
+    bom: bool,
+    crlf: bool,
+    quote_all: bool,
+    delimiter: char,
+    rowids: Option<Vec<i64>>,
+    columns: Option<Vec<String>>,
+    include_rowid: bool,
+}
+
+fn column_stats(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    filter: Option<String>,
+) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    let cols_only = table_columns(conn, &tref)?;
+    let (where_sql, params) = table_filter_clause(&tref, &cols_only, filter.as_deref());
+    let c = if column == "__rowid__" {
+        "__rowid__".to_string()
+    } else {
+        ident(column)
+    };
+    let sql = format!(
+        "SELECT COUNT(*), COUNT({c}), COUNT(DISTINCT {c}), \
+         COALESCE(SUM(typeof({c}) IN ('integer', 'real')), 0), MIN({c}), MAX({c}), AVG({c}) \
+         FROM (SELECT {} as __rowid__, * FROM {}{})",
+        tref.rowid_sql(),
+        tref.sql(),
+        where_sql
+    );
+    let params_refs: Vec<&dyn rusqlite::ToSql> =
+        params.iter().map(|v| v as &dyn rusqlite::ToSql).collect();
+    let stats = conn.query_row(&sql, params_refs.as_slice(), |row| {
+        let rows: i64 = row.get(0)?;
+        let non_null: i64 = row.get(1)?;
+        let numeric: i64 = row.get(3)?;
+        let is_numeric = non_null > 0 && numeric == non_null;
+        let (min, max, avg) = if is_numeric {
+            (
+                Some(value_to_string(row.get_ref(4)?)),
+                Some(value_to_string(row.get_ref(5)?)),
+                row.get(6)?,
+            )
+        } else {
+            (None, None, None)
+        };
+        Ok(ColumnStats {
+            rows,
+            distinct: row.get(2)?,
+            nulls: rows - non_null,
+            min,
+            max,
+            avg,
+        })
+    })?;
+    Ok(DBResponse::ColumnStats {
+        table: table.to_string(),
+        column: column.to_string(),
+        stats,
+    })
+}
+
+/// Rows the distinct-values picker and `--query`'s text table show by default
+/// (--result-limit); the UI says when more were left out
+pub const DEFAULT_RESULT_LIMIT: usize = 1000;
+
+fn distinct_values(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    limit: usize,
+) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    let c = ident(column);
+    let sql = format!(
+        "SELECT {c}, COUNT(*) FROM {} GROUP BY {c} ORDER BY COUNT(*) DESC, {c} LIMIT ?1",
+        tref.sql(),
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    // Fetch one extra row to tell whether the list was cut off (-1: no LIMIT)
+    let sql_limit = if limit == 0 { -1 } else { limit as i64 + 1 };
+    let mut values = stmt
+        .query_map([sql_limit], |row| {
+            Ok((value_to_opt_string(row.get_ref(0)?), row.get::<_, i64>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let total = if limit > 0 && values.len() > limit {
+        values.truncate(limit);
+        conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM (SELECT 1 FROM {} GROUP BY {c})",
+                tref.sql()
+            ),
+            [],
+            |row| row.get::<_, i64>(0),
+        )? as usize
+    } else {
+        values.len()
+    };
+    Ok(DBResponse::DistinctValues {
+        table: table.to_string(),
+        column: column.to_string(),
+        values,
+        total,
+    })
+}
+
+fn begin_transaction(
+    conn: &Connection,
+    history: &History,
+    txn_history: &mut Option<History>,
+) -> Result<DBResponse> {
+    if !conn.is_autocommit() {
+        anyhow::bail!("A transaction is already open");
+    }
+    conn.execute_batch("BEGIN")?;
+    *txn_history = Some(history.clone());
+    Ok(DBResponse::Transaction {
+        open: true,
+        rolled_back: false,
+    })
+}
+
+fn end_transaction(
+    conn: &Connection,
+    history: &mut History,
+    txn_history: &mut Option<History>,
+    rollback: bool,
+) -> Result<DBResponse> {
+    if conn.is_autocommit() {
+        *txn_history = None;
+        anyhow::bail!("No transaction is open");
+    }
+    conn.execute_batch(if rollback { "ROLLBACK" } else { "COMMIT" })?;
+    let before = txn_history.take();
+    if rollback {
+        // The rows are back as they were at BEGIN, and so are both stacks: steps taken
+        // in the transaction are gone, and steps undone or redone in it are back where
+        // they were
+        *history = before.unwrap_or_default();
+    }
+    Ok(DBResponse::Transaction {
+        open: false,
+        rolled_back: rollback,
+    })
+}
+
+fn delete_matching(conn: &Connection, table: &str, filter: &str) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    if tref.is_view {
+        anyhow::bail!("Views are read-only");
+    }
+    let cols_only = table_columns(conn, &tref)?;
+    let (where_sql, where_params) = table_filter_clause(&tref, &cols_only, Some(filter));
+    // Never turn an empty filter into a whole-table DELETE
+    if where_sql.is_empty() {
+        anyhow::bail!("Delete needs a filter");
+    }
+    let params_refs: Vec<&dyn rusqlite::ToSql> = where_params
+        .iter()
+        .map(|v| v as &dyn rusqlite::ToSql)
+        .collect();
+    let count = with_savepoint(conn, || {
+        Ok(conn.execute(
+            &format!("DELETE FROM {}{}", tref.sql(), where_sql),
+            params_refs.as_slice(),
+        )?)
+    })?;
+    Ok(DBResponse::RowsDeleted {
+        table: table.to_string(),
+        count,
+    })
+}
+
+fn delete_row(
+    conn: &Connection,
+    history: &mut History,
+    table: &str,
+    rowid: i64,
+) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    if tref.is_view {
+        anyhow::bail!("Views are read-only");
+    }
+    // Generated columns can't be written, so undo lets SQLite recompute them
+    let columns: Vec<String> = column_infos(conn, &tref)?
+        .into_iter()
+        .filter(|(_, _, generated)| !generated)
+        .map(|(name, _, _)| name)
+        .collect();
+    let select_list = std::iter::once("rowid".to_string())
+        .chain(columns.iter().map(|c| ident(c)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if tref.is_without_rowid(conn) {
+        anyhow::bail!(
+            "{} has no rowid (WITHOUT ROWID table); rows can't be deleted here",
+            table
+        );
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM {} WHERE rowid = ?1",
+        select_list,
+        tref.sql()
+    ))?;
+    // Keep the whole row, types and all, so undo can put it back as it was
+    let values: Vec<rusqlite::types::Value> = stmt
+        .query_row([rowid], |row| {
+            (1..=columns.len()).map(|i| row.get(i)).collect()
+        })
+        .optional()?
+        .ok_or_else(|| anyhow::anyhow!("No row with rowid {} in {}", rowid, table))?;
+    conn.execute(
+        &format!("DELETE FROM {} WHERE rowid = ?1", tref.sql()),
+        [rowid],
+    )?;
+    history
+        .entry(table.to_string())
+        .or_default()
+        .push(UndoStep::RowDeleted {
+            table: table.to_string(),
+            rowid,
+            columns,
+            values,
+        });
+    Ok(DBResponse::RowDeleted {
+        table: table.to_string(),
+        rowid,
+    })
+}
+
+fn duplicate_row(conn: &Connection, table: &str, rowid: i64) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    if tref.is_view {
+        anyhow::bail!("Views are read-only");
+    }
+    // (name, declared type, pk position) per column
+    let mut stmt = conn.prepare(&tref.pragma("table_info"))?;
+    let cols = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    // A lone INTEGER PRIMARY KEY aliases the rowid; leave it out so SQLite picks a new one
+    let pk_count = cols.iter().filter(|(_, _, pk)| *pk > 0).count();
+    let copied = cols
+        .iter()
+        .filter(|(_, ty, pk)| !(pk_count == 1 && *pk == 1 && ty.eq_ignore_ascii_case("INTEGER")))
+        .map(|(name, _, _)| ident(name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let inserted = if copied.is_empty() {
+        // Nothing to copy but the rowid; the row still has to exist
+        let exists = conn
+            .query_row(
+                &format!("SELECT 1 FROM {} WHERE rowid = ?1", tref.sql()),
+                [rowid],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if exists {
+            conn.execute(&format!("INSERT INTO {} DEFAULT VALUES", tref.sql()), [])
+        } else {
+            Ok(0)
+        }
+    } else {
+        conn.execute(
+            &format!(
+                "INSERT INTO {t} ({c}) SELECT {c} FROM {t} WHERE rowid = ?1",
+                t = tref.sql(),
+                c = copied
+            ),
+            [rowid],
+        )
+    }
+    .map_err(|e| anyhow::anyhow!("Duplicate failed: {e}"))?;
+    if inserted == 0 {
+        anyhow::bail!("Duplicate failed: row {rowid} no longer exists");
+    }
+    Ok(DBResponse::RowInserted {
+        table: table.to_string(),
+        rowid: conn.last_insert_rowid(),
+    })
+}
+
+fn paste_row(conn: &Connection, table: &str, fields: &[String]) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    if tref.is_view {
+        anyhow::bail!("Views are read-only");
+    }
+    let infos = column_infos(conn, &tref)?;
+    if fields.len() != infos.len() {
+        anyhow::bail!(
+            "Paste failed: the row has {} field{} but {} has {} column{} ({})",
+            fields.len(),
+            if fields.len() == 1 { "" } else { "s" },
+            table,
+            infos.len(),
+            if infos.len() == 1 { "" } else { "s" },
+            infos
+                .iter()
+                .map(|(name, _, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    let mut names = Vec::new();
+    let mut values = Vec::new();
+    for ((name, ty, generated), field) in infos.iter().zip(fields) {
+        if *generated {
+            continue;
+        }
+        let affinity = Affinity::from_decl_type(ty);
+        let value = if field == "NULL" {
+            rusqlite::types::Value::Null
+        } else if let Some(bytes) = field
+            .strip_prefix("0x")
+            .filter(|_| affinity == Affinity::Blob)
+            .and_then(hex::decode)
+        {
+            // Copies show BLOBs as 0x..., so paste them back as bytes
+            rusqlite::types::Value::Blob(bytes)
+        } else {
+            parse_value_for(field, affinity)
+        };
+        names.push(ident(name));
+        values.push(value);
+    }
+    let sql = if names.is_empty() {
+        format!("INSERT INTO {} DEFAULT VALUES", tref.sql())
+    } else {
+        format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            tref.sql(),
+            names.join(", "),
+            (1..=names.len())
+                .map(|i| format!("?{}", i))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    };
+    conn.execute(&sql, rusqlite::params_from_iter(values))
+        .map_err(|e| anyhow::anyhow!("Paste failed: {e}"))?;
+    Ok(DBResponse::RowInserted {
+        table: table.to_string(),
+        rowid: conn.last_insert_rowid(),
+    })
+}
+
+fn insert_row(conn: &Connection, table: &str, values: &[InsertValue]) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    if tref.is_view {
+        anyhow::bail!("Views are read-only");
+    }
+    let infos = column_infos(conn, &tref)?;
+    if values.len() != infos.len() {
+        anyhow::bail!(
+            "Insert failed: {} now has {} columns (reload and try again)",
+            table,
+            infos.len()
+        );
+    }
+    // Columns left out take their DEFAULT (or NULL); a NOT NULL one without a default
+    // makes SQLite refuse the row, and that error is what the status line shows
+    let mut names = Vec::new();
+    let mut params = Vec::new();
+    for ((name, ty, generated), value) in infos.iter().zip(values) {
+        let param = match value {
+            _ if *generated => continue,
+            InsertValue::Default => continue,
+            InsertValue::Null => rusqlite::types::Value::Null,
+            InsertValue::Text(text) => parse_value_for(text, Affinity::from_decl_type(ty)),
+        };
+        names.push(ident(name));
+        params.push(param);
+    }
+    let sql = if names.is_empty() {
+        format!("INSERT INTO {} DEFAULT VALUES", tref.sql())
+    } else {
+        format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            tref.sql(),
+            names.join(", "),
+            (1..=names.len())
+                .map(|i| format!("?{}", i))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    };
+    conn.execute(&sql, rusqlite::params_from_iter(params))
+        .map_err(|e| anyhow::anyhow!("Insert failed: {e}"))?;
+    Ok(DBResponse::RowInserted {
+        table: table.to_string(),
+        rowid: conn.last_insert_rowid(),
+    })
+}
+
+fn locate_row(
+    conn: &Connection,
+    table: &str,
+    rowid: i64,
+    filter: Option<String>,
+    sort_by: Option<String>,
+    sort_dir: Option<SortDir>,
+) -> Result<DBResponse> {
+    let position = row_positions(conn, table, &[rowid], filter, sort_by, sort_dir)?
+        .first()
+        .map(|(_, p)| *p);
+    Ok(DBResponse::RowLocated {
+        table: table.to_string(),
+        rowid,
+        position,
+    })
+}
+
+// (rowid, position) of those of `rowids` the filter shows, in display order
+fn row_positions(
+    conn: &Connection,
+    table: &str,
+    rowids: &[i64],
+    filter: Option<String>,
+    sort_by: Option<String>,
+    sort_dir: Option<SortDir>,
+) -> Result<Vec<(i64, usize)>> {
+    let tref = table_ref(conn, table)?;
+    let cols_only = table_columns(conn, &tref)?;
+    let (where_sql, mut params) = table_filter_clause(&tref, &cols_only, filter.as_deref());
+    let mut order_sql = order_clause(&cols_only, sort_by.as_deref(), sort_dir);
+    let rank = ranked(&tref, filter.as_deref());
+    if order_sql.is_empty() {
+        // Unsorted pages come back in rowid order (FTS5 searches by rank)
+        order_sql = if rank {
+            " ORDER BY rank".to_string()
+        } else {
+            " ORDER BY __rowid__".to_string()
+        };
+    }
+    // One JSON array parameter, like a selection export
+    let sql = format!(
+        "SELECT __rowid__, pos FROM (SELECT __rowid__, ROW_NUMBER() OVER ({}) - 1 AS pos FROM \
+         (SELECT {} as __rowid__, {}* FROM {}{})) \
+         WHERE __rowid__ IN (SELECT value FROM json_each(?)) ORDER BY pos",
+        order_sql.trim_start(),
+        tref.rowid_sql(),
+        if rank { "rank, " } else { "" },
+        tref.sql(),
+        where_sql
+    );
+    params.push(rusqlite::types::Value::Text(serde_json::to_string(rowids)?));
+    let params_refs: Vec<&dyn rusqlite::ToSql> =
+        params.iter().map(|v| v as &dyn rusqlite::ToSql).collect();
+    let mut stmt = conn.prepare(&sql)?;
+    let positions = stmt
+        .query_map(params_refs.as_slice(), |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? as usize))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(positions)
+}
+
+/// (name, declared type, generated) per column, in declaration order. `table_info` leaves
+/// generated columns out, so this reads `table_xinfo` and drops only the hidden columns of
+/// virtual tables (hidden = 1; generated columns are 2 and 3)
+fn column_infos(conn: &Connection, tref: &TableRef) -> Result<Vec<(String, String, bool)>> {
+    let mut stmt = conn.prepare(&tref.pragma("table_xinfo"))?;
+    let infos = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(6)?,
+            ))
+        })?
+        .filter(|r| !matches!(r, Ok((_, _, 1))))
+        .map(|r| r.map(|(name, ty, hidden)| (name, ty, hidden >= 2)))
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(infos)
+}
+
+/// Column names of a table or view, in declaration order
+fn table_columns(conn: &Connection, tref: &TableRef) -> Result<Vec<String>> {
+    Ok(column_infos(conn, tref)?
+        .into_iter()
+        .map(|(name, _, _)| name)
+        .collect())
+}
+
+fn column_affinities(conn: &Connection, tref: &TableRef) -> Result<Vec<Affinity>> {
+    Ok(column_infos(conn, tref)?
+        .iter()
+        .map(|(_, ty, _)| Affinity::from_decl_type(ty))
+        .collect())
+}
+
+/// Affinity of one column (Blob, i.e. no coercion, when it isn't listed)
+fn column_affinity(conn: &Connection, tref: &TableRef, column: &str) -> Result<Affinity> {
+    let names = table_columns(conn, tref)?;
+    let affinities = column_affinities(conn, tref)?;
+    Ok(names
+        .iter()
+        .position(|n| n == column)
+        .and_then(|i| affinities.get(i).copied())
+        .unwrap_or(Affinity::Blob))
+}
+
+/// `CREATE VIRTUAL TABLE ... USING fts5(...)`
+fn is_fts5_sql(sql: &str) -> bool {
+    let sql = sql.to_ascii_uppercase();
+    sql.starts_with("CREATE VIRTUAL TABLE") && sql.contains("USING FTS5")
+}
+
+/// WHERE clause for the filter on this table: a full-text `MATCH` for FTS5 tables, else
+/// `filter_clause`
+fn table_filter_clause(
+    tref: &TableRef,
+    cols_only: &[String],
+    filter: Option<&str>,
+) -> (String, Vec<rusqlite::types::Value>) {
+    match filter {
+        // FTS5 matching follows the tokenizer, so `cs:` has nothing to change there
+        Some(f) if tref.is_fts5 && !f.trim().is_empty() => (
+            format!(" WHERE {} MATCH ?", ident(&tref.name)),
+            vec![rusqlite::types::Value::Text(
+                f.strip_prefix(CASE_SENSITIVE_PREFIX)
+                    .unwrap_or(f)
+                    .to_string(),
+            )],
+        ),
+        _ => filter_clause(cols_only, filter),
+    }
+}
+
+/// Unsorted FTS5 searches list the best matches first
+fn ranked(tref: &TableRef, filter: Option<&str>) -> bool {
+    tref.is_fts5 && filter.is_some_and(|f| !f.trim().is_empty())
+}
+
+/// Filter prefix that makes the substring search case-sensitive
+pub const CASE_SENSITIVE_PREFIX: &str = "cs:";
+
+/// WHERE clause for the filter.
+///
+/// `column=value` (for an existing column) matches that column exactly, with `column=NULL`
+/// meaning IS NULL. Space-separated `column:value` (substring) and `column=value` terms
+/// must all match, and `|` separates alternatives (see `parse_compound_filter`). Anything
+/// else is a case-insensitive substring search across all columns (cast to TEXT); with the
+/// `cs:` prefix the search uses GLOB and keeps the case.
+fn filter_clause(
+    cols_only: &[String],
+    filter: Option<&str>,
+) -> (String, Vec<rusqlite::types::Value>) {
+    let mut where_sql = String::new();
+    let mut where_params: Vec<rusqlite::types::Value> = Vec::new();
+    let Some(f) = filter else {
+        return (where_sql, where_params);
+    };
+    let (f, case_sensitive) = match f.strip_prefix(CASE_SENSITIVE_PREFIX) {
+        Some(rest) => (rest, true),
+        None => (f, false),
+    };
+    // An unknown column in a compound filter falls through to the plain search
+    if let Ok(Some(alternatives)) = parse_compound_filter(cols_only, f) {
+        let ors = alternatives
+            .iter()
+            .map(|terms| {
+                let ands = terms
+                    .iter()
+                    .map(|t| {
+                        if t.exact {
+                            equals_clause(&t.column, &t.value, &mut where_params)
+                        } else {
+                            contains_clause(&t.column, &t.value, case_sensitive, &mut where_params)
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                format!("({})", ands.join(" AND "))
+            })
+            .collect::<Vec<_>>();
+        where_sql = format!(" WHERE {}", ors.join(" OR "));
+        return (where_sql, where_params);
+    }
+    if let Some((col, value)) = split_column_filter(cols_only, f) {
+        where_sql = format!(" WHERE {}", equals_clause(col, value, &mut where_params));
+        return (where_sql, where_params);
+    }
+    if !cols_only.is_empty() {
+        let ors = cols_only
+            .iter()
+            .map(|c| contains_clause(c, f, case_sensitive, &mut where_params))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        where_sql.push_str(" WHERE ");
+        where_sql.push_str(&ors);
+    }
+    (where_sql, where_params)
+}
+
+/// `col` equals `value`: IS NULL for "NULL", else a typed comparison for numbers, a text
+/// comparison for everything else, and a blob comparison when it looks like a displayed blob
+fn equals_clause(col: &str, value: &str, params: &mut Vec<rusqlite::types::Value>) -> String {
+    let c = ident(col);
+    if value == "NULL" {
+        return format!("{} IS NULL", c);
+    }
+    let mut ors = vec![format!("{} = ?", c), format!("CAST({} AS TEXT) = ?", c)];
+    params.push(parse_value(value));
+    params.push(rusqlite::types::Value::Text(value.to_string()));
+    if let Some(blob) = value.strip_prefix("0x").and_then(hex::decode) {
+        ors.push(format!("{} = ?", c));
+        params.push(rusqlite::types::Value::Blob(blob));
+    }
+    format!("({})", ors.join(" OR "))
+}
+
+/// `col` (as TEXT) contains `value`, ignoring case unless `case_sensitive`
+fn contains_clause(
+    col: &str,
+    value: &str,
+    case_sensitive: bool,
+    params: &mut Vec<rusqlite::types::Value>,
+) -> String {
+    if case_sensitive {
+        params.push(rusqlite::types::Value::Text(format!(
+            "*{}*",
+            glob_escape(value)
+        )));
+        format!("CAST({} AS TEXT) GLOB ?", ident(col))
+    } else {
+        params.push(rusqlite::types::Value::Text(format!(
+            "%{}%",
+            value.to_lowercase()
+        )));
+        format!("unicode_lower(CAST({} AS TEXT)) LIKE ?", ident(col))
+    }
+}
+
+/// One term of a compound filter: `column:value` (substring) or `column=value` (exact)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FilterTerm {
+    column: String,
+    value: String,
+    exact: bool,
+}
+
+/// Split a filter into `|`-separated alternatives of space-separated terms (values may be
+/// "double quoted" to hold spaces or `|`). Ok(None) when some word isn't a
+/// `name:value`/`name=value` term, or it is a lone `column=value` (the plain filter handles
+/// both); Err(name) when a term names no column of the table.
+fn parse_compound_filter(
+    cols_only: &[String],
+    filter: &str,
+) -> Result<Option<Vec<Vec<FilterTerm>>>, String> {
+    let mut alternatives: Vec<Vec<String>> = vec![Vec::new()];
+    let mut word = String::new();
+    let mut quoted = false;
+    for ch in filter.chars() {
+        match ch {
+            '"' => quoted = !quoted,
+            '|' if !quoted => {
+                alternatives
+                    .last_mut()
+                    .unwrap()
+                    .extend(take_word(&mut word));
+                alternatives.push(Vec::new());
+            }
+            c if c.is_whitespace() && !quoted => alternatives
+                .last_mut()
+                .unwrap()
+                .extend(take_word(&mut word)),
+            c => word.push(c),
+        }
+    }
+    alternatives
+        .last_mut()
+        .unwrap()
+        .extend(take_word(&mut word));
+    alternatives.retain(|terms| !terms.is_empty());
+
+    let mut parsed = Vec::with_capacity(alternatives.len());
+    for words in &alternatives {
+        let mut terms = Vec::with_capacity(words.len());
+        for word in words {
+            let Some(term) = split_filter_term(cols_only, word) else {
+                return Ok(None);
+            };
+            terms.push(term);
         }
-    };
+        parsed.push(terms);
+    }
+    let lone_equals = parsed.len() == 1 && parsed[0].len() == 1 && parsed[0][0].exact;
+    if parsed.is_empty() || lone_equals {
+        return Ok(None);
+    }
+    match parsed
+        .iter()
+        .flatten()
+        .find(|t| !cols_only.contains(&t.column))
+    {
+        Some(unknown) => Err(unknown.column.clone()),
+        None => Ok(Some(parsed)),
+    }
+}
 
-    // safemode: faster reading
-    let _ = conn.pragma_update(None, "journal_mode", "WAL");
-    let _ = conn.pragma_update(None, "synchronous", "NORMAL");
+fn take_word(word: &mut String) -> Option<String> {
+    (!word.is_empty()).then(|| std::mem::take(word))
+}
 
-    // Per-table history of updates for undo functionality
-    let mut history: HashMap<String, Vec<Change>> = HashMap::new();
+// "col:value" / "col=value"; a known column wins (longest name), else the text before the
+// first ':' or '=' is taken as the (unknown) column
+fn split_filter_term(cols_only: &[String], word: &str) -> Option<FilterTerm> {
+    let term = |column: &str, sep: char, value: &str| FilterTerm {
+        column: column.to_string(),
+        value: value.to_string(),
+        exact: sep == '=',
+    };
+    let known = cols_only
+        .iter()
+        .filter_map(|c| {
+            let rest = word.strip_prefix(c.as_str())?;
+            let sep = rest.chars().next().filter(|s| matches!(s, ':' | '='))?;
+            Some(term(c, sep, &rest[1..]))
+        })
+        .max_by_key(|t| t.column.len());
+    known.or_else(|| {
+        let at = word.find([':', '=']).filter(|&i| i > 0)?;
+        Some(term(
+            &word[..at],
+            word[at..].chars().next()?,
+            &word[at + 1..],
+        ))
+    })
+}
 
-    while let Ok(req) = req_rx.recv() {
-        let result = match req {
-            DBRequest::LoadSchema => load_schema(&conn).map(|tables| DBResponse::Schema { tables }),
-            DBRequest::LoadTable {
-                table,
-                page,
-                page_size,
-                offset_override,
-                filter,
-                sort_by,
-                sort_dir,
-            } => {
-                let params = LoadTableParams {
-                    table,
-                    page,
-                    page_size,
-                    offset_override,
-                    filter,
-                    sort_by,
-                    sort_dir,
-                };
-                load_table(&conn, &params)
-            }
-            DBRequest::UpdateCell {
-                table,
-                rowid,
-                column,
-                new_value,
-            } => update_cell(&conn, &mut history, &table, rowid, &column, new_value),
-            DBRequest::UndoLastChange { table } => undo_last_change(&conn, &mut history, &table),
-            DBRequest::ExportCSV {
-                table,
-                path,
-                filter,
-                sort_by,
-                sort_dir,
-            } => export_csv(&conn, &table, &path, filter, sort_by, sort_dir),
-        };
+/// Readable form of a compound filter for the filter bar (None for other filters)
+pub fn describe_filter(cols_only: &[String], filter: &str) -> Option<String> {
+    let f = filter.strip_prefix(CASE_SENSITIVE_PREFIX).unwrap_or(filter);
+    match parse_compound_filter(cols_only, f) {
+        Ok(Some(alternatives)) => {
+            let group = |terms: &Vec<FilterTerm>| {
+                terms
+                    .iter()
+                    .map(|t| {
+                        let op = if t.exact { "=" } else { "contains" };
+                        format!("{} {} \"{}\"", t.column, op, t.value)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" AND ")
+            };
+            Some(match alternatives.as_slice() {
+                [only] => group(only),
+                _ => alternatives
+                    .iter()
+                    .map(|terms| format!("({})", group(terms)))
+                    .collect::<Vec<_>>()
+                    .join(" OR "),
+            })
+        }
+        Ok(None) => None,
+        Err(column) => Some(format!("no column {}; searching all columns", column)),
+    }
+}
 
-        match result {
-            Ok(resp) => {
-                let _ = resp_tx.send(resp);
-            }
-            Err(e) => {
-                let _ = resp_tx.send(DBResponse::Error(e.to_string()));
+// GLOB wildcards in the search text match themselves when wrapped in brackets
+fn glob_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '*' | '?' | '[' => {
+                out.push('[');
+                out.push(c);
+                out.push(']');
             }
+            c => out.push(c),
         }
     }
+    out
 }
 
-fn load_schema(conn: &Connection) -> Result<Vec<String>> {
-    let mut stmt = conn.prepare(
-        "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
-    )?;
-    let names = stmt
-        .query_map([], |row| row.get::<_, String>(0))?
-        .collect::<std::result::Result<Vec<_>, _>>()?;
-    Ok(names)
+// "col=value" -> (col, value) when col is a column of the table (longest name wins)
+fn split_column_filter<'a>(cols_only: &'a [String], filter: &'a str) -> Option<(&'a str, &'a str)> {
+    cols_only
+        .iter()
+        .filter_map(|c| {
+            filter
+                .strip_prefix(c.as_str())
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(|value| (c.as_str(), value))
+        })
+        .max_by_key(|(c, _)| c.len())
 }
 
-struct LoadTableParams {
-    table: String,
-    page: usize,
-    page_size: usize,
-    offset_override: Option<usize>,
-    filter: Option<String>,
-    sort_by: Option<String>,
-    sort_dir: Option<SortDir>,
+/// Sort column prefix that orders by the value as a number (TEXT "10" after "2")
+pub const NUMERIC_SORT_PREFIX: &str = "num:";
+
+/// The column `sort_by` names, and whether it sorts numerically
+pub fn split_numeric_sort(sort_by: &str) -> (&str, bool) {
+    match sort_by.strip_prefix(NUMERIC_SORT_PREFIX) {
+        Some(col) => (col, true),
+        None => (sort_by, false),
+    }
+}
+
+/// ORDER BY clause; ties are broken by rowid so paging (and `locate_row`) is stable
+fn order_clause(cols_only: &[String], sort_by: Option<&str>, sort_dir: Option<SortDir>) -> String {
+    let Some(sort_by) = sort_by else {
+        return String::new();
+    };
+    // A column really named "num:..." sorts as itself
+    let (col, numeric) = if cols_only.iter().any(|c| c == sort_by) {
+        (sort_by, false)
+    } else {
+        split_numeric_sort(sort_by)
+    };
+    let valid = col == "__rowid__" || cols_only.iter().any(|c| c == col);
+    if !valid {
+        return String::new();
+    }
+    let dir = match sort_dir.unwrap_or(SortDir::Asc) {
+        SortDir::Asc => "ASC",
+        SortDir::Desc => "DESC",
+    };
+    if col == "__rowid__" {
+        format!(" ORDER BY __rowid__ {}", dir)
+    } else if numeric {
+        // Text that isn't a number casts to 0; the plain value orders those among themselves
+        let c = ident(col);
+        format!(" ORDER BY CAST({c} AS REAL) {dir}, {c} {dir}, __rowid__ ASC")
+    } else {
+        format!(" ORDER BY {} {}, __rowid__ ASC", ident(col), dir)
+    }
+}
+
+/// `--export` arguments refer to a table and, if given, a column to sort by
+pub fn check_export(conn: &Connection, table: &str, sort_by: Option<&str>) -> Result<()> {
+    let tref = table_ref(conn, table)?;
+    let cols_only = table_columns(conn, &tref)?;
+    if cols_only.is_empty() {
+        anyhow::bail!("No such table: {}", table);
+    }
+    if let Some(sort_by) = sort_by
+        && order_clause(&cols_only, Some(sort_by), None).is_empty()
+    {
+        anyhow::bail!("{} has no column {} to sort by", table, sort_by);
+    }
+    Ok(())
 }
 
 fn load_table(conn: &Connection, p: &LoadTableParams) -> Result<DBResponse> {
@@ -169,65 +2350,39 @@ fn load_table(conn: &Connection, p: &LoadTableParams) -> Result<DBResponse> {
     let filter = p.filter.clone();
     let sort_by = p.sort_by.clone();
     let sort_dir = p.sort_dir;
+    let tref = table_ref(conn, table)?;
 
     // columns
-    let mut col_stmt = conn.prepare(&format!("PRAGMA table_info({})", ident(table)))?;
+    let infos = column_infos(conn, &tref)?;
+    let cols_only: Vec<String> = infos.iter().map(|(name, _, _)| name.clone()).collect();
     let mut columns: Vec<String> = vec!["__rowid__".to_string()];
-    let mut cols_only: Vec<String> = Vec::new();
-    let mut col_rows = col_stmt.query([])?;
-    while let Some(row) = col_rows.next()? {
-        let name: String = row.get(1)?;
-        columns.push(name.clone());
-        cols_only.push(name);
-    }
-
-    // Build WHERE for filter: case-insensitive substring across all columns (cast to TEXT)
-    let mut where_sql = String::new();
-    let mut where_params: Vec<rusqlite::types::Value> = Vec::new();
-    if let Some(f) = filter.as_ref() {
-        let pat = format!("%{}%", f.to_lowercase());
-        if !cols_only.is_empty() {
-            let ors = cols_only
-                .iter()
-                .map(|c| format!("LOWER(CAST({} AS TEXT)) LIKE ?", ident(c)))
-                .collect::<Vec<_>>()
-                .join(" OR ");
-            where_sql.push_str(" WHERE ");
-            where_sql.push_str(&ors);
-            for _ in &cols_only {
-                where_params.push(rusqlite::types::Value::Text(pat.clone()));
-            }
-        }
-    }
+    columns.extend(cols_only.iter().cloned());
+    let mut affinities = vec![Affinity::Integer];
+    affinities.extend(infos.iter().map(|(_, ty, _)| Affinity::from_decl_type(ty)));
+    let mut generated = vec![false];
+    generated.extend(infos.iter().map(|(_, _, g)| *g));
 
-    // Build ORDER BY
-    let mut order_sql = String::new();
-    if let Some(col) = sort_by.as_ref() {
-        let valid = col == "__rowid__" || cols_only.iter().any(|c| c == col);
-        if valid {
-            let dir = match sort_dir.unwrap_or(SortDir::Asc) {
-                SortDir::Asc => "ASC",
-                SortDir::Desc => "DESC",
-            };
-            let name = if col == "__rowid__" {
-                "__rowid__".to_string()
-            } else {
-                ident(col)
-            };
-            order_sql = format!(" ORDER BY {} {}", name, dir);
-        }
+    let (where_sql, where_params) = table_filter_clause(&tref, &cols_only, filter.as_deref());
+    let mut order_sql = order_clause(&cols_only, sort_by.as_deref(), sort_dir);
+    if order_sql.is_empty() && ranked(&tref, filter.as_deref()) {
+        order_sql = " ORDER BY rank".to_string();
+    } else if order_sql.is_empty() && !tref.is_view {
+        // Spelled out: a filter can make SQLite scan an index instead, and `locate_row`
+        // counts positions in rowid order
+        order_sql = " ORDER BY __rowid__".to_string();
     }
 
     // data page
     let offset = offset_override.unwrap_or(page * page_size);
     let sql = format!(
-        "SELECT rowid as __rowid__, {} FROM {}{}{} LIMIT ? OFFSET ?",
+        "SELECT {} as __rowid__, {} FROM {}{}{} LIMIT ? OFFSET ?",
+        tref.rowid_sql(),
         cols_only
             .iter()
             .map(|c| ident(c))
             .collect::<Vec<_>>()
             .join(", "),
-        ident(table),
+        tref.sql(),
         where_sql,
         order_sql
     );
@@ -243,17 +2398,27 @@ fn load_table(conn: &Connection, p: &LoadTableParams) -> Result<DBResponse> {
         .collect();
 
     let data_iter = data_stmt.query_map(params_refs.as_slice(), |row| {
-        row_to_strings(row, columns.len())
+        row_to_cells(row, columns.len())
     })?;
 
     let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut kinds: Vec<Vec<CellKind>> = Vec::new();
     for r in data_iter {
-        rows.push(r?);
+        let (row, row_kinds) = r?;
+        rows.push(row);
+        kinds.push(row_kinds);
     }
 
     // total count (optional; can be expensive on very large tables)
-    let count_sql = format!("SELECT COUNT(*) FROM {}{}", ident(table), where_sql);
-    let total_rows: Option<usize> = if where_sql.is_empty() {
+    let estimate = if p.approx_count && where_sql.is_empty() {
+        row_count_estimate(conn, &tref).filter(|&n| n >= APPROX_COUNT_MIN_ROWS)
+    } else {
+        None
+    };
+    let count_sql = format!("SELECT COUNT(*) FROM {}{}", tref.sql(), where_sql);
+    let total_rows: Option<usize> = if let Some(n) = estimate {
+        Some(n as usize)
+    } else if where_sql.is_empty() {
         conn.query_row(&count_sql, [], |row| row.get::<_, i64>(0))
             .ok()
             .map(|n| n as usize)
@@ -273,75 +2438,270 @@ fn load_table(conn: &Connection, p: &LoadTableParams) -> Result<DBResponse> {
     Ok(DBResponse::TableData {
         table: table.to_string(),
         columns,
+        affinities,
+        generated,
         rows,
+        kinds,
         page,
         total_rows,
+        total_estimated: estimate.is_some(),
+    })
+}
+
+/// Below this many rows `approx_count` still counts exactly; COUNT(*) is quick there
+const APPROX_COUNT_MIN_ROWS: i64 = 100_000;
+
+/// Cheap row count guess: sqlite_stat1 (after ANALYZE), else MAX(rowid), which is exact
+/// until rows are deleted. None for views and WITHOUT ROWID tables
+fn row_count_estimate(conn: &Connection, tref: &TableRef) -> Option<i64> {
+    if tref.is_view {
+        return None;
+    }
+    stat1_row_estimate(conn, tref).or_else(|| {
+        conn.query_row(
+            &format!("SELECT MAX(rowid) FROM {}", tref.sql()),
+            [],
+            |row| row.get::<_, Option<i64>>(0),
+        )
+        .ok()
+        .flatten()
+    })
+}
+
+/// Header of the count column in the grouped view
+pub const GROUP_COUNT_COLUMN: &str = "COUNT(*)";
+
+fn group_by_count(conn: &Connection, key: &PageKey, column: &str) -> Result<DBResponse> {
+    let tref = table_ref(conn, &key.table)?;
+    let infos = column_infos(conn, &tref)?;
+    let cols_only: Vec<String> = infos.iter().map(|(name, _, _)| name.clone()).collect();
+    let Some(affinity) = infos
+        .iter()
+        .find(|(name, _, _)| name == column)
+        .map(|(_, ty, _)| Affinity::from_decl_type(ty))
+    else {
+        anyhow::bail!("No column {} in {}", column, key.table);
+    };
+    let (where_sql, where_params) = table_filter_clause(&tref, &cols_only, key.filter.as_deref());
+    let c = ident(column);
+    let dir = match key.sort_dir.unwrap_or(SortDir::Asc) {
+        SortDir::Asc => "ASC",
+        SortDir::Desc => "DESC",
+    };
+    let order_sql = match key.sort_by.as_deref() {
+        Some(s) if s == column => format!("{c} {dir}"),
+        Some(GROUP_COUNT_COLUMN) => format!("COUNT(*) {dir}, {c}"),
+        _ => format!("COUNT(*) DESC, {c}"),
+    };
+    let sql = format!(
+        "SELECT NULL AS __rowid__, {c}, COUNT(*) FROM {}{} GROUP BY {c} ORDER BY {} \
+         LIMIT ? OFFSET ?",
+        tref.sql(),
+        where_sql,
+        order_sql
+    );
+    let mut params = where_params.clone();
+    params.push(rusqlite::types::Value::Integer(key.page_size as i64));
+    params.push(rusqlite::types::Value::Integer(key.offset as i64));
+    let params_refs: Vec<&dyn rusqlite::ToSql> =
+        params.iter().map(|v| v as &dyn rusqlite::ToSql).collect();
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = Vec::new();
+    let mut kinds = Vec::new();
+    for r in stmt.query_map(params_refs.as_slice(), |row| row_to_cells(row, 3))? {
+        let (row, row_kinds) = r?;
+        rows.push(row);
+        kinds.push(row_kinds);
+    }
+
+    let count_refs: Vec<&dyn rusqlite::ToSql> = where_params
+        .iter()
+        .map(|v| v as &dyn rusqlite::ToSql)
+        .collect();
+    let total_rows = conn
+        .query_row(
+            &format!(
+                "SELECT COUNT(*) FROM (SELECT 1 FROM {}{} GROUP BY {c})",
+                tref.sql(),
+                where_sql
+            ),
+            count_refs.as_slice(),
+            |row| row.get::<_, i64>(0),
+        )
+        .ok()
+        .map(|n| n as usize);
+
+    Ok(DBResponse::TableData {
+        table: key.table.clone(),
+        columns: vec![
+            "__rowid__".to_string(),
+            column.to_string(),
+            GROUP_COUNT_COLUMN.to_string(),
+        ],
+        affinities: vec![Affinity::Integer, affinity, Affinity::Integer],
+        generated: vec![false; 3],
+        rows,
+        kinds,
+        page: key.offset / key.page_size.max(1),
+        total_rows,
+        total_estimated: false,
     })
 }
 
 fn export_csv(
     conn: &Connection,
-    table: &str,
-    path: &str,
-    filter: Option<String>,
-    sort_by: Option<String>,
-    sort_dir: Option<SortDir>,
+    p: &ExportCsvParams,
+    cancel: &AtomicBool,
+    resp_tx: &Sender<DBResponse>,
 ) -> Result<DBResponse> {
-    // Build columns
-    let mut col_stmt = conn.prepare(&format!("PRAGMA table_info({})", ident(table)))?;
-    let mut cols_only: Vec<String> = Vec::new();
-    let mut col_rows = col_stmt.query([])?;
-    while let Some(row) = col_rows.next()? {
-        let name: String = row.get(1)?;
-        cols_only.push(name);
-    }
-
-    // WHERE
-    let mut where_sql = String::new();
-    let mut where_params: Vec<rusqlite::types::Value> = Vec::new();
-    if let Some(f) = filter.as_ref() {
-        let pat = format!("%{}%", f.to_lowercase());
-        if !cols_only.is_empty() {
-            let ors = cols_only
-                .iter()
-                .map(|c| format!("LOWER(CAST({} AS TEXT)) LIKE ?", ident(c)))
-                .collect::<Vec<_>>()
-                .join(" OR ");
-            where_sql.push_str(" WHERE ");
-            where_sql.push_str(&ors);
-            for _ in &cols_only {
-                where_params.push(rusqlite::types::Value::Text(pat.clone()));
+    let path = p.path.as_str();
+    let file = if p.append {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)?
+    } else {
+        File::create(path)?
+    };
+    // Only the first export into a file gets a header (and the BOM)
+    let start_len = file.metadata()?.len();
+    let fresh = start_len == 0;
+    let mut w = BufWriter::new(file);
+    let mut last_progress = Instant::now();
+    let written = export_table(
+        conn,
+        &p.table,
+        ExportOptions {
+            format: ExportFormat::Csv,
+            header: fresh,
+            bom: p.bom && fresh,
+            crlf: p.crlf,
+            quote_all: p.quote_all,
+            delimiter: p.delimiter,
+        },
+        RowScope {
+            filter: p.filter.as_deref(),
+            sort_by: p.sort_by.as_deref(),
+            sort_dir: p.sort_dir,
+            rowids: p.rowids.as_deref(),
+            columns: p.columns.as_deref(),
+            include_rowid: p.include_rowid,
+        },
+        &mut w,
+        &mut |rows_written| {
+            // Throttled so a fast export doesn't flood the UI with redraws
+            if last_progress.elapsed() >= EXPORT_PROGRESS_INTERVAL {
+                last_progress = Instant::now();
+                let _ = resp_tx.send(DBResponse::ExportProgress { rows_written });
+            }
+            !cancel.load(Ordering::Relaxed)
+        },
+    )
+    .and_then(|n| {
+        w.flush()?;
+        Ok(n)
+    });
+    match written {
+        Ok(_) => Ok(DBResponse::ExportedCSV {
+            ok: true,
+            path: path.to_string(),
+            message: None,
+        }),
+        Err(e) => {
+            // Don't leave a partial export behind: remove a new file, or cut an appended
+            // one back to what it held before
+            drop(w);
+            if p.append && !fresh {
+                if let Ok(f) = std::fs::OpenOptions::new().write(true).open(path) {
+                    let _ = f.set_len(start_len);
+                }
+            } else {
+                let _ = std::fs::remove_file(path);
             }
+            Ok(DBResponse::ExportedCSV {
+                ok: false,
+                path: path.to_string(),
+                message: Some(e.to_string()),
+            })
         }
     }
+}
 
-    // ORDER BY
-    let mut order_sql = String::new();
-    if let Some(col) = sort_by.as_ref() {
-        let valid = col == "__rowid__" || cols_only.iter().any(|c| c == col);
-        if valid {
-            let dir = match sort_dir.unwrap_or(SortDir::Asc) {
-                SortDir::Asc => "ASC",
-                SortDir::Desc => "DESC",
-            };
-            let name = if col == "__rowid__" {
-                "__rowid__".to_string()
+/// How often (in rows) `export_table` checks back with its caller
+const EXPORT_CHECK_ROWS: usize = 1000;
+/// Minimum time between `ExportProgress` messages
+const EXPORT_PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Stream a table (filtered and sorted like the data pane) to `w` in the given format.
+/// `keep_going` is called with the rows written so far every `EXPORT_CHECK_ROWS` rows;
+/// returning false stops with an "Export cancelled" error. Returns the number of rows written.
+pub fn export_table<W: Write>(
+    conn: &Connection,
+    table: &str,
+    options: ExportOptions,
+    scope: RowScope<'_>,
+    w: &mut W,
+    keep_going: &mut dyn FnMut(usize) -> bool,
+) -> Result<usize> {
+    let tref = table_ref(conn, table)?;
+    let cols_only = table_columns(conn, &tref)?;
+    if cols_only.is_empty() {
+        anyhow::bail!("No such table: {}", table);
+    }
+    let (where_sql, where_params) = match scope.rowids {
+        // One JSON array parameter, so large selections don't hit the variable limit
+        Some(ids) => (
+            format!(
+                " WHERE {} IN (SELECT value FROM json_each(?))",
+                tref.rowid_sql()
+            ),
+            vec![rusqlite::types::Value::Text(serde_json::to_string(ids)?)],
+        ),
+        None => table_filter_clause(&tref, &cols_only, scope.filter),
+    };
+    let mut order_sql = order_clause(&cols_only, scope.sort_by, scope.sort_dir);
+    if order_sql.is_empty() && scope.rowids.is_none() && ranked(&tref, scope.filter) {
+        order_sql = " ORDER BY rank".to_string();
+    }
+
+    // The header is the projection; the SELECT list follows it
+    let columns = scope.columns.unwrap_or(&cols_only);
+    if let Some(unknown) = columns
+        .iter()
+        .find(|c| *c != "__rowid__" && !cols_only.contains(c))
+    {
+        anyhow::bail!("No column {} in {}", unknown, table);
+    }
+    let header: Vec<String> = scope
+        .include_rowid
+        .then(|| "__rowid__".to_string())
+        .into_iter()
+        .chain(columns.iter().filter(|c| *c != "__rowid__").cloned())
+        .collect();
+    if header.is_empty() {
+        anyhow::bail!("No columns to export");
+    }
+    let mut select_list = header
+        .iter()
+        .map(|c| {
+            if c == "__rowid__" {
+                format!("{} AS __rowid__", tref.rowid_sql())
             } else {
-                ident(col)
-            };
-            order_sql = format!(" ORDER BY {} {}", name, dir);
-        }
+                ident(c)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    // ORDER BY breaks ties on __rowid__, so select it (unwritten) when it isn't exported
+    if !header.iter().any(|c| c == "__rowid__") {
+        select_list.push_str(&format!(", {} AS __rowid__", tref.rowid_sql()));
     }
 
     // Prepare query
     let sql = format!(
-        "SELECT rowid as __rowid__, {} FROM {}{}{}",
-        cols_only
-            .iter()
-            .map(|c| ident(c))
-            .collect::<Vec<_>>()
-            .join(", "),
-        ident(table),
+        "SELECT {} FROM {}{}{}",
+        select_list,
+        tref.sql(),
         where_sql,
         order_sql
     );
@@ -351,48 +2711,396 @@ fn export_csv(
         .map(|v| v as &dyn rusqlite::ToSql)
         .collect();
 
-    // Open file
-    let file = File::create(path)?;
-    let mut w = BufWriter::new(file);
-
-    // Write header
-    let mut header = Vec::with_capacity(cols_only.len() + 1);
-    header.push("__rowid__".to_string());
-    header.extend(cols_only.iter().cloned());
-    write_csv_row(&mut w, &header)?;
+    let ncols = header.len();
+    let mut out = RecordWriter::new(w, options, header)?;
 
     // Stream rows
-    let mut rows = if params_refs.is_empty() {
-        stmt.query([])
-    } else {
-        stmt.query(params_refs.as_slice())
-    }?;
+    let mut rows = stmt.query(params_refs.as_slice())?;
+    let mut written = 0usize;
     while let Some(row) = rows.next()? {
-        let ncols = header.len();
-        let mut values = Vec::with_capacity(ncols);
-        for i in 0..ncols {
-            let v = row.get_ref(i)?;
-            values.push(value_to_string(v));
+        let values = (0..ncols)
+            .map(|i| row.get_ref(i))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        out.write(&values)?;
+        written += 1;
+        if written.is_multiple_of(EXPORT_CHECK_ROWS) && !keep_going(written) {
+            anyhow::bail!("Export cancelled");
+        }
+    }
+    Ok(out.finish()?)
+}
+
+/// Run one statement; with a `limit`, only that many rows are kept. The rest are counted
+/// when `count_all` is set; otherwise it stops at the first row past the limit
+pub fn run_query(
+    conn: &Connection,
+    sql: &str,
+    limit: Option<usize>,
+    count_all: bool,
+) -> Result<QueryOutput> {
+    let mut stmt = conn.prepare(sql)?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    if columns.is_empty() {
+        let changes = stmt.execute([])?;
+        return Ok(QueryOutput {
+            columns,
+            rows: Vec::new(),
+            total_rows: 0,
+            changes,
+        });
+    }
+    let ncols = columns.len();
+    let mut rows = Vec::new();
+    let mut total_rows = 0;
+    let mut result = stmt.query([])?;
+    while let Some(row) = result.next()? {
+        total_rows += 1;
+        if limit.is_some_and(|limit| total_rows > limit) && !count_all {
+            break;
+        }
+        if limit.is_none_or(|limit| rows.len() < limit) {
+            rows.push(
+                (0..ncols)
+                    .map(|i| row.get::<_, rusqlite::types::Value>(i))
+                    .collect::<rusqlite::Result<Vec<_>>>()?,
+            );
+        }
+    }
+    Ok(QueryOutput {
+        columns,
+        rows,
+        total_rows,
+        changes: 0,
+    })
+}
+
+// The `:` query bar is for looking, not changing: SELECT / WITH only, and nothing that
+// SQLite says writes (WITH … DELETE starts like a query)
+fn run_read_only_query(conn: &Connection, sql: &str, limit: usize) -> Result<DBResponse> {
+    let keyword = sql
+        .trim_start()
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default();
+    if !(keyword.eq_ignore_ascii_case("select") || keyword.eq_ignore_ascii_case("with")) {
+        anyhow::bail!("Only SELECT and WITH queries can run here");
+    }
+    if !conn.prepare(sql)?.readonly() {
+        anyhow::bail!("This query would change the database; only read-only queries can run here");
+    }
+    // Counting a huge result past the limit would hold up the worker; one row more
+    // says there are more
+    let out = run_query(conn, sql, (limit > 0).then_some(limit), false)?;
+    let columns = std::iter::once("__rowid__".to_string())
+        .chain(out.columns)
+        .collect();
+    let (rows, kinds) = out
+        .rows
+        .iter()
+        .map(|row| {
+            std::iter::once(&rusqlite::types::Value::Null)
+                .chain(row)
+                .map(|v| (display_value(v), CellKind::from(ValueRef::from(v))))
+                .unzip()
+        })
+        .unzip();
+    Ok(DBResponse::QueryResult {
+        sql: sql.to_string(),
+        columns,
+        rows,
+        kinds,
+        total_rows: out.total_rows,
+    })
+}
+
+/// Display form of an owned value (same as cells in the data pane)
+pub fn display_value(v: &rusqlite::types::Value) -> String {
+    value_to_string(v.into())
+}
+
+/// Writes a header and records in one of the export formats
+pub struct RecordWriter<'a, W: Write> {
+    w: &'a mut W,
+    format: ExportFormat,
+    delimiter: char,
+    crlf: bool,
+    quote_all: bool,
+    header: Vec<String>,
+    count: usize,
+}
+
+impl<'a, W: Write> RecordWriter<'a, W> {
+    pub fn new(
+        w: &'a mut W,
+        options: impl Into<ExportOptions>,
+        header: Vec<String>,
+    ) -> std::io::Result<Self> {
+        let ExportOptions {
+            format,
+            header: with_header,
+            bom,
+            crlf,
+            quote_all,
+            delimiter,
+        } = options.into();
+        if bom {
+            w.write_all("\u{feff}".as_bytes())?;
+        }
+        match format {
+            ExportFormat::Csv if with_header => {
+                write_csv_row(w, &header, delimiter, crlf, quote_all)?
+            }
+            ExportFormat::Tsv if with_header => write_tsv_row(w, &header)?,
+            ExportFormat::Json => w.write_all(b"[")?,
+            _ => {}
+        }
+        Ok(Self {
+            w,
+            format,
+            delimiter,
+            crlf,
+            quote_all,
+            header,
+            count: 0,
+        })
+    }
+
+    pub fn write(&mut self, values: &[ValueRef<'_>]) -> std::io::Result<()> {
+        match self.format {
+            ExportFormat::Csv | ExportFormat::Tsv => {
+                let values: Vec<String> = values.iter().map(|v| value_to_string(*v)).collect();
+                if self.format == ExportFormat::Csv {
+                    write_csv_row(self.w, &values, self.delimiter, self.crlf, self.quote_all)?;
+                } else {
+                    write_tsv_row(self.w, &values)?;
+                }
+            }
+            ExportFormat::Json => {
+                self.w
+                    .write_all(if self.count == 0 { b"\n  " } else { b",\n  " })?;
+                write_json_object(self.w, &self.header, values)?;
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Close the output (JSON needs its closing bracket); returns the record count
+    pub fn finish(self) -> std::io::Result<usize> {
+        if self.format == ExportFormat::Json {
+            self.w
+                .write_all(if self.count == 0 { b"]\n" } else { b"\n]\n" })?;
+        }
+        Ok(self.count)
+    }
+}
+
+/// Grid cells in an export format, for copying: each cell is its display text and storage
+/// class (so JSON keeps numbers and nulls). With `one_record` the first row is written bare:
+/// no header line, no trailing newline, and a JSON object instead of an array
+pub fn format_cells<'a>(
+    format: ExportFormat,
+    header: Vec<String>,
+    rows: &[Vec<(&'a str, Option<CellKind>)>],
+    one_record: bool,
+) -> String {
+    let values = |row: &[(&'a str, Option<CellKind>)]| -> Vec<ValueRef<'a>> {
+        row.iter()
+            .map(|&(text, kind)| cell_value(text, kind))
+            .collect()
+    };
+    let mut out = Vec::new();
+    let written = if one_record && format == ExportFormat::Json {
+        rows.first().map_or(Ok(()), |row| {
+            write_json_object(&mut out, &header, &values(row))
+        })
+    } else {
+        let options = ExportOptions {
+            header: !one_record,
+            ..format.into()
+        };
+        RecordWriter::new(&mut out, options, header).and_then(|mut w| {
+            for row in rows.iter().take(if one_record { 1 } else { rows.len() }) {
+                w.write(&values(row))?;
+            }
+            w.finish().map(|_| ())
+        })
+    };
+    written.expect("writing to a Vec can't fail");
+    let mut text = String::from_utf8_lossy(&out).into_owned();
+    if one_record {
+        text.truncate(text.trim_end_matches(['\r', '\n']).len());
+    }
+    text
+}
+
+// Back from display text to a value; blobs (and large-blob placeholders) stay text
+fn cell_value(text: &str, kind: Option<CellKind>) -> ValueRef<'_> {
+    match kind {
+        Some(CellKind::Null) => ValueRef::Null,
+        Some(CellKind::Integer) => text
+            .parse()
+            .map_or(ValueRef::Text(text.as_bytes()), ValueRef::Integer),
+        Some(CellKind::Real) => text
+            .parse()
+            .map_or(ValueRef::Text(text.as_bytes()), ValueRef::Real),
+        _ => ValueRef::Text(text.as_bytes()),
+    }
+}
+
+/// One record as a JSON object. Written by hand to keep keys in column order
+pub fn write_json_object<W: Write>(
+    w: &mut W,
+    header: &[String],
+    values: &[ValueRef<'_>],
+) -> std::io::Result<()> {
+    w.write_all(b"{")?;
+    for (i, (name, v)) in header.iter().zip(values).enumerate() {
+        if i > 0 {
+            w.write_all(b", ")?;
+        }
+        serde_json::to_writer(&mut *w, name).map_err(std::io::Error::from)?;
+        w.write_all(b": ")?;
+        serde_json::to_writer(&mut *w, &value_to_json(*v)).map_err(std::io::Error::from)?;
+    }
+    w.write_all(b"}")
+}
+
+// Numbers stay numbers, NULL becomes null, blobs use their "0x..." display form
+fn value_to_json(v: ValueRef<'_>) -> serde_json::Value {
+    match v {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned().into(),
+        ValueRef::Blob(b) => format!("0x{}", hex::encode(b)).into(),
+    }
+}
+
+// Tabs, newlines and backslashes are escaped so each record stays on one line
+fn write_tsv_row<W: Write>(w: &mut W, cols: &[String]) -> std::io::Result<()> {
+    let line = cols
+        .iter()
+        .map(|c| {
+            c.replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r")
+        })
+        .collect::<Vec<_>>()
+        .join("\t");
+    w.write_all(line.as_bytes())?;
+    w.write_all(b"\n")
+}
+
+/// Fields of one pasted TSV or CSV record (the formats `RecordWriter` writes). CSV fields may
+/// hold tabs, so both readings are tried and the first whose field count is in `counts` wins;
+/// otherwise TSV is assumed when the first line has a tab. A trailing line break is fine, a
+/// second record is an error
+pub fn parse_clipboard_row(
+    text: &str,
+    counts: &[usize],
+) -> std::result::Result<Vec<String>, String> {
+    let text = text.trim_end_matches(['\r', '\n']);
+    if text.is_empty() {
+        return Err("the clipboard is empty".into());
+    }
+    let tsv = || -> Vec<Vec<String>> {
+        text.lines()
+            .map(|line| line.split('\t').map(unescape_tsv).collect())
+            .collect()
+    };
+    let mut readings = Vec::new();
+    if text.lines().next().is_some_and(|l| l.contains('\t')) {
+        readings.push(Ok(tsv()));
+    }
+    readings.push(parse_csv_records(text));
+    let fits = |r: &std::result::Result<Vec<Vec<String>>, String>| matches!(r, Ok(records) if records.len() == 1 && counts.contains(&records[0].len()));
+    let pick = readings.iter().position(fits).unwrap_or(0);
+    let records = readings.swap_remove(pick)?;
+    match records.len() {
+        1 => Ok(records.into_iter().next().unwrap_or_default()),
+        n => Err(format!(
+            "the clipboard holds {} records; copy a single row (C)",
+            n
+        )),
+    }
+}
+
+// Reverse of the escaping in `write_tsv_row`
+fn unescape_tsv(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
         }
-        write_csv_row(&mut w, &values)?;
     }
+    out
+}
 
-    w.flush()?;
-    Ok(DBResponse::ExportedCSV {
-        ok: true,
-        path: path.to_string(),
-        message: None,
-    })
+// Comma-separated records with "..." quoting ("" inside quotes is one quote)
+fn parse_csv_records(text: &str) -> std::result::Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                fields.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut fields));
+            }
+            c => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("unterminated quoted field".into());
+    }
+    fields.push(field);
+    records.push(fields);
+    Ok(records)
 }
-fn write_csv_row<W: Write>(w: &mut W, cols: &[String]) -> std::io::Result<()> {
+
+fn write_csv_row<W: Write>(
+    w: &mut W,
+    cols: &[String],
+    delimiter: char,
+    crlf: bool,
+    quote_all: bool,
+) -> std::io::Result<()> {
+    let mut sep = [0u8; 4];
+    let sep = delimiter.encode_utf8(&mut sep).as_bytes();
     let mut first = true;
     for col in cols {
         if !first {
-            w.write_all(b",")?;
+            w.write_all(sep)?;
         }
         first = false;
-        let needs_quotes =
-            col.contains(',') || col.contains('"') || col.contains('\n') || col.contains('\r');
+        let needs_quotes = quote_all
+            || col.contains(delimiter)
+            || col.contains('"')
+            || col.contains('\n')
+            || col.contains('\r');
         if needs_quotes {
             let escaped = col.replace('"', "\"\"");
             w.write_all(b"\"")?;
@@ -402,117 +3110,655 @@ fn write_csv_row<W: Write>(w: &mut W, cols: &[String]) -> std::io::Result<()> {
             w.write_all(col.as_bytes())?;
         }
     }
-    w.write_all(b"\n")?;
+    w.write_all(if crlf { b"\r\n" } else { b"\n" })?;
     Ok(())
 }
 
+/// One cell write; the values are kept as stored so undo and redo write back the same
+/// type and bytes (a BLOB, "007" as TEXT)
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 struct Change {
     table: String,
     rowid: i64,
     column: String,
-    prev_value: Option<String>,
-    new_value: Option<String>,
+    prev_value: rusqlite::types::Value,
+    new_value: rusqlite::types::Value,
+}
+
+/// What one `u` reverts (and Ctrl+r applies again)
+#[derive(Debug, Clone)]
+enum UndoStep {
+    /// A single edit, or every cell of a batch such as a fill-down
+    Cells(Vec<Change>),
+    /// A deleted row: its rowid and the values of its (non-generated) columns
+    RowDeleted {
+        table: String,
+        rowid: i64,
+        columns: Vec<String>,
+        values: Vec<rusqlite::types::Value>,
+    },
+}
+
+impl UndoStep {
+    fn cells(&self) -> &[Change] {
+        match self {
+            UndoStep::Cells(changes) => changes,
+            UndoStep::RowDeleted { .. } => &[],
+        }
+    }
+
+    fn rename_table(&mut self, new_name: &str) {
+        match self {
+            UndoStep::Cells(changes) => {
+                for change in changes {
+                    change.table = new_name.to_string();
+                }
+            }
+            UndoStep::RowDeleted { table, .. } => *table = new_name.to_string(),
+        }
+    }
+
+    fn rename_column(&mut self, column: &str, new_name: &str) {
+        let names: Vec<&mut String> = match self {
+            UndoStep::Cells(changes) => changes.iter_mut().map(|c| &mut c.column).collect(),
+            UndoStep::RowDeleted { columns, .. } => columns.iter_mut().collect(),
+        };
+        for name in names.into_iter().filter(|n| *n == column) {
+            *name = new_name.to_string();
+        }
+    }
+}
+
+/// Undo and redo steps of one table, oldest first
+#[derive(Debug, Clone, Default)]
+struct TableHistory {
+    undo: Vec<UndoStep>,
+    /// Steps `u` reverted, newest last; a fresh change drops them
+    redo: Vec<UndoStep>,
+}
+
+impl TableHistory {
+    fn push(&mut self, step: UndoStep) {
+        self.undo.push(step);
+        self.redo.clear();
+    }
+}
+
+type History = HashMap<String, TableHistory>;
+
+// A write that isn't undoable still changes the rows redo steps were recorded against
+fn drop_redo(history: &mut History, table: &str) {
+    if let Some(h) = history.get_mut(table) {
+        h.redo.clear();
+    }
+}
+
+// The column is still where the app saw it; otherwise the edit could land in another column
+fn check_column_position(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    column_index: usize,
+) -> Result<()> {
+    let tref = table_ref(conn, table)?;
+    let columns = table_columns(conn, &tref)?;
+    if columns.get(column_index).map(String::as_str) != Some(column) {
+        anyhow::bail!(
+            "Schema of {} changed since it was loaded ({} moved or is gone); reload with r",
+            table,
+            column
+        );
+    }
+    Ok(())
 }
 
 fn update_cell(
     conn: &Connection,
-    history: &mut HashMap<String, Vec<Change>>,
+    history: &mut History,
     table: &str,
     rowid: i64,
     column: &str,
-    new_value: Option<String>,
+    write: CellWrite,
 ) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+
     // Fetch previous value for history
-    let prev_value: Option<String> = {
+    let read_value = || -> Result<rusqlite::types::Value> {
         let sql = format!(
             "SELECT {} FROM {} WHERE rowid = ?1",
             ident(column),
-            ident(table)
+            tref.sql()
         );
         let mut stmt_prev = conn.prepare(&sql)?;
-        stmt_prev
-            .query_row([rowid], |row| {
-                let v = row.get_ref(0)?;
-                Ok(value_to_opt_string(v))
-            })
-            .ok()
-            .flatten()
+        match stmt_prev
+            .query_row([rowid], |row| Ok(stored_value(row.get_ref(0)?)))
+            .optional()?
+        {
+            Some(value) => value,
+            // The row is gone; the UPDATE below changes nothing
+            None => Ok(rusqlite::types::Value::Null),
+        }
     };
+    let prev_value = read_value()?;
 
-    // naive type handling: try to bind as integer/real if it parses, else as text; allow NULL
+    let affinity = column_affinity(conn, &tref, column)?;
+    // Allowlisted expressions go into the SQL; everything else is bound
+    let (value_sql, value_param) = match &write {
+        CellWrite::Null => ("?2", Some(rusqlite::types::Value::Null)),
+        CellWrite::Value(s, value_type) => ("?2", Some(typed_value(s, *value_type, affinity)?)),
+        CellWrite::Expr(expr) => (expr.sql(), None),
+    };
     let mut stmt = conn.prepare(&format!(
-        "UPDATE {} SET {} = ?1 WHERE rowid = ?2",
-        ident(table),
+        "UPDATE {} SET {} = {} WHERE rowid = ?1",
+        tref.sql(),
         ident(column),
+        value_sql,
     ))?;
     let mut ok = true;
     let mut msg = None;
-    let new_value_clone = new_value.clone();
-    let value_param = match new_value_clone {
-        None => rusqlite::types::Value::Null,
-        Some(ref s) => parse_value(s),
+    let mut rows = 0;
+    let result = match value_param {
+        Some(value) => stmt.execute((rowid, value)),
+        None => stmt.execute([rowid]),
     };
-    if let Err(e) = stmt.execute((value_param, rowid)) {
-        ok = false;
-        msg = Some(e.to_string());
-    } else {
-        // push to per-table history on success
-        let entry = Change {
-            table: table.to_string(),
-            rowid,
-            column: column.to_string(),
-            prev_value,
-            new_value,
-        };
-        history.entry(table.to_string()).or_default().push(entry);
+    match result {
+        Err(e) => {
+            ok = false;
+            let written = match &write {
+                CellWrite::Null => "NULL",
+                CellWrite::Value(s, _) => s.as_str(),
+                CellWrite::Expr(expr) => expr.sql(),
+            };
+            msg = Some(match constraint_message(&e, column, written) {
+                Some(m) => m,
+                None if is_locked(&e) => LOCKED_MESSAGE.to_string(),
+                None => e.to_string(),
+            });
+        }
+        // Nothing changed, so there is nothing to undo either
+        Ok(0) => {}
+        Ok(n) => {
+            rows = n;
+            // push to per-table history on success; the value as stored (after column
+            // affinity, or an evaluated expression), so redo writes back exactly that
+            let new_value = read_value()?;
+            let entry = Change {
+                table: table.to_string(),
+                rowid,
+                column: column.to_string(),
+                prev_value,
+                new_value,
+            };
+            history
+                .entry(table.to_string())
+                .or_default()
+                .push(UndoStep::Cells(vec![entry]));
+        }
     }
     Ok(DBResponse::CellUpdated {
         ok,
-        message: msg.or_else(|| Some("OK".into())),
+        rows,
+        message: msg,
+    })
+}
+
+/// Shown when another connection still holds the lock after the busy timeout
+const LOCKED_MESSAGE: &str = "Database is locked by another process — try again";
+
+fn is_locked(e: &rusqlite::Error) -> bool {
+    matches!(
+        e.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// What `DBResponse::Error` says for a failed request
+fn error_message(e: &anyhow::Error) -> String {
+    if is_locked_error(e) {
+        format!("{LOCKED_MESSAGE}\n{e}")
+    } else {
+        e.to_string()
+    }
+}
+
+// Some write paths re-wrap SQLite errors as text ("Duplicate failed: database is locked")
+fn is_locked_error(e: &anyhow::Error) -> bool {
+    e.chain()
+        .any(|c| c.downcast_ref::<rusqlite::Error>().is_some_and(is_locked))
+        || e.to_string().contains("database is locked")
+}
+
+/// Plain-language text for a constraint violation from writing `value` to `column`; None for
+/// other errors
+fn constraint_message(e: &rusqlite::Error, column: &str, value: &str) -> Option<String> {
+    use rusqlite::ffi;
+    let rusqlite::Error::SqliteFailure(err, detail) = e else {
+        return None;
+    };
+    if err.code != rusqlite::ErrorCode::ConstraintViolation {
+        return None;
+    }
+    // SQLite's detail reads "<KIND> constraint failed: <what>"
+    let what = detail
+        .as_deref()
+        .and_then(|d| d.split_once(": "))
+        .map(|(_, w)| w)
+        .unwrap_or("");
+    Some(match err.extended_code {
+        ffi::SQLITE_CONSTRAINT_NOTNULL => format!("Column '{}' cannot be NULL", column),
+        ffi::SQLITE_CONSTRAINT_UNIQUE | ffi::SQLITE_CONSTRAINT_PRIMARYKEY => {
+            format!(
+                "Another row already has '{}' in {} (UNIQUE on {})",
+                value, column, what
+            )
+        }
+        ffi::SQLITE_CONSTRAINT_CHECK if what.is_empty() => {
+            "Value violates a CHECK constraint".into()
+        }
+        ffi::SQLITE_CONSTRAINT_CHECK => format!("Value violates CHECK constraint: {}", what),
+        ffi::SQLITE_CONSTRAINT_FOREIGNKEY => {
+            "Value violates a FOREIGN KEY constraint (no matching parent row)".into()
+        }
+        ffi::SQLITE_CONSTRAINT_DATATYPE => {
+            format!(
+                "Column '{}' doesn't accept this type (STRICT table)",
+                column
+            )
+        }
+        _ => format!("Constraint failed: {}", detail.as_deref().unwrap_or("")),
+    })
+}
+
+/// Pages copied per backup step; progress is reported between steps
+const BACKUP_PAGES_PER_STEP: i32 = 1024;
+
+/// Busy/locked steps in a row (50ms apart) before a backup gives up
+const BACKUP_MAX_BUSY_RETRIES: u32 = 200;
+
+fn blob_head(
+    conn: &Connection,
+    table: String,
+    rowid: i64,
+    column: String,
+    bytes: usize,
+) -> Result<DBResponse> {
+    let tref = table_ref(conn, &table)?;
+    let sql = format!(
+        "SELECT substr({}, 1, ?2) FROM {} WHERE rowid = ?1",
+        ident(&column),
+        tref.sql()
+    );
+    let head: Vec<u8> = conn.query_row(&sql, (rowid, bytes as i64), |row| row.get(0))?;
+    Ok(DBResponse::BlobHead {
+        table,
+        rowid,
+        column,
+        head,
     })
 }
 
-fn undo_last_change(
+fn save_blob(
     conn: &Connection,
-    history: &mut HashMap<String, Vec<Change>>,
     table: &str,
+    rowid: i64,
+    column: &str,
+    path: &str,
 ) -> Result<DBResponse> {
-    if let Some(stack) = history.get_mut(table)
-        && let Some(change) = stack.pop()
-    {
-        // Apply reverse update: set column back to previous value
-        let mut stmt = conn.prepare(&format!(
-            "UPDATE {} SET {} = ?1 WHERE rowid = ?2",
-            ident(&change.table),
-            ident(&change.column),
-        ))?;
-        let value_param = match change.prev_value {
-            None => rusqlite::types::Value::Null,
-            Some(ref s) => parse_value(s),
-        };
-        match stmt.execute((value_param, change.rowid)) {
-            Ok(_) => {
-                return Ok(DBResponse::CellUpdated {
-                    ok: true,
-                    message: Some("Undo applied".into()),
-                });
+    let tref = table_ref(conn, table)?;
+    let sql = format!(
+        "SELECT {} FROM {} WHERE rowid = ?1",
+        ident(column),
+        tref.sql()
+    );
+    let bytes: Option<Vec<u8>> = conn.query_row(&sql, [rowid], |row| {
+        Ok(match row.get_ref(0)? {
+            ValueRef::Blob(b) => Some(b.to_vec()),
+            _ => None,
+        })
+    })?;
+    let Some(bytes) = bytes else {
+        anyhow::bail!("{} is no longer a BLOB in this row", column);
+    };
+    std::fs::write(path, &bytes)?;
+    Ok(DBResponse::BlobSaved {
+        path: path.to_string(),
+        bytes: bytes.len(),
+    })
+}
+
+fn backup(
+    conn: &Connection,
+    path: &str,
+    overwrite: bool,
+    resp_tx: &Sender<DBResponse>,
+) -> Result<DBResponse> {
+    use rusqlite::backup::{Backup, StepResult};
+
+    let target = std::path::Path::new(path);
+    if target.exists() {
+        if !overwrite {
+            anyhow::bail!("{} already exists", path);
+        }
+        let open_db = conn
+            .path()
+            .filter(|p| !p.is_empty())
+            .map(std::fs::canonicalize);
+        if let Some(Ok(open_db)) = open_db
+            && std::fs::canonicalize(target)? == open_db
+        {
+            anyhow::bail!("{} is the open database; back up to another file", path);
+        }
+    }
+    // Copied into a fresh file next to the target, which is only replaced once the copy
+    // is complete (a non-database file can be replaced too)
+    let tmp = format!("{}.backup-tmp", path);
+    let _ = std::fs::remove_file(&tmp);
+    let copied = (|| -> Result<()> {
+        let mut dst = Connection::open(&tmp)?;
+        let backup = Backup::new(conn, &mut dst)?;
+        let mut busy = 0;
+        loop {
+            let step = backup.step(BACKUP_PAGES_PER_STEP)?;
+            let p = backup.progress();
+            let _ = resp_tx.send(DBResponse::BackupProgress {
+                done_pages: p.pagecount - p.remaining,
+                total_pages: p.pagecount,
+            });
+            match step {
+                StepResult::Done => return Ok(()),
+                // Another connection holds a lock; give it a moment
+                StepResult::Busy | StepResult::Locked => {
+                    busy += 1;
+                    if busy >= BACKUP_MAX_BUSY_RETRIES {
+                        anyhow::bail!("Backup gave up: {}", LOCKED_MESSAGE);
+                    }
+                    std::thread::sleep(Duration::from_millis(50))
+                }
+                _ => busy = 0,
             }
-            Err(e) => {
-                return Ok(DBResponse::CellUpdated {
-                    ok: false,
-                    message: Some(format!("Undo failed: {}", e)),
-                });
+        }
+    })();
+    if let Err(e) = copied.and_then(|_| Ok(std::fs::rename(&tmp, target)?)) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    Ok(DBResponse::BackupDone {
+        path: path.to_string(),
+        size_bytes: std::fs::metadata(path)?.len(),
+    })
+}
+
+fn reindex(conn: &Connection, table: &str) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    if tref.is_view {
+        anyhow::bail!("Views have no indexes to rebuild");
+    }
+    let command = format!("REINDEX {}", tref.sql());
+    conn.execute_batch(&command)?;
+    Ok(DBResponse::MaintenanceDone { command })
+}
+
+fn integrity_check(conn: &Connection) -> Result<DBResponse> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let mut problems = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if problems.len() == 1 && problems[0] == "ok" {
+        problems.clear();
+    }
+    Ok(DBResponse::IntegrityCheck { problems })
+}
+
+fn database_info(conn: &Connection) -> Result<DBResponse> {
+    let page_count: i64 = conn.query_row("PRAGMA main.page_count", [], |r| r.get(0))?;
+    let page_size: i64 = conn.query_row("PRAGMA main.page_size", [], |r| r.get(0))?;
+    let sqlite_version: String = conn.query_row("SELECT sqlite_version()", [], |r| r.get(0))?;
+    let wal_bytes = conn
+        .path()
+        .filter(|p| !p.is_empty())
+        .and_then(|p| std::fs::metadata(format!("{}-wal", p)).ok())
+        .map(|m| m.len());
+    Ok(DBResponse::DatabaseInfo {
+        size_bytes: (page_count * page_size) as u64,
+        wal_bytes,
+        sqlite_version,
+    })
+}
+
+fn checkpoint(conn: &Connection) -> Result<DBResponse> {
+    // (busy, WAL frames, frames checkpointed); busy means a reader kept it from finishing
+    let (busy, log, done): (i64, i64, i64) =
+        conn.query_row("PRAGMA main.wal_checkpoint(TRUNCATE)", [], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+        })?;
+    if log < 0 {
+        anyhow::bail!("Not in WAL mode; there is nothing to checkpoint");
+    }
+    if busy != 0 {
+        anyhow::bail!(
+            "Checkpoint incomplete: {} of {} frames copied; another connection is still reading",
+            done,
+            log
+        );
+    }
+    Ok(DBResponse::MaintenanceDone {
+        command: "Checkpoint".into(),
+    })
+}
+
+fn fill_down(
+    conn: &Connection,
+    history: &mut History,
+    table: &str,
+    column: &str,
+    source_rowid: i64,
+    rowids: &[i64],
+) -> Result<DBResponse> {
+    let tref = table_ref(conn, table)?;
+    let select_sql = format!(
+        "SELECT {} FROM {} WHERE rowid = ?1",
+        ident(column),
+        tref.sql()
+    );
+    let read = |rowid: i64| -> Result<rusqlite::types::Value> {
+        conn.query_row(&select_sql, [rowid], |row| {
+            Ok(stored_value(row.get_ref(0)?))
+        })?
+    };
+    // Copy the stored value as-is so its type survives ("007" stays text)
+    let value = read(source_rowid)?;
+    let update_sql = format!(
+        "UPDATE {} SET {} = ?1 WHERE rowid = ?2",
+        tref.sql(),
+        ident(column)
+    );
+
+    let applied = with_savepoint(conn, || {
+        let mut changes = Vec::with_capacity(rowids.len());
+        for &rowid in rowids {
+            let prev_value = read(rowid)?;
+            conn.execute(&update_sql, (&value, rowid))?;
+            changes.push(Change {
+                table: table.to_string(),
+                rowid,
+                column: column.to_string(),
+                prev_value,
+                new_value: value.clone(),
+            });
+        }
+        Ok(changes)
+    });
+    match applied {
+        Ok(changes) => {
+            let count = changes.len();
+            // One step, so a single `u` reverts the whole fill
+            if !changes.is_empty() {
+                history
+                    .entry(table.to_string())
+                    .or_default()
+                    .push(UndoStep::Cells(changes));
             }
+            Ok(DBResponse::RowsFilled {
+                table: table.to_string(),
+                count,
+            })
+        }
+        Err(e) => Ok(DBResponse::CellUpdated {
+            ok: false,
+            rows: 0,
+            message: Some(format!("Fill-down rolled back: {}", e)),
+        }),
+    }
+}
+
+/// Run `f` inside a SAVEPOINT so it is all-or-nothing, even within an outer transaction
+fn with_savepoint<T>(conn: &Connection, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    conn.execute_batch("SAVEPOINT sqlite_tui_batch")?;
+    match f() {
+        Ok(v) => {
+            conn.execute_batch("RELEASE sqlite_tui_batch")?;
+            Ok(v)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO sqlite_tui_batch; RELEASE sqlite_tui_batch");
+            Err(e)
+        }
+    }
+}
+
+// Undo the newest step, or with `redo` apply again the step undone last; the step then
+// moves to the other stack
+fn replay_last_change(
+    conn: &Connection,
+    history: &mut History,
+    table: &str,
+    redo: bool,
+) -> Result<DBResponse> {
+    let label = if redo { "Redo" } else { "Undo" };
+    if let Some(steps) = history.get_mut(table) {
+        let (from, to) = if redo {
+            (&mut steps.redo, &mut steps.undo)
+        } else {
+            (&mut steps.undo, &mut steps.redo)
+        };
+        if let Some(step) = from.pop() {
+            // All or nothing, so a half-applied step can't happen
+            let applied = with_savepoint(conn, || match &step {
+                UndoStep::Cells(changes) => write_cells(conn, changes, redo),
+                UndoStep::RowDeleted { table, rowid, .. } if redo => {
+                    let tref = table_ref(conn, table)?;
+                    Ok(conn.execute(
+                        &format!("DELETE FROM {} WHERE rowid = ?1", tref.sql()),
+                        [rowid],
+                    )?)
+                }
+                UndoStep::RowDeleted {
+                    table,
+                    rowid,
+                    columns,
+                    values,
+                } => restore_row(conn, table, *rowid, columns, values),
+            });
+            return match applied {
+                Ok(rows) => {
+                    to.push(step);
+                    Ok(DBResponse::CellUpdated {
+                        ok: true,
+                        rows,
+                        message: Some(format!("{} applied", label)),
+                    })
+                }
+                Err(e) => {
+                    // Nothing was written; keep the step so it can be tried again
+                    from.push(step);
+                    Ok(DBResponse::CellUpdated {
+                        ok: false,
+                        rows: 0,
+                        message: Some(format!("{} failed: {}", label, e)),
+                    })
+                }
+            };
         }
     }
     Ok(DBResponse::CellUpdated {
         ok: false,
-        message: Some("Nothing to undo".into()),
+        rows: 0,
+        message: Some(format!("Nothing to {}", label.to_lowercase())),
     })
 }
 
+// Set each cell back to its previous value, newest first; with `redo`, to its new value
+// again, oldest first
+fn write_cells(conn: &Connection, changes: &[Change], redo: bool) -> Result<usize> {
+    let mut rows = 0;
+    // A fill-down step is many cells of one column; look each column up once
+    let mut columns: HashMap<(&str, &str), String> = HashMap::new();
+    let mut ordered: Vec<&Change> = changes.iter().collect();
+    if !redo {
+        ordered.reverse();
+    }
+    for change in ordered {
+        let key = (change.table.as_str(), change.column.as_str());
+        let update_sql = match columns.entry(key) {
+            std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+            std::collections::hash_map::Entry::Vacant(e) => {
+                let tref = table_ref(conn, &change.table)?;
+                e.insert(format!(
+                    "UPDATE {} SET {} = ?1 WHERE rowid = ?2",
+                    tref.sql(),
+                    ident(&change.column),
+                ))
+            }
+        };
+        let mut stmt = conn.prepare_cached(update_sql)?;
+        // Bound as stored, so the type and bytes come back unchanged
+        let value = if redo {
+            &change.new_value
+        } else {
+            &change.prev_value
+        };
+        rows += stmt.execute((value, change.rowid))?;
+    }
+    Ok(rows)
+}
+
+// Re-insert a deleted row at its old rowid with the values it had
+fn restore_row(
+    conn: &Connection,
+    table: &str,
+    rowid: i64,
+    columns: &[String],
+    values: &[rusqlite::types::Value],
+) -> Result<usize> {
+    let tref = table_ref(conn, table)?;
+    let names: Vec<String> = std::iter::once("rowid".to_string())
+        .chain(columns.iter().map(|c| ident(c)))
+        .collect();
+    let sql = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        tref.sql(),
+        names.join(", "),
+        (1..=names.len())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let params =
+        std::iter::once(rusqlite::types::Value::Integer(rowid)).chain(values.iter().cloned());
+    Ok(conn.execute(&sql, rusqlite::params_from_iter(params))?)
+}
+
+/// A cell as stored, to be written back later. TEXT that isn't valid UTF-8 is refused:
+/// it can only be held as a String with its bytes changed
+fn stored_value(v: ValueRef<'_>) -> Result<rusqlite::types::Value> {
+    if let ValueRef::Text(t) = v
+        && std::str::from_utf8(t).is_err()
+    {
+        anyhow::bail!("This text isn't valid UTF-8; changing it here would corrupt it");
+    }
+    Ok(v.into())
+}
+
 fn value_to_opt_string(v: ValueRef<'_>) -> Option<String> {
     match v {
         ValueRef::Null => None,
@@ -523,6 +3769,35 @@ fn value_to_opt_string(v: ValueRef<'_>) -> Option<String> {
     }
 }
 
+/// Bind `s` as the requested type; Auto follows the column affinity
+fn typed_value(
+    s: &str,
+    value_type: EditType,
+    affinity: Affinity,
+) -> Result<rusqlite::types::Value> {
+    Ok(match value_type {
+        EditType::Auto => parse_value_for(s, affinity),
+        EditType::Text => rusqlite::types::Value::Text(s.to_string()),
+        EditType::Integer => match s.trim().parse::<i64>() {
+            Ok(i) => rusqlite::types::Value::Integer(i),
+            Err(_) => anyhow::bail!("'{}' is not an INTEGER", s),
+        },
+        EditType::Real => match s.trim().parse::<f64>() {
+            Ok(f) => rusqlite::types::Value::Real(f),
+            Err(_) => anyhow::bail!("'{}' is not a REAL", s),
+        },
+    })
+}
+
+/// `parse_value` for columns with numeric affinity; anything else keeps the text as typed
+fn parse_value_for(s: &str, affinity: Affinity) -> rusqlite::types::Value {
+    if affinity.is_numeric() {
+        parse_value(s)
+    } else {
+        rusqlite::types::Value::Text(s.to_string())
+    }
+}
+
 fn parse_value(s: &str) -> rusqlite::types::Value {
     if let Ok(i) = s.parse::<i64>() {
         return rusqlite::types::Value::Integer(i);
@@ -534,13 +3809,34 @@ fn parse_value(s: &str) -> rusqlite::types::Value {
     rusqlite::types::Value::Text(s.to_string())
 }
 
-fn row_to_strings(row: &Row, ncols: usize) -> rusqlite::Result<Vec<String>> {
+/// BLOBs up to this size are sent to the UI as full hex; larger ones as a placeholder
+const BLOB_INLINE_MAX: usize = 1024;
+
+/// Grid text for a large BLOB; its bytes are fetched separately when needed
+pub fn blob_placeholder(len: usize) -> String {
+    format!("<BLOB {} bytes>", len)
+}
+
+/// Byte length from a `blob_placeholder` string
+pub fn parse_blob_placeholder(s: &str) -> Option<usize> {
+    s.strip_prefix("<BLOB ")?
+        .strip_suffix(" bytes>")?
+        .parse()
+        .ok()
+}
+
+fn row_to_cells(row: &Row, ncols: usize) -> rusqlite::Result<(Vec<String>, Vec<CellKind>)> {
     let mut out = Vec::with_capacity(ncols);
+    let mut kinds = Vec::with_capacity(ncols);
     for i in 0..ncols {
         let v = row.get_ref(i)?;
-        out.push(value_to_string(v));
+        out.push(match v {
+            ValueRef::Blob(b) if b.len() > BLOB_INLINE_MAX => blob_placeholder(b.len()),
+            _ => value_to_string(v),
+        });
+        kinds.push(CellKind::from(v));
     }
-    Ok(out)
+    Ok((out, kinds))
 }
 
 fn value_to_string(v: ValueRef<'_>) -> String {
@@ -557,7 +3853,7 @@ fn value_to_string(v: ValueRef<'_>) -> String {
 }
 
 // Quote identifiers with double-quotes, and escape inner quotes
-fn ident(name: &str) -> String {
+pub fn ident(name: &str) -> String {
     let escaped = name.replace('"', "\"\"");
     format!("\"{}\"", escaped)
 }
@@ -574,4 +3870,226 @@ mod hex {
         }
         s
     }
+
+    /// Inverse of `encode` for the "0x..." strings blobs are displayed as
+    pub fn decode(s: &str) -> Option<Vec<u8>> {
+        if !s.len().is_multiple_of(2) {
+            return None;
+        }
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::types::Value;
+
+    fn memory_db(schema: &str) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        register_unicode_lower(&conn).unwrap();
+        conn.execute_batch(schema).unwrap();
+        conn
+    }
+
+    fn stored(conn: &Connection, sql: &str) -> Value {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn undo_and_redo_write_back_the_stored_values() {
+        let conn = memory_db("CREATE TABLE t(v); INSERT INTO t VALUES (x'00ff10');");
+        let mut history = History::new();
+        let write = CellWrite::Value("1.5".into(), EditType::Real);
+        update_cell(&conn, &mut history, "t", 1, "v", write).unwrap();
+
+        replay_last_change(&conn, &mut history, "t", false).unwrap();
+        assert_eq!(
+            stored(&conn, "SELECT v FROM t"),
+            Value::Blob(vec![0x00, 0xff, 0x10])
+        );
+        // No declared type: the REAL must not come back as TEXT
+        replay_last_change(&conn, &mut history, "t", true).unwrap();
+        assert_eq!(stored(&conn, "SELECT v FROM t"), Value::Real(1.5));
+    }
+
+    #[test]
+    fn auto_edit_keeps_text_in_a_text_column() {
+        let conn =
+            memory_db("CREATE TABLE t(code TEXT, n INTEGER); INSERT INTO t VALUES ('a', 1);");
+        let mut history = History::new();
+        let write = CellWrite::Value("007".into(), EditType::Auto);
+        update_cell(&conn, &mut history, "t", 1, "code", write).unwrap();
+        assert_eq!(
+            stored(&conn, "SELECT code FROM t"),
+            Value::Text("007".into())
+        );
+        let write = CellWrite::Value("007".into(), EditType::Auto);
+        update_cell(&conn, &mut history, "t", 1, "n", write).unwrap();
+        assert_eq!(stored(&conn, "SELECT n FROM t"), Value::Integer(7));
+    }
+
+    #[test]
+    fn invalid_utf8_cells_are_not_edited() {
+        let conn =
+            memory_db("CREATE TABLE t(v TEXT); INSERT INTO t VALUES (CAST(x'ff41' AS TEXT));");
+        let mut history = History::new();
+        let write = CellWrite::Value("A".into(), EditType::Auto);
+        assert!(update_cell(&conn, &mut history, "t", 1, "v", write).is_err());
+        assert!(fill_down(&conn, &mut history, "t", "v", 1, &[1]).is_err());
+        assert_eq!(
+            stored(&conn, "SELECT CAST(v AS BLOB) FROM t"),
+            Value::Blob(vec![0xff, 0x41])
+        );
+        assert!(history.get("t").is_none_or(|h| h.undo.is_empty()));
+    }
+
+    #[test]
+    fn filter_folds_non_ascii_case() {
+        let conn = memory_db("CREATE TABLE t(name); INSERT INTO t VALUES ('Müller'), ('Muller');");
+        let cols = vec!["name".to_string()];
+        let (where_sql, params) = filter_clause(&cols, Some("müller"));
+        let names: Vec<String> = conn
+            .prepare(&format!("SELECT name FROM t{}", where_sql))
+            .unwrap()
+            .query_map(rusqlite::params_from_iter(params), |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(names, ["Müller"]);
+    }
+
+    #[test]
+    fn unique_violation_names_the_duplicated_value() {
+        let conn =
+            memory_db("CREATE TABLE t(email UNIQUE); INSERT INTO t VALUES ('a@x'), ('b@x');");
+        let mut history = History::new();
+        let write = CellWrite::Value("a@x".into(), EditType::Text);
+        let Ok(DBResponse::CellUpdated { ok, message, .. }) =
+            update_cell(&conn, &mut history, "t", 2, "email", write)
+        else {
+            panic!("expected CellUpdated");
+        };
+        assert!(!ok);
+        assert_eq!(
+            message.as_deref(),
+            Some("Another row already has 'a@x' in email (UNIQUE on t.email)")
+        );
+    }
+
+    #[test]
+    fn query_bar_stops_one_row_past_the_limit() {
+        let conn = memory_db("CREATE TABLE t(v); INSERT INTO t VALUES (1), (2), (3), (4), (5);");
+        let Ok(DBResponse::QueryResult {
+            rows, total_rows, ..
+        }) = run_read_only_query(&conn, "SELECT v FROM t", 2)
+        else {
+            panic!("expected a query result");
+        };
+        assert_eq!(rows.len(), 2);
+        assert_eq!(total_rows, 3);
+    }
+
+    #[test]
+    fn fill_down_and_its_undo_keep_types() {
+        let conn = memory_db(
+            "CREATE TABLE t(code TEXT, v); INSERT INTO t VALUES ('007', 1), (NULL, x'01'), (7, 2.5);",
+        );
+        let mut history = History::new();
+        fill_down(&conn, &mut history, "t", "code", 1, &[2, 3]).unwrap();
+        assert_eq!(
+            stored(&conn, "SELECT count(*) FROM t WHERE code IS '007'"),
+            Value::Integer(3)
+        );
+        fill_down(&conn, &mut history, "t", "v", 2, &[1, 3]).unwrap();
+        replay_last_change(&conn, &mut history, "t", false).unwrap();
+        replay_last_change(&conn, &mut history, "t", false).unwrap();
+        assert_eq!(
+            stored(&conn, "SELECT v FROM t WHERE rowid = 1"),
+            Value::Integer(1)
+        );
+        assert_eq!(
+            stored(&conn, "SELECT v FROM t WHERE rowid = 3"),
+            Value::Real(2.5)
+        );
+        assert_eq!(
+            stored(&conn, "SELECT code FROM t WHERE rowid = 2"),
+            Value::Null
+        );
+        assert_eq!(
+            stored(&conn, "SELECT code FROM t WHERE rowid = 3"),
+            Value::Text("7".into())
+        );
+    }
+
+    #[test]
+    fn rollback_restores_steps_undone_in_the_transaction() {
+        let conn = memory_db("CREATE TABLE t(v); INSERT INTO t VALUES (1);");
+        let mut history = History::new();
+        let mut txn_history = None;
+        let write = CellWrite::Value("2".into(), EditType::Integer);
+        update_cell(&conn, &mut history, "t", 1, "v", write).unwrap();
+        begin_transaction(&conn, &history, &mut txn_history).unwrap();
+        replay_last_change(&conn, &mut history, "t", false).unwrap();
+        let write = CellWrite::Value("3".into(), EditType::Integer);
+        update_cell(&conn, &mut history, "t", 1, "v", write).unwrap();
+        end_transaction(&conn, &mut history, &mut txn_history, true).unwrap();
+
+        assert_eq!(stored(&conn, "SELECT v FROM t"), Value::Integer(2));
+        // The edit made before BEGIN is back on the undo stack, not lost with the rollback
+        replay_last_change(&conn, &mut history, "t", false).unwrap();
+        assert_eq!(stored(&conn, "SELECT v FROM t"), Value::Integer(1));
+        assert!(history["t"].redo.len() == 1 && history["t"].undo.is_empty());
+    }
+
+    #[test]
+    fn duplicate_row_with_only_a_rowid_column() {
+        let conn = memory_db("CREATE TABLE t(id INTEGER PRIMARY KEY); INSERT INTO t VALUES (1);");
+        duplicate_row(&conn, "t", 1).unwrap();
+        assert_eq!(stored(&conn, "SELECT count(*) FROM t"), Value::Integer(2));
+        assert!(duplicate_row(&conn, "t", 99).is_err());
+        assert_eq!(stored(&conn, "SELECT count(*) FROM t"), Value::Integer(2));
+    }
+
+    #[test]
+    fn backup_refuses_to_overwrite_the_open_database() {
+        let dir = std::env::temp_dir().join(format!("sqlite-tui-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = dir.join("live.db");
+        let conn = Connection::open(&db).unwrap();
+        conn.execute_batch("CREATE TABLE t(v); INSERT INTO t VALUES (1);")
+            .unwrap();
+        let (tx, _rx) = crossbeam_channel::unbounded();
+
+        let err = backup(&conn, db.to_str().unwrap(), true, &tx).unwrap_err();
+        assert!(err.to_string().contains("is the open database"));
+        assert_eq!(stored(&conn, "SELECT count(*) FROM t"), Value::Integer(1));
+
+        let copy = dir.join("copy.db");
+        std::fs::write(&copy, "not a database").unwrap();
+        backup(&conn, copy.to_str().unwrap(), true, &tx).unwrap();
+        let copied = Connection::open(&copy).unwrap();
+        assert_eq!(stored(&copied, "SELECT v FROM t"), Value::Integer(1));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn row_positions_follow_the_sort_order() {
+        let conn = memory_db(
+            "CREATE TABLE t(name TEXT); INSERT INTO t VALUES ('c'), ('a'), ('b'), ('d');",
+        );
+        let positions = row_positions(
+            &conn,
+            "t",
+            &[1, 3, 4],
+            None,
+            Some("name".into()),
+            Some(SortDir::Asc),
+        )
+        .unwrap();
+        assert_eq!(positions, vec![(3, 1), (1, 2), (4, 3)]);
+    }
 }
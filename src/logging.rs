@@ -0,0 +1,82 @@
+//! Optional file log (--log, --verbose). The UI owns the terminal, so nothing is ever
+//! written to stdout or stderr; without --log every call here is a no-op.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Longest request or response text written by --verbose; row data can be huge
+const VERBOSE_TEXT_LIMIT: usize = 300;
+
+struct Logger {
+    file: Mutex<File>,
+    verbose: bool,
+    started: Instant,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Append to the file at `path`; `verbose` also records every request and response
+pub fn init(path: &Path, verbose: bool) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    writeln!(
+        file,
+        "--- sqlite-editor {} started (unix time {}) ---",
+        env!("CARGO_PKG_VERSION"),
+        since_epoch
+    )?;
+    let _ = LOGGER.set(Logger {
+        file: Mutex::new(file),
+        verbose,
+        started: Instant::now(),
+    });
+    Ok(())
+}
+
+pub fn enabled() -> bool {
+    LOGGER.get().is_some()
+}
+
+/// Record a line (errors, timings); `line` only runs when logging is on
+pub fn log(line: impl FnOnce() -> String) {
+    if let Some(logger) = LOGGER.get() {
+        logger.write(&line());
+    }
+}
+
+/// Record a line only with --verbose
+pub fn debug(line: impl FnOnce() -> String) {
+    if let Some(logger) = LOGGER.get().filter(|l| l.verbose) {
+        logger.write(&line());
+    }
+}
+
+/// `value`'s Debug text, cut to VERBOSE_TEXT_LIMIT characters
+pub fn brief(value: &impl std::fmt::Debug) -> String {
+    let text = format!("{:?}", value);
+    match text.char_indices().nth(VERBOSE_TEXT_LIMIT) {
+        Some((i, _)) => format!("{}… ({} bytes)", &text[..i], text.len()),
+        None => text,
+    }
+}
+
+impl Logger {
+    fn write(&self, line: &str) {
+        let t = self.started.elapsed();
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(
+                file,
+                "[{:>5}.{:03}] {}",
+                t.as_secs(),
+                t.subsec_millis(),
+                line
+            );
+        }
+    }
+}
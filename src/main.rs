@@ -1,20 +1,29 @@
 use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use clap::Parser;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEventKind,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{backend::CrosstermBackend, prelude::*};
 
 mod app;
+mod cli;
 mod db;
+mod logging;
 mod ui;
+mod views;
 
 use app::{App, AppMode};
+use cli::OutputFormat;
 use db::{DBRequest, DBResponse, start_db_worker};
 
 #[derive(Parser, Debug)]
@@ -27,6 +36,119 @@ struct Args {
     /// Page size (rows per page)
     #[arg(short = 'n', long, default_value_t = 200)]
     page_size: usize,
+
+    /// Watch the database file and reload when another process changes it
+    #[arg(long)]
+    watch: bool,
+
+    /// Open the database read-only and refuse edits and schema changes
+    #[arg(long)]
+    read_only: bool,
+
+    /// Open the database immutable (read-only, no locks or journal files) for read-only
+    /// or WORM media; implies --read-only
+    #[arg(long, conflicts_with = "watch")]
+    immutable: bool,
+
+    /// Write TABLE to stdout (or --out) and exit without starting the UI
+    #[arg(long, value_name = "TABLE", conflicts_with = "query")]
+    export: Option<String>,
+
+    /// With --export, write __rowid__ as the first column
+    #[arg(long, requires = "export")]
+    include_rowid: bool,
+
+    /// Run one SQL statement, print its result and exit without starting the UI
+    #[arg(long, value_name = "SQL")]
+    query: Option<String>,
+
+    /// Output format for --export (default: csv) and --query (default: aligned table)
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Write --export/--query output to this file instead of stdout
+    #[arg(long, value_name = "PATH")]
+    out: Option<String>,
+
+    /// Open this table on startup
+    #[arg(long, value_name = "NAME")]
+    table: Option<String>,
+
+    /// Filter rows (same syntax as the / filter); applies to --table and --export
+    #[arg(long)]
+    filter: Option<String>,
+
+    /// Sort rows by this column; applies to --table and --export
+    #[arg(long, value_name = "COLUMN")]
+    sort: Option<String>,
+
+    /// Sort descending (with --sort)
+    #[arg(long, requires = "sort")]
+    desc: bool,
+
+    /// Format of clipboard copies (T cycles while running)
+    #[arg(long, value_enum, default_value_t = OutputFormat::Tsv)]
+    copy_format: OutputFormat,
+
+    /// Program (plus args, split on spaces) that receives copies on stdin, tried before
+    /// pbcopy/wl-copy/xclip/xsel/clip; e.g. "tmux load-buffer -". Also read from
+    /// SQLITE_TUI_CLIPBOARD
+    #[arg(long, value_name = "CMD")]
+    clipboard_cmd: Option<String>,
+
+    /// Text shown for NULL cells in the data grid
+    #[arg(long, value_name = "TEXT", default_value = "NULL")]
+    null: String,
+
+    /// Show 0/1 in these INTEGER columns as booleans (comma-separated names)
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    bool_columns: Vec<String>,
+
+    /// How boolean columns are drawn
+    #[arg(long, value_enum, default_value_t = BoolStyleArg::Words)]
+    bool_style: BoolStyleArg,
+
+    /// Show the __rowid__ column (hidden by default; F5 toggles)
+    #[arg(long)]
+    show_rowid: bool,
+
+    /// Redraw/poll interval in milliseconds while something is pending (idle waits longer)
+    #[arg(long, value_name = "MS", default_value_t = 100)]
+    tick_ms: u64,
+
+    /// Number of recently loaded pages the worker keeps (0 disables the cache)
+    #[arg(long, value_name = "PAGES", default_value_t = 16)]
+    page_cache: usize,
+
+    /// Estimate the row count of big unfiltered tables (sqlite_stat1 or MAX(rowid)) instead
+    /// of running COUNT(*); # toggles
+    #[arg(long)]
+    approx_count: bool,
+
+    /// Rows the distinct-values picker, `:` queries and --query's text table show before
+    /// cutting off (0 shows all); + in the picker shows more
+    #[arg(long, value_name = "ROWS", default_value_t = db::DEFAULT_RESULT_LIMIT)]
+    result_limit: usize,
+
+    /// How long a write waits for another process's lock before giving up
+    #[arg(long = "busy-timeout", value_name = "MS", default_value_t = 2000)]
+    busy_timeout_ms: u64,
+
+    /// Append errors and request timings to this file
+    #[arg(long, value_name = "PATH")]
+    log: Option<std::path::PathBuf>,
+
+    /// With --log, also record every request and response
+    #[arg(long, requires = "log")]
+    verbose: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum BoolStyleArg {
+    /// false / true
+    Words,
+    /// ☐ / ☑
+    Boxes,
 }
 
 fn setup_terminal() -> Result<Terminal<CrosstermBackend<io::Stdout>>> {
@@ -51,6 +173,25 @@ fn restore_terminal(mut terminal: Terminal<CrosstermBackend<io::Stdout>>) -> Res
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    if let Some(path) = &args.log {
+        logging::init(path, args.verbose)
+            .map_err(|e| anyhow::anyhow!("Can't open log {}: {e}", path.display()))?;
+        logging::log(|| format!("database {}", args.db_path));
+    }
+
+    // Scripting modes: print and exit without touching the terminal
+    let cli_result = if let Some(table) = args.export.as_deref() {
+        Some(cli::run_export(&args, table))
+    } else {
+        args.query.as_deref().map(|sql| cli::run_query(&args, sql))
+    };
+    if let Some(res) = cli_result {
+        if let Err(e) = res {
+            eprintln!("Error: {e:#}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
     let mut terminal = setup_terminal()?;
 
@@ -60,92 +201,316 @@ fn main() -> Result<()> {
 
     // Start DB worker
     let db_path = args.db_path.clone();
-    std::thread::spawn(move || start_db_worker(db_path, req_rx, resp_tx));
+    let open_mode = db::OpenMode::new(args.read_only, args.immutable);
+    let page_cache_size = args.page_cache;
+    let busy_timeout = Duration::from_millis(args.busy_timeout_ms);
+    let export_cancel = Arc::new(AtomicBool::new(false));
+    let worker_cancel = export_cancel.clone();
+    std::thread::spawn(move || {
+        start_db_worker(
+            db_path,
+            open_mode,
+            req_rx,
+            resp_tx,
+            worker_cancel,
+            page_cache_size,
+            busy_timeout,
+        )
+    });
 
     // Initialize app state
     let mut app = App::new(args.page_size, req_tx, resp_rx);
+    app.export_cancel = export_cancel;
+    app.read_only = open_mode.is_read_only();
+    app.immutable = open_mode == db::OpenMode::Immutable;
+    app.null_display = args.null.clone();
+    app.copy_format = args.copy_format.into();
+    app.clipboard_cmd = args
+        .clipboard_cmd
+        .clone()
+        .or_else(|| std::env::var("SQLITE_TUI_CLIPBOARD").ok())
+        .map(|cmd| cmd.split_whitespace().map(String::from).collect::<Vec<_>>())
+        .filter(|cmd| !cmd.is_empty());
+    app.views_key = db_key(&args.db_path);
+    app.show_rowid = args.show_rowid;
+    app.approx_count = args.approx_count;
+    app.result_limit = args.result_limit;
+    app.bool_columns = args.bool_columns.iter().cloned().collect();
+    app.bool_style = match args.bool_style {
+        BoolStyleArg::Words => app::BoolStyle::Words,
+        BoolStyleArg::Boxes => app::BoolStyle::Boxes,
+    };
     app.status = "Press ? for help — / filter | s/S sort | +/- (=/_) width | a/A autosize | v view cell | c/C/Ctrl+C copy | E export CSV | e edit | Ctrl-d NULL (edit) | u undo".into();
+    if let Some(table) = args.table.clone() {
+        app.filter = args.filter.clone();
+        if args.sort.is_some() {
+            app.sort_by = args.sort.clone();
+            app.sort_dir = Some(if args.desc {
+                db::SortDir::Desc
+            } else {
+                db::SortDir::Asc
+            });
+        }
+        app.focus = app::Focus::Data;
+        app.pending_select_table = Some(table);
+    } else {
+        app.restore_table = load_last_table(&args.db_path);
+    }
     app.request_schema_refresh();
 
-    let mut last_tick = Instant::now();
-    let tick_rate = Duration::from_millis(100);
+    // Optional file watcher; the watcher must stay alive for events to flow
+    let watcher = if args.watch {
+        match start_file_watcher(&args.db_path) {
+            Ok(w) => Some(w),
+            Err(e) => {
+                app.status = format!("Watch disabled: {e}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let watch_rx = watcher.as_ref().map(|(_, rx)| rx.clone());
 
-    let res = run_app(&mut terminal, &mut app, tick_rate, &mut last_tick);
+    let tick_rate = Duration::from_millis(args.tick_ms.max(1));
+
+    let res = run_app(&mut terminal, &mut app, tick_rate, watch_rx);
 
     restore_terminal(terminal)?;
+    if let Some(table) = app.loaded_table.as_deref() {
+        save_last_table(&args.db_path, table, app.global_row_offset);
+    }
     if let Err(e) = res {
         eprintln!("Error: {e:?}");
     }
     Ok(())
 }
 
+/// `$XDG_STATE_HOME/sqlite-editor/last-tables.json` (or `~/.local/state/...`): the last
+/// open table and row offset per database path
+fn last_tables_path() -> Option<std::path::PathBuf> {
+    let dir = std::env::var_os("XDG_STATE_HOME")
+        .filter(|d| !d.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|h| std::path::PathBuf::from(h).join(".local/state"))
+        })?;
+    Some(dir.join("sqlite-editor").join("last-tables.json"))
+}
+
+fn db_key(db_path: &str) -> String {
+    std::fs::canonicalize(db_path)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| db_path.to_string())
+}
+
+fn load_last_table(db_path: &str) -> Option<(String, usize)> {
+    let text = std::fs::read_to_string(last_tables_path()?).ok()?;
+    let all: serde_json::Value = serde_json::from_str(&text).ok()?;
+    let entry = all.get(db_key(db_path))?;
+    let table = entry.get("table")?.as_str()?.to_string();
+    let offset = entry.get("offset").and_then(|o| o.as_u64()).unwrap_or(0);
+    Some((table, offset as usize))
+}
+
+/// Best effort: a state file that can't be written just means no restore next time. One
+/// that can't be read or parsed is left alone rather than replaced
+fn save_last_table(db_path: &str, table: &str, offset: usize) {
+    let Some(path) = last_tables_path() else {
+        return;
+    };
+    let mut all = match std::fs::read_to_string(&path) {
+        Ok(text) => match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(v) if v.is_object() => v,
+            _ => return,
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => serde_json::json!({}),
+        Err(_) => return,
+    };
+    all[db_key(db_path)] = serde_json::json!({ "table": table, "offset": offset });
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Ok(text) = serde_json::to_string_pretty(&all) {
+        let _ = std::fs::write(&path, text);
+    }
+}
+
+/// Watch the database file (and its -wal sibling) for modifications.
+/// Events are forwarded as unit messages; debouncing happens in `run_app`.
+fn start_file_watcher(
+    db_path: &str,
+) -> Result<(notify::RecommendedWatcher, crossbeam_channel::Receiver<()>)> {
+    use notify::{EventKind, RecursiveMode, Watcher};
+
+    let path = std::fs::canonicalize(db_path)?;
+    let dir = path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| ".".into());
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_os_string())
+        .unwrap_or_default();
+    let mut wal_name = file_name.clone();
+    wal_name.push("-wal");
+
+    let (tx, rx) = crossbeam_channel::unbounded::<()>();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let Ok(event) = res else {
+            return;
+        };
+        if !matches!(
+            event.kind,
+            EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_)
+        ) {
+            return;
+        }
+        let relevant = event.paths.iter().any(|p| {
+            p.file_name()
+                .is_some_and(|n| n == file_name.as_os_str() || n == wal_name.as_os_str())
+        });
+        if relevant {
+            let _ = tx.send(());
+        }
+    })?;
+    // Watch the directory so WAL files created later are seen too
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+    Ok((watcher, rx))
+}
+
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     app: &mut App,
     tick_rate: Duration,
-    last_tick: &mut Instant,
+    watch_rx: Option<crossbeam_channel::Receiver<()>>,
 ) -> Result<()> {
+    // Input, DB responses and file-watch events all wake one select below, so an idle
+    // app blocks instead of polling
+    let event_rx = spawn_event_reader();
+    let resp_rx = app.resp_rx.clone();
+    let watch_rx = watch_rx.unwrap_or_else(crossbeam_channel::never);
     let mut filter_mode = false;
     let mut export_mode = false;
     let mut export_path_buf = String::new();
     // Redraw only when state changes or on tick
     let mut dirty = true;
+    // Debounce file-watch events: check once things have been quiet for a moment
+    let watch_debounce = Duration::from_millis(500);
+    let mut last_watch_event: Option<Instant> = None;
     loop {
+        while watch_rx.try_recv().is_ok() {
+            last_watch_event = Some(Instant::now());
+        }
+        if last_watch_event.is_some_and(|t| t.elapsed() >= watch_debounce) {
+            last_watch_event = None;
+            let _ = app.req_tx.send(DBRequest::CheckExternalChange);
+        }
+
         // Process any DB responses without blocking
-        while let Ok(msg) = app.resp_rx.try_recv() {
-            match msg {
-                DBResponse::ExportedCSV { ok, path, message } => {
-                    if ok {
-                        app.status = format!("Exported CSV to {}", path);
-                    } else {
-                        app.status = format!(
-                            "Export failed: {}",
-                            message.unwrap_or_else(|| "unknown error".into())
-                        );
-                    }
-                    dirty = true;
-                }
-                _ => {
-                    app.handle_db_response(msg);
-                    dirty = true;
-                }
-            }
+        while let Ok(msg) = resp_rx.try_recv() {
+            handle_response(app, msg);
+            dirty = true;
         }
 
-        let tick_due = last_tick.elapsed() >= tick_rate;
-        if dirty || tick_due {
+        if dirty {
             terminal.draw(|f| ui::draw(f, app))?;
             dirty = false;
-            if tick_due {
-                *last_tick = Instant::now();
-            }
         }
 
-        let timeout = tick_rate
-            .checked_sub(last_tick.elapsed())
-            .unwrap_or(Duration::from_secs(0));
+        // Tick while a watch debounce is pending or a loading spinner turns; otherwise
+        // sleep long, since anything that changes the screen arrives on one of the channels
+        let timeout = if last_watch_event.is_some()
+            || app.schema_loading.is_some()
+            || app.table_loading.is_some()
+        {
+            tick_rate
+        } else {
+            tick_rate * IDLE_TICKS
+        };
+        let next_event = crossbeam_channel::select! {
+            recv(event_rx) -> ev => match ev {
+                Ok(ev) => Some(ev?),
+                Err(_) => anyhow::bail!("terminal input closed"),
+            },
+            recv(resp_rx) -> msg => {
+                if let Ok(msg) = msg {
+                    handle_response(app, msg);
+                }
+                dirty = true;
+                None
+            }
+            recv(watch_rx) -> _ => {
+                last_watch_event = Some(Instant::now());
+                None
+            }
+            default(timeout) => {
+                // Periodic redraw as a safety net
+                dirty = true;
+                None
+            }
+        };
 
-        let should_exit = if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if export_mode {
+        let should_exit = if let Some(ev) = next_event {
+            if let Event::Key(key) = ev {
+                if app.prompt.is_some() {
+                    use crossterm::event::{KeyCode::*, KeyModifiers};
+                    match key.code {
+                        Enter => app.submit_prompt(),
+                        Esc => app.cancel_prompt(),
+                        Backspace => app.prompt_backspace(),
+                        Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.prompt_insert(c);
+                        }
+                        _ => {}
+                    }
+                    dirty = true;
+                    false
+                } else if app.overlay.is_some() {
+                    handle_key_overlay(app, key.code);
+                    dirty = true;
+                    false
+                } else if export_mode {
                     use crossterm::event::{KeyCode::*, KeyModifiers};
                     match key.code {
                         Enter => {
+                            let rowids = if app.export_selection {
+                                app.selected_rowids()
+                            } else {
+                                None
+                            };
                             if export_path_buf.is_empty() {
                                 app.status = "Export cancelled".into();
-                            } else if let Some(table) =
-                                app.current_table_name().map(|s| s.to_string())
-                            {
+                            } else if app.export_selection && rowids.is_none() {
+                                app.status =
+                                    "Export: the selection extends beyond the loaded rows".into();
+                            } else if let Some(table) = app.loaded_table.clone() {
+                                // A selection exports exactly its rows; otherwise the filtered count
+                                app.export_total = match &rowids {
+                                    Some(ids) => Some(ids.len()),
+                                    None => app.total_rows,
+                                };
+                                app.export_target = export_path_buf.clone();
+                                let columns = app.export_columns(&table);
                                 let _ = app.req_tx.send(DBRequest::ExportCSV {
                                     table,
                                     path: export_path_buf.clone(),
                                     filter: app.filter.clone(),
                                     sort_by: app.sort_by.clone(),
                                     sort_dir: app.sort_dir,
+                                    append: app.export_append,
+                                    bom: app.export_bom,
+                                    crlf: app.export_crlf,
+                                    quote_all: app.export_quote_all,
+                                    delimiter: app.export_delimiter,
+                                    rowids,
+                                    columns,
+                                    include_rowid: app.export_include_rowid,
                                 });
-                                app.status = format!("Exporting CSV to {}...", export_path_buf);
+                                app.export_running = true;
+                                app.status = app.export_progress_status(0);
                             } else {
-                                app.status = "No table selected for export".into();
+                                app.status = "No table loaded to export".into();
                             }
                             export_mode = false;
                             export_path_buf.clear();
@@ -157,18 +522,94 @@ fn run_app(
                         }
                         Backspace => {
                             export_path_buf.pop();
-                            app.status = format!("Export CSV: {}_", export_path_buf);
+                            app.status = app.export_prompt_status(&export_path_buf);
                         }
-                        Char(c) => {
-                            if !key.modifiers.contains(KeyModifiers::CONTROL) {
-                                export_path_buf.push(c);
-                                app.status = format!("Export CSV: {}_", export_path_buf);
-                            }
+                        Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.export_append = !app.export_append;
+                            app.status = app.export_prompt_status(&export_path_buf);
+                        }
+                        Char('s')
+                            if key.modifiers.contains(KeyModifiers::CONTROL)
+                                && app.can_export_selection() =>
+                        {
+                            app.export_selection = !app.export_selection;
+                            app.status = app.export_prompt_status(&export_path_buf);
+                        }
+                        Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.cycle_export_delimiter();
+                            app.status = app.export_prompt_status(&export_path_buf);
+                        }
+                        Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.export_bom = !app.export_bom;
+                            app.status = app.export_prompt_status(&export_path_buf);
+                        }
+                        Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.export_crlf = !app.export_crlf;
+                            app.status = app.export_prompt_status(&export_path_buf);
+                        }
+                        Char('q') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.export_quote_all = !app.export_quote_all;
+                            app.status = app.export_prompt_status(&export_path_buf);
+                        }
+                        Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.export_include_rowid = !app.export_include_rowid;
+                            app.status = app.export_prompt_status(&export_path_buf);
+                        }
+                        Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            export_path_buf.push(c);
+                            app.status = app.export_prompt_status(&export_path_buf);
                         }
                         _ => {}
                     }
                     dirty = true;
                     false
+                } else if app.sidebar_query_input {
+                    use crossterm::event::{KeyCode::*, KeyModifiers};
+                    match key.code {
+                        Enter => {
+                            app.sidebar_query_input = false;
+                            app.status = if app.sidebar_query.is_empty() {
+                                "Sidebar filter cleared".into()
+                            } else {
+                                format!("Sidebar filter: {} (Esc clears)", app.sidebar_query)
+                            };
+                        }
+                        Esc => {
+                            app.clear_sidebar_query();
+                            app.status = "Sidebar filter cleared".into();
+                        }
+                        Backspace => {
+                            app.pop_sidebar_query();
+                            app.status = app.sidebar_query_status();
+                        }
+                        Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.push_sidebar_query(c);
+                            app.status = app.sidebar_query_status();
+                        }
+                        _ => {}
+                    }
+                    dirty = true;
+                    false
+                } else if app.query_input.is_some() {
+                    use crossterm::event::{KeyCode::*, KeyModifiers};
+                    match key.code {
+                        Enter => app.submit_query_input(),
+                        Esc => app.cancel_query_input(),
+                        Backspace => app.backspace_query_input(),
+                        // Sets its own status (the candidate count)
+                        Tab => app.complete_query_input(),
+                        Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.update_query_input_char(c)
+                        }
+                        _ => {}
+                    }
+                    if key.code != Tab
+                        && let Some(buf) = &app.query_input
+                    {
+                        app.status = format!("Query: {}_", buf);
+                    }
+                    dirty = true;
+                    false
                 } else if filter_mode {
                     use crossterm::event::{KeyCode::*, KeyModifiers};
                     match key.code {
@@ -196,12 +637,16 @@ fn run_app(
                                 app.status = "Filter: _".into();
                             }
                         }
-                        Char(c) => {
-                            if !key.modifiers.contains(KeyModifiers::CONTROL) {
-                                app.update_filter_input_char(c);
-                                if let Some(buf) = &app.filter_input {
-                                    app.status = format!("Filter: {}_", buf);
-                                }
+                        Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.toggle_filter_case();
+                            if let Some(buf) = &app.filter_input {
+                                app.status = format!("Filter: {}_", buf);
+                            }
+                        }
+                        Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.update_filter_input_char(c);
+                            if let Some(buf) = &app.filter_input {
+                                app.status = format!("Filter: {}_", buf);
                             }
                         }
                         _ => {}
@@ -211,6 +656,11 @@ fn run_app(
                 } else {
                     match app.mode {
                         AppMode::Normal => match key.code {
+                            KeyCode::Char('/') if app.focus == app::Focus::Tables => {
+                                app.begin_sidebar_query();
+                                dirty = true;
+                                false
+                            }
                             KeyCode::Char('/') => {
                                 filter_mode = true;
                                 app.begin_filter_input();
@@ -219,6 +669,11 @@ fn run_app(
                                 dirty = true;
                                 false
                             }
+                            KeyCode::Char(':') => {
+                                app.begin_query_input();
+                                dirty = true;
+                                false
+                            }
                             KeyCode::Char('?') => {
                                 app.toggle_help();
                                 if app.show_help {
@@ -241,12 +696,117 @@ fn run_app(
                                 dirty = true;
                                 false
                             }
+                            KeyCode::Char('n') => {
+                                app.toggle_numeric_sort();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('E') if app.query_view.is_some() => {
+                                app.status =
+                                    "Query results: cannot export (Esc returns to the table)"
+                                        .into();
+                                dirty = true;
+                                false
+                            }
                             KeyCode::Char('E') => {
                                 export_mode = true;
                                 export_path_buf.clear();
+                                // With rows selected, offer to export just those
+                                app.export_selection = app.can_export_selection();
+                                app.status = app.export_prompt_status(&export_path_buf);
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('D') => {
+                                app.begin_drop_table();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('Z') => {
+                                app.begin_truncate_table();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('R') => {
+                                app.begin_rename();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('N') => {
+                                app.begin_create_table();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('f') => {
+                                app.begin_goto_column();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('g') => {
+                                app.begin_goto_page();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('M') => {
+                                app.begin_maintenance();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('K') => {
+                                app.checkpoint_wal();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('B') => {
+                                app.begin_prompt(app::PromptKind::Backup);
+                                app.status = "Backup: type a destination path and Enter".into();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('O') => {
+                                app.begin_prompt(app::PromptKind::Attach);
                                 app.status =
-                                    "Export CSV path: type and Enter to save (Esc to cancel)"
-                                        .into();
+                                    "Attach: type a path (optionally 'as alias') and Enter".into();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Up | KeyCode::Down
+                                if key
+                                    .modifiers
+                                    .contains(crossterm::event::KeyModifiers::SHIFT)
+                                    && app.focus == app::Focus::Data =>
+                            {
+                                app.extend_selection(key.code == KeyCode::Down);
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Char('F') => {
+                                app.fill_down_selection();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Esc if app.export_running => {
+                                app.cancel_export();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Esc
+                                if app.focus == app::Focus::Tables
+                                    && !app.sidebar_query.is_empty() =>
+                            {
+                                app.clear_sidebar_query();
+                                app.status = "Sidebar filter cleared".into();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Esc if app.sel_anchor_row.is_some() => {
+                                app.clear_row_selection();
+                                app.status = "Selection cleared".into();
+                                dirty = true;
+                                false
+                            }
+                            KeyCode::Esc if app.query_view.is_some() => {
+                                app.close_query_view();
                                 dirty = true;
                                 false
                             }
@@ -259,6 +819,15 @@ fn run_app(
                                 dirty = true;
                                 false
                             }
+                            KeyCode::Left | KeyCode::Right
+                                if key
+                                    .modifiers
+                                    .contains(crossterm::event::KeyModifiers::CONTROL) =>
+                            {
+                                app.resize_sidebar(if key.code == KeyCode::Right { 2 } else { -2 });
+                                dirty = true;
+                                false
+                            }
                             _ => {
                                 if key
                                     .modifiers
@@ -268,6 +837,10 @@ fn run_app(
                                         app.copy_current_page_tsv();
                                         dirty = true;
                                         false
+                                    } else if let KeyCode::Char('r') = key.code {
+                                        app.undo_or_redo(true);
+                                        dirty = true;
+                                        false
                                     } else {
                                         let r = handle_key_normal(app, key.code);
                                         dirty = true;
@@ -285,16 +858,40 @@ fn run_app(
                             // Mark dirty only for keys that affect the edit buffer/cursor/state
                             match key.code {
                                 Enter | Esc | Backspace | Delete | Left | Right | Home | End
-                                | Char(_) => {
+                                | Tab | BackTab | Char(_) => {
                                     dirty = true;
                                 }
                                 _ => {}
                             }
                             handle_key_editing(app, key)
                         }
+                        AppMode::CreateTable => {
+                            dirty = true;
+                            handle_key_create_table(app, key)
+                        }
                     }
                 }
+            } else if let Event::Resize(..) = ev {
+                // Drawing measures the new data area; then refill or trim the window to it
+                terminal.draw(|f| ui::draw(f, app))?;
+                app.refit_visible_rows();
+                dirty = true;
+                false
             } else {
+                // Clicks only act in plain browsing, not over prompts, overlays or inputs
+                if let Event::Mouse(mouse) = ev
+                    && mouse.kind == MouseEventKind::Down(MouseButton::Left)
+                    && matches!(app.mode, AppMode::Normal)
+                    && app.prompt.is_none()
+                    && app.overlay.is_none()
+                    && !export_mode
+                    && !filter_mode
+                    && app.query_input.is_none()
+                    && !app.sidebar_query_input
+                {
+                    app.click_at(mouse.column, mouse.row);
+                    dirty = true;
+                }
                 false
             }
         } else {
@@ -305,23 +902,101 @@ fn run_app(
             return Ok(());
         }
 
-        // periodic tasks if needed (last_tick is updated when a tick-driven redraw occurs)
-
         if app.should_quit {
             return Ok(());
         }
     }
 }
 
+/// How many ticks an idle loop waits before redrawing anyway
+const IDLE_TICKS: u32 = 50;
+
+/// Read terminal events on their own thread so run_app can wait on them together with
+/// the DB and file-watch channels
+fn spawn_event_reader() -> crossbeam_channel::Receiver<io::Result<Event>> {
+    let (tx, rx) = crossbeam_channel::unbounded();
+    std::thread::spawn(move || {
+        loop {
+            let ev = event::read();
+            let failed = ev.is_err();
+            if tx.send(ev).is_err() || failed {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+fn handle_response(app: &mut App, msg: DBResponse) {
+    match msg {
+        DBResponse::ExportedCSV { ok, path, message } => {
+            app.export_running = false;
+            if ok {
+                app.status = format!("Exported CSV to {}", path);
+            } else if app.export_cancel.load(std::sync::atomic::Ordering::Relaxed) {
+                app.status = format!("Export cancelled; partial output removed from {}", path);
+            } else {
+                app.status = format!(
+                    "Export failed: {}",
+                    message.unwrap_or_else(|| "unknown error".into())
+                );
+            }
+        }
+        _ => app.handle_db_response(msg),
+    }
+}
+
+fn handle_key_overlay(app: &mut App, code: KeyCode) {
+    use app::Overlay;
+    match (&app.overlay, code) {
+        (
+            Some(
+                Overlay::DistinctValues { .. } | Overlay::SavedViews { .. } | Overlay::Info { .. },
+            ),
+            KeyCode::Up | KeyCode::Char('k'),
+        ) => app.move_overlay_selection(false),
+        (
+            Some(
+                Overlay::DistinctValues { .. } | Overlay::SavedViews { .. } | Overlay::Info { .. },
+            ),
+            KeyCode::Down | KeyCode::Char('j'),
+        ) => app.move_overlay_selection(true),
+        (Some(Overlay::DistinctValues { .. } | Overlay::SavedViews { .. }), KeyCode::Enter) => {
+            app.accept_overlay()
+        }
+        (Some(Overlay::DistinctValues { .. }), KeyCode::Char('+')) => app.raise_distinct_limit(),
+        (Some(Overlay::SavedViews { .. }), KeyCode::Char('s')) => app.begin_save_view(),
+        (Some(Overlay::SavedViews { .. }), KeyCode::Char('d')) => app.delete_selected_view(),
+        // Pickers only close explicitly; everything else closes on any key
+        (Some(Overlay::DistinctValues { .. } | Overlay::SavedViews { .. }), code)
+            if !matches!(code, KeyCode::Esc | KeyCode::Char('q')) => {}
+        _ => app.close_overlay(),
+    }
+}
+
 fn handle_key_normal(app: &mut App, code: KeyCode) -> bool {
-    match code {
-        KeyCode::Char('q') => {
-            app.should_quit = true;
+    // The focused cell viewer takes the scrolling keys; everything else works as usual
+    if app.focus == app::Focus::Viewer {
+        let page = app.viewer_page.max(1) as i32;
+        let lines = match code {
+            KeyCode::Up | KeyCode::Char('k') => Some(-1),
+            KeyCode::Down | KeyCode::Char('j') => Some(1),
+            KeyCode::PageUp => Some(-page),
+            KeyCode::PageDown => Some(page),
+            _ => None,
+        };
+        if let Some(lines) = lines {
+            app.scroll_viewer_lines(lines);
+            return false;
         }
+    }
+    match code {
+        KeyCode::Char('q') => app.request_quit(),
         KeyCode::Up => {
             if app.focus == app::Focus::Tables {
                 app.move_table_selection_up()
             } else {
+                app.clear_row_selection();
                 app.move_cell_up()
             }
         }
@@ -329,35 +1004,55 @@ fn handle_key_normal(app: &mut App, code: KeyCode) -> bool {
             if app.focus == app::Focus::Tables {
                 app.move_table_selection_down()
             } else {
+                app.clear_row_selection();
                 app.move_cell_down()
             }
         }
         KeyCode::Tab => {
             app.toggle_focus();
         }
-        KeyCode::Enter => app.load_selected_table_page(0),
+        KeyCode::Enter => {
+            if app.focus == app::Focus::Tables {
+                app.activate_sidebar_selection()
+            } else {
+                app.load_selected_table_page(0)
+            }
+        }
         KeyCode::PageDown => app.next_page(),
         KeyCode::PageUp => app.prev_page(),
+        KeyCode::Home => app.first_page(),
+        KeyCode::End => app.last_page(),
         KeyCode::Left => app.move_cell_left(),
         KeyCode::Right => app.move_cell_right(),
         KeyCode::Char('j') => app.move_cell_down(),
         KeyCode::Char('k') => app.move_cell_up(),
         KeyCode::Char('e') => app.begin_edit_cell(),
-        KeyCode::Char('r') => app.reload_current_table(),
+        KeyCode::Char('r') => app.refresh_current_table(),
         KeyCode::Char('c') => {
             app.copy_current_cell_tsv();
         }
         KeyCode::Char('C') => {
             app.copy_current_row_tsv();
         }
-        KeyCode::Char('u') => {
-            if let Some(table) = app.current_table_name().map(|s| s.to_string()) {
-                let _ = app.req_tx.send(DBRequest::UndoLastChange { table });
-                app.status = "Undoing last change...".into();
-            } else {
-                app.status = "No table selected to undo".into();
-            }
+        KeyCode::Char('Y') => {
+            app.copy_current_column_tsv();
         }
+        KeyCode::Char('T') => app.cycle_copy_format(),
+        KeyCode::Char('y') => app.duplicate_selected_row(),
+        KeyCode::Char('d') => app.delete_current_row(),
+        KeyCode::Char('o') => app.begin_insert_row(),
+        KeyCode::Char('p') => app.paste_row_from_clipboard(),
+        KeyCode::Char('m') => app.toggle_bookmark(),
+        KeyCode::Char('w') => app.open_saved_views(),
+        KeyCode::Char('#') => app.toggle_approx_count(),
+        KeyCode::Char('\'') => app.jump_to_bookmark(true),
+        KeyCode::Char('"') => app.jump_to_bookmark(false),
+        KeyCode::Char('i') => app.request_column_stats(),
+        KeyCode::Char('V') => app.request_distinct_values(),
+        KeyCode::Char('I') => app.request_integrity_check(),
+        KeyCode::Char('X') => app.begin_delete_matching(),
+        KeyCode::Char('t') => app.transaction_key(),
+        KeyCode::Char('u') => app.undo_or_redo(false),
         KeyCode::Char('+') | KeyCode::Char('=') => {
             app.resize_current_column_wider();
             app.status = "Column width: wider".into();
@@ -374,6 +1069,18 @@ fn handle_key_normal(app: &mut App, code: KeyCode) -> bool {
             app.request_autosize_all_columns();
             app.status = "Autosizing all columns…".into();
         }
+        KeyCode::Char('J') => app.toggle_viewer_pretty_json(),
+        KeyCode::Char('[') => app.scroll_viewer(-1),
+        KeyCode::Char(']') => app.scroll_viewer(1),
+        KeyCode::F(2) => app.toggle_align_numbers(),
+        KeyCode::F(3) => app.toggle_zebra(),
+        KeyCode::F(4) => app.toggle_row_numbers(),
+        KeyCode::F(5) => app.toggle_rowid_column(),
+        KeyCode::F(6) => app.toggle_sidebar(),
+        KeyCode::Char('z') => app.toggle_zen(),
+        KeyCode::Char('!') => app.show_error_log(),
+        KeyCode::Char('G') => app.toggle_group_view(),
+        KeyCode::Char('W') => app.begin_save_blob(),
         KeyCode::Char('v') => {
             app.toggle_cell_viewer();
             if app.show_cell_viewer {
@@ -387,6 +1094,29 @@ fn handle_key_normal(app: &mut App, code: KeyCode) -> bool {
     false
 }
 
+fn handle_key_create_table(app: &mut App, key: crossterm::event::KeyEvent) -> bool {
+    use crossterm::event::{KeyCode::*, KeyModifiers};
+
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        Enter => app.submit_create_table(),
+        Esc => app.cancel_create_table(),
+        Up => app.create_form_move_row(false),
+        Down => app.create_form_move_row(true),
+        Left => app.create_form_cycle_type(false),
+        Right => app.create_form_cycle_type(true),
+        Tab => app.create_form_next_field(false),
+        BackTab => app.create_form_next_field(true),
+        Backspace => app.create_form_backspace(),
+        Char('a') if ctrl => app.create_form_add_column(),
+        Char('x') if ctrl => app.create_form_remove_column(),
+        Char(' ') => app.create_form_toggle_or_space(),
+        Char(c) if !ctrl => app.create_form_insert(c),
+        _ => {}
+    }
+    false
+}
+
 fn handle_key_editing(app: &mut App, key: crossterm::event::KeyEvent) -> bool {
     use crossterm::event::{KeyCode::*, KeyModifiers};
 
@@ -399,9 +1129,18 @@ fn handle_key_editing(app: &mut App, key: crossterm::event::KeyEvent) -> bool {
         Right => app.edit_input_right(),
         Home => app.edit_input_home(),
         End => app.edit_input_end(),
+        Tab if app.inserting() => app.insert_next_field(false),
+        BackTab if app.inserting() => app.insert_next_field(true),
+        Tab => app.cycle_edit_type(),
         Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
             app.edit_mark_null();
         }
+        Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.paste_clipboard_into_edit();
+        }
+        Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.cycle_edit_expr();
+        }
         Char(c) => {
             let c = if key.modifiers.contains(KeyModifiers::CONTROL) {
                 // ignore control chars in insert
@@ -1,22 +1,31 @@
-use crate::app::{App, AppMode, Focus};
+use crate::app::{
+    App, AppMode, Focus, Overlay, SidebarEntry, SidebarSection, format_bytes, group_thousands,
+};
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+
 use ratatui::{
     Frame,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Flex, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Table, Wrap},
+    widgets::{
+        Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, Wrap,
+        block::Title,
+    },
 };
 
 pub fn draw(f: &mut Frame, app: &mut App) {
-    // Layout: when help is visible, allocate an extra pane above the status line
+    // Layout: when help is visible, allocate an extra pane above the status line.
+    // The status line is 2 rows: its top border plus the text itself.
     let constraints = if app.show_help {
         vec![
             Constraint::Min(1),
-            Constraint::Length(15),
-            Constraint::Length(1),
+            Constraint::Length(18),
+            Constraint::Length(2),
         ]
     } else {
-        vec![Constraint::Min(1), Constraint::Length(1)]
+        vec![Constraint::Min(1), Constraint::Length(2)]
     };
 
     let chunks = Layout::default()
@@ -31,29 +40,339 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         (None, chunks[1])
     };
 
-    let body_constraints = if app.show_cell_viewer {
-        vec![
-            Constraint::Length(30),
-            Constraint::Min(10),
-            Constraint::Length(40),
-        ]
+    // Hidden sidebar: the data pane takes its space; never more than half the screen
+    let sidebar_width = if app.show_sidebar && !app.zen {
+        app.sidebar_width.min(top.width / 2)
     } else {
-        vec![Constraint::Length(30), Constraint::Min(10)]
+        0
     };
+    let mut body_constraints = vec![Constraint::Length(sidebar_width), Constraint::Min(10)];
+    if app.show_cell_viewer {
+        body_constraints.push(Constraint::Length(40));
+    }
     let body_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints(body_constraints)
         .split(top);
 
-    draw_tables(f, body_chunks[0], app);
+    if sidebar_width > 0 {
+        draw_tables(f, body_chunks[0], app);
+    }
     draw_data(f, body_chunks[1], &mut *app);
     if app.show_cell_viewer && body_chunks.len() > 2 {
-        draw_cell_viewer(f, body_chunks[2], app);
+        draw_cell_viewer(f, body_chunks[2], &mut *app);
     }
     if let Some(help_area) = help_area_opt {
         draw_help(f, help_area, app);
     }
     draw_status(f, status_area, app);
+    if let AppMode::CreateTable = app.mode {
+        draw_create_table(f, top, app);
+    }
+    if app.overlay.is_some() {
+        draw_overlay(f, top, app);
+    }
+}
+
+fn draw_overlay(f: &mut Frame, area: Rect, app: &App) {
+    let mut scroll = 0;
+    let mut footer = "any key closes";
+    let (title, lines) = match app.overlay.as_ref() {
+        Some(Overlay::Info {
+            title,
+            lines,
+            scroll: offset,
+        }) => {
+            scroll = *offset;
+            footer = "Up/Down scroll, any other key closes";
+            (
+                title.clone(),
+                lines.iter().map(|l| Line::from(l.as_str())).collect(),
+            )
+        }
+        Some(Overlay::DistinctValues { .. }) => {
+            draw_value_picker(f, area, app);
+            return;
+        }
+        Some(Overlay::SavedViews { .. }) => {
+            draw_saved_views(f, area, app);
+            return;
+        }
+        Some(Overlay::ColumnStats {
+            table,
+            column,
+            filtered,
+            stats,
+        }) => {
+            let label = |name: &str| {
+                Span::styled(format!("{:<10}", name), Style::default().fg(Color::Cyan))
+            };
+            let mut lines = vec![
+                Line::from(vec![label("Rows"), Span::raw(group_thousands(stats.rows))]),
+                Line::from(vec![
+                    label("Distinct"),
+                    Span::raw(group_thousands(stats.distinct)),
+                ]),
+                Line::from(vec![
+                    label("NULLs"),
+                    Span::raw(group_thousands(stats.nulls)),
+                ]),
+            ];
+            match (&stats.min, &stats.max) {
+                (Some(min), Some(max)) => {
+                    lines.push(Line::from(vec![label("Min"), Span::raw(min.clone())]));
+                    lines.push(Line::from(vec![label("Max"), Span::raw(max.clone())]));
+                    if let Some(avg) = stats.avg {
+                        lines.push(Line::from(vec![
+                            label("Avg"),
+                            Span::raw(format!("{:.4}", avg)),
+                        ]));
+                    }
+                }
+                _ => lines.push(Line::from(Span::styled(
+                    "Not numeric: min/max/avg skipped",
+                    Style::default().fg(Color::DarkGray),
+                ))),
+            }
+            let scope = if *filtered {
+                "filtered rows"
+            } else {
+                "all rows"
+            };
+            (format!("Stats — {}.{} ({})", table, column, scope), lines)
+        }
+        None => return,
+    };
+    let popup = centered_rect(60, 50, area);
+    f.render_widget(Clear, popup);
+    let p = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll as u16, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title(title)
+                .title_bottom(footer),
+        );
+    f.render_widget(p, popup);
+}
+
+fn draw_value_picker(f: &mut Frame, area: Rect, app: &App) {
+    let Some(Overlay::DistinctValues {
+        table,
+        column,
+        values,
+        total,
+        selected,
+        ..
+    }) = app.overlay.as_ref()
+    else {
+        return;
+    };
+    let popup = centered_rect(50, 70, area);
+    f.render_widget(Clear, popup);
+    let width = values
+        .iter()
+        .map(|(v, _)| v.as_deref().unwrap_or("NULL").chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(60);
+    let items: Vec<ListItem> = values
+        .iter()
+        .map(|(v, n)| {
+            ListItem::new(format!(
+                "{:<width$}  {:>10}",
+                v.as_deref().unwrap_or("NULL"),
+                group_thousands(*n),
+                width = width
+            ))
+        })
+        .collect();
+    let footer = if *total > values.len() {
+        format!(
+            "showing first {} of {} — + more, Enter filter, Esc close",
+            group_thousands(values.len() as i64),
+            group_thousands(*total as i64)
+        )
+    } else {
+        "Enter filter, Esc close".to_string()
+    };
+    let mut state = ListState::default().with_selected(Some(*selected));
+    let list = List::new(items)
+        .highlight_style(Style::default().bg(Color::Blue).fg(Color::Black))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title(format!("Values — {}.{}", table, column))
+                .title_bottom(footer),
+        );
+    f.render_stateful_widget(list, popup, &mut state);
+}
+
+fn draw_saved_views(f: &mut Frame, area: Rect, app: &App) {
+    let Some(Overlay::SavedViews { views, selected }) = app.overlay.as_ref() else {
+        return;
+    };
+    let popup = centered_rect(60, 60, area);
+    f.render_widget(Clear, popup);
+    let width = views
+        .iter()
+        .map(|v| v.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(30);
+    let items: Vec<ListItem> = if views.is_empty() {
+        vec![ListItem::new(Span::styled(
+            "No saved views — s saves the current filter and sort",
+            Style::default().fg(Color::DarkGray),
+        ))]
+    } else {
+        views
+            .iter()
+            .map(|v| {
+                let sort = match (
+                    v.sort_by.as_deref().map(crate::db::split_numeric_sort),
+                    v.sort_dir,
+                ) {
+                    (Some((col, numeric)), dir) => format!(
+                        "  {}{}{}",
+                        if dir == Some(crate::db::SortDir::Desc) {
+                            "↓"
+                        } else {
+                            "↑"
+                        },
+                        col,
+                        if numeric { " (numeric)" } else { "" }
+                    ),
+                    _ => String::new(),
+                };
+                let filter = v
+                    .filter
+                    .as_deref()
+                    .map(|f| format!("  /{}", f))
+                    .unwrap_or_default();
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{:<width$}  ", v.name, width = width)),
+                    Span::styled(v.table.clone(), Style::default().fg(Color::Cyan)),
+                    Span::raw(filter),
+                    Span::styled(sort, Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect()
+    };
+    let selected = (!views.is_empty()).then_some(*selected);
+    let mut state = ListState::default().with_selected(selected);
+    let list = List::new(items)
+        .highlight_style(Style::default().bg(Color::Blue).fg(Color::Black))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title("Saved views")
+                .title_bottom("Enter apply, s save current, d delete, Esc close"),
+        );
+    f.render_stateful_widget(list, popup, &mut state);
+}
+
+// Rect of the given percentage size centered within `area`
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+fn draw_create_table(f: &mut Frame, area: Rect, app: &App) {
+    let Some(form) = app.create_form.as_ref() else {
+        return;
+    };
+    let popup = centered_rect(70, 80, area);
+    f.render_widget(Clear, popup);
+
+    let focus = Style::default().bg(Color::Blue).fg(Color::Black);
+    let field = |text: String, focused: bool| {
+        if focused {
+            Span::styled(text, focus)
+        } else {
+            Span::raw(text)
+        }
+    };
+    let check = |b: bool| if b { "[x]" } else { "[ ]" };
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Table name: ", Style::default().fg(Color::Cyan)),
+            field(format!("{}_", form.name), form.row == 0),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(
+            "   Column               Type          Null  PK",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )),
+    ];
+    for (i, col) in form.columns.iter().enumerate() {
+        let row_focused = form.row == i + 1;
+        let marker = if row_focused { "▶ " } else { "  " };
+        lines.push(Line::from(vec![
+            Span::raw(marker),
+            field(format!(" {:<20}", col.name), row_focused && form.field == 0),
+            field(
+                format!(" {:<13}", col.col_type),
+                row_focused && form.field == 1,
+            ),
+            field(
+                format!(" {} ", check(col.nullable)),
+                row_focused && form.field == 2,
+            ),
+            field(
+                format!(" {} ", check(col.pk)),
+                row_focused && form.field == 3,
+            ),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Preview:",
+        Style::default().fg(Color::Cyan),
+    )));
+    match form.ddl() {
+        Ok(sql) => lines.extend(sql.lines().map(|l| Line::from(l.to_string()))),
+        Err(e) => lines.push(Line::from(Span::styled(
+            e,
+            Style::default().fg(Color::DarkGray),
+        ))),
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Up/Down row | Tab/Shift+Tab field | Left/Right type | Space toggle | Ctrl+a add column | Ctrl+x remove column | Enter create | Esc cancel",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let p = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title("Create table"),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(p, popup);
 }
 
 fn draw_help(f: &mut Frame, area: Rect, _app: &App) {
@@ -66,22 +385,52 @@ fn draw_help(f: &mut Frame, area: Rect, _app: &App) {
                 .add_modifier(Modifier::BOLD),
         )),
         Line::from(""),
-        Line::from("Global:        q Quit  | r Reload table  | ? Toggle keybinds"),
-        Line::from("Tables:        Up/Down Move selection    | Enter Open selected table"),
         Line::from(
-            "Data:          Left/Right Move column    | Up/Down or j/k Move row   | PageUp/PageDown Prev/Next page   | +/- (=/_) Adjust width",
+            "Global:        q Quit  | r Reload table (clears edit underlines) | ? Toggle keybinds | ! Error log | Tab Next pane (Schema → Data → Viewer)",
+        ),
+        Line::from(
+            "Tables:        Up/Down Move selection    | Enter Open table/view, fold section, show index SQL | / Find (fuzzy; Esc clears)",
+        ),
+        Line::from(
+            "Data:          Left/Right Move column    | Up/Down or j/k Move row   | PageUp/PageDown Prev/Next page | g Go to page | f Go to column | # Exact/estimated counts | Home/End First/last row | +/- (=/_) Adjust width",
+        ),
+        Line::from(
+            "Editing:       e Edit cell               | Enter Save   | Esc Cancel  | Ctrl-d NULL / empty string | Ctrl-t CURRENT_TIMESTAMP/DATE/TIME | Ctrl-v Paste | Tab Cycle type | u Undo last change | Ctrl-r Redo",
+        ),
+        Line::from(
+            "Rows:          y Duplicate selected row  | d Delete selected row | o Insert row | p Paste row from clipboard | Shift+Up/Down Select rows | F Fill anchor value over selection | X Delete rows matching filter",
         ),
         Line::from(
-            "Editing:       e Edit cell               | Enter Save   | Esc Cancel  | Ctrl-d Set NULL | u Undo last change",
+            "Bookmarks:     m Toggle bookmark on row | ' Next bookmark | \" Previous bookmark",
         ),
         Line::from(
-            "Filter:        / Begin filter  | Enter Apply  | Esc Clear (also in normal mode)",
+            "Views:         w Saved filter/sort views (Enter apply, s save current, d delete)",
+        ),
+        Line::from(
+            "Filter:        / Begin filter  | Enter Apply  | Esc Clear (also in normal mode) | column=value exact, column:text contains (space = AND, | = OR) | Ctrl+s (or cs:) case-sensitive",
+        ),
+        Line::from(
+            "Query:         : Run a SELECT / WITH query (results replace the table, read-only) | Tab Complete a table/column name | Esc Back to the table",
+        ),
+        Line::from(
+            "Sorting:       s Cycle sort by column     | S Toggle direction | n Numeric/stored order",
+        ),
+        Line::from(
+            "Copy:          c Copy cell | C Copy row/selection | Y Copy column | Ctrl+C Copy page | T TSV/CSV/JSON",
         ),
-        Line::from("Sorting:       s Cycle sort by column     | S Toggle direction"),
-        Line::from("Copy:          c Copy cell | C Copy row | Ctrl+C Copy page (TSV)"),
         Line::from("Autosize:      a Autosize column | A Autosize all"),
-        Line::from("Viewer:        v Toggle cell viewer (shows full content)"),
-        Line::from("Export:        E Export CSV (type path, Enter to save, Esc to cancel)"),
+        Line::from(
+            "Viewer:        v Toggle cell viewer (shows full content; [ ] scroll, or Tab to it for j/k/PageUp/PageDown; J raw/pretty JSON; W save BLOB) | i Column stats | V Pick a value to filter on | G Counts per value (G returns) | F2 Right-align numbers | F3 Zebra | F4 Row numbers | F5 __rowid__ column | F6 Sidebar, Ctrl+Left/Right its width | z Full-screen data",
+        ),
+        Line::from(
+            "Export:        E Export CSV (type path, Enter to save, Esc to cancel; Esc while running stops it)",
+        ),
+        Line::from(
+            "Databases:     O Attach another database (path [as alias]) | N New table | D Drop table (type name to confirm) | Z Delete all rows (type name) | I Integrity check | M ANALYZE/REINDEX | K Checkpoint the WAL | B Backup | t Begin/commit/rollback",
+        ),
+        Line::from(
+            "Rename:        R Rename selected table (sidebar focus) or selected column (data focus)",
+        ),
     ];
     let p =
         Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Keybindings"));
@@ -89,17 +438,84 @@ fn draw_help(f: &mut Frame, area: Rect, _app: &App) {
 }
 
 fn draw_tables(f: &mut Frame, area: Rect, app: &App) {
+    let header_style = Style::default()
+        .fg(Color::DarkGray)
+        .add_modifier(Modifier::BOLD);
     let items: Vec<ListItem> = app
-        .tables
-        .iter()
-        .map(|t| ListItem::new(t.clone()))
+        .sidebar_entries()
+        .into_iter()
+        .map(|entry| match entry {
+            SidebarEntry::Section(section) => {
+                let names: Vec<&str> = match section {
+                    SidebarSection::Tables => app.tables.iter().map(|t| t.as_str()).collect(),
+                    SidebarSection::Views => app.views.iter().map(|v| v.as_str()).collect(),
+                    SidebarSection::Indexes => {
+                        app.indexes.iter().map(|i| i.name.as_str()).collect()
+                    }
+                };
+                // While filtering, show how many entries match out of the total
+                let count = if app.sidebar_query.is_empty() {
+                    names.len().to_string()
+                } else {
+                    let matched = names.iter().filter(|n| app.sidebar_matches(n)).count();
+                    format!("{}/{}", matched, names.len())
+                };
+                let marker = if app.collapsed_sections.contains(&section) {
+                    "▸"
+                } else {
+                    "▾"
+                };
+                ListItem::new(Line::from(Span::styled(
+                    format!("{} {} ({})", marker, section.label(), count),
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                )))
+            }
+            SidebarEntry::Schema(i) => ListItem::new(Line::from(Span::styled(
+                format!("  {}", app.schemas.get(i).map(|s| s.as_str()).unwrap_or("")),
+                header_style,
+            ))),
+            SidebarEntry::Table(i) => {
+                let name = &app.tables[i];
+                match app.table_counts.get(name) {
+                    Some(n) => {
+                        let approx = if app.estimated_counts.contains(name) {
+                            "~"
+                        } else {
+                            ""
+                        };
+                        ListItem::new(Line::from(vec![
+                            Span::raw(format!("  {} ", name)),
+                            Span::styled(
+                                format!("({}{})", approx, group_thousands(*n)),
+                                Style::default().fg(Color::DarkGray),
+                            ),
+                        ]))
+                    }
+                    None => ListItem::new(format!("  {}", name)),
+                }
+            }
+            SidebarEntry::View(i) => ListItem::new(format!("  {}", app.views[i])),
+            SidebarEntry::Index(i) => ListItem::new(Line::from(Span::styled(
+                format!("  {}", app.indexes[i].name),
+                Style::default().fg(Color::DarkGray),
+            ))),
+        })
         .collect();
 
     // Visually indicate focus on the Tables pane by changing border color and title
+    let spinner = app
+        .schema_loading
+        .map(|since| {
+            let frame = (since.elapsed().as_millis() / 100) as usize % SPINNER.len();
+            format!(" {}", SPINNER[frame])
+        })
+        .unwrap_or_default();
     let title = if app.focus == Focus::Tables {
-        "Tables ◀"
+        format!("Schema{} ◀", spinner)
     } else {
-        "Tables"
+        format!("Schema{}", spinner)
     };
     let block = if app.focus == Focus::Tables {
         Block::default()
@@ -110,27 +526,67 @@ fn draw_tables(f: &mut Frame, area: Rect, app: &App) {
         Block::default().borders(Borders::ALL).title(title)
     };
 
-    let list = List::new(items).block(block).highlight_style(
+    // The sidebar filter takes the first line inside the block
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let list_area = if app.sidebar_query_input || !app.sidebar_query.is_empty() {
+        let [query_area, rest] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+        let cursor = if app.sidebar_query_input { "_" } else { "" };
+        f.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled("/", Style::default().fg(Color::Yellow)),
+                Span::raw(format!("{}{}", app.sidebar_query, cursor)),
+            ])),
+            query_area,
+        );
+        rest
+    } else {
+        inner
+    };
+
+    let items_empty = items.is_empty();
+    // Instead of bare section headers, say why there is nothing: still loading, or a
+    // database without tables
+    if app.tables.is_empty() && app.views.is_empty() && app.sidebar_query.is_empty() {
+        let text = if app.schema_loading.is_some() {
+            "Loading schema…"
+        } else {
+            "No tables — press N to create one (or O to attach a database)"
+        };
+        f.render_widget(
+            Paragraph::new(text)
+                .style(Style::default().fg(Color::DarkGray))
+                .wrap(Wrap { trim: true }),
+            list_area,
+        );
+        return;
+    }
+    let list = List::new(items).highlight_style(
         Style::default()
             .add_modifier(Modifier::BOLD)
             .fg(Color::Yellow),
     );
 
-    f.render_stateful_widget(list, area, &mut list_state(app));
-}
-
-fn list_state(app: &App) -> ratatui::widgets::ListState {
-    let mut st = ratatui::widgets::ListState::default();
-    if !app.tables.is_empty() {
-        st.select(Some(app.selected_table));
+    let mut state = ratatui::widgets::ListState::default();
+    if !items_empty {
+        state.select(Some(app.selected_table));
     }
-    st
+    f.render_stateful_widget(list, list_area, &mut state);
 }
 
+/// A table load taking longer than this gets the "Loading…" overlay (quick ones would flicker)
+const LOADING_OVERLAY_DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// Frames of the schema and table loading spinners, one per 100ms
+const SPINNER: [&str; 4] = ["|", "/", "-", "\\"];
+
 fn draw_status(f: &mut Frame, area: Rect, app: &App) {
     let mode = match app.mode {
-        AppMode::Normal => "NORMAL",
-        AppMode::Editing { .. } => "EDIT",
+        AppMode::Normal => "NORMAL".to_string(),
+        AppMode::Editing { .. } if app.inserting() => "INSERT".to_string(),
+        AppMode::Editing { .. } => format!("EDIT {}", app.edit_type_label()),
+        AppMode::CreateTable => "CREATE".to_string(),
     };
 
     let filter_str = app
@@ -139,13 +595,40 @@ fn draw_status(f: &mut Frame, area: Rect, app: &App) {
         .map(|s| format!(" | filter: {}", s))
         .unwrap_or_default();
 
-    let sort_str = match (&app.sort_by, app.sort_dir) {
-        (Some(col), Some(crate::db::SortDir::Asc)) => format!(" | sort: {} ↑", col),
-        (Some(col), Some(crate::db::SortDir::Desc)) => format!(" | sort: {} ↓", col),
-        (Some(col), None) => format!(" | sort: {}", col),
+    let numeric = if app.sort_is_numeric() {
+        " (numeric)"
+    } else {
+        ""
+    };
+    let sort_str = match (app.sort_column(), app.sort_dir) {
+        (Some(col), Some(crate::db::SortDir::Asc)) => format!(" | sort: {} ↑{}", col, numeric),
+        (Some(col), Some(crate::db::SortDir::Desc)) => format!(" | sort: {} ↓{}", col, numeric),
+        (Some(col), None) => format!(" | sort: {}{}", col, numeric),
         _ => String::new(),
     };
 
+    if let Some(p) = &app.prompt {
+        let text = Line::from(vec![
+            Span::styled(
+                "[PROMPT] ",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!("{}: {}_", p.kind.label(), p.input)),
+        ]);
+        let p = Paragraph::new(text).block(status_block(app));
+        f.render_widget(p, area);
+        return;
+    }
+
+    let ro = if app.immutable {
+        "[IMMUTABLE] "
+    } else if app.read_only {
+        "[RO] "
+    } else {
+        ""
+    };
     let text = Line::from(vec![
         Span::styled(
             format!("[{mode}] "),
@@ -153,7 +636,31 @@ fn draw_status(f: &mut Frame, area: Rect, app: &App) {
                 .fg(Color::Cyan)
                 .add_modifier(Modifier::BOLD),
         ),
-        Span::raw(&app.status),
+        Span::styled(ro, Style::default().fg(Color::Yellow)),
+        Span::styled(
+            if app.in_transaction { "[TXN] " } else { "" },
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ),
+        // Database errors stand out until the next status replaces them
+        if app.status.starts_with("Error: ") {
+            Span::styled(
+                &app.status,
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::raw(&app.status)
+        },
+        match (&app.mode, app.clipped_cell_text()) {
+            (AppMode::Normal, Some(full)) => Span::styled(
+                format!(" | {}", full.replace('\n', "⏎")),
+                Style::default().fg(Color::Gray),
+            ),
+            _ => Span::raw(""),
+        },
         Span::raw(filter_str),
         Span::raw(sort_str),
         match app.mode {
@@ -161,13 +668,104 @@ fn draw_status(f: &mut Frame, area: Rect, app: &App) {
             _ => Span::raw("".to_string()),
         },
     ]);
-    let p = Paragraph::new(text).block(Block::default().borders(Borders::TOP));
-    f.render_widget(p, area);
+    // Cursor position stays pinned to the right; the message gets the rest
+    let block = status_block(app);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let position = cursor_position(app);
+    let [msg_area, pos_area] = Layout::horizontal([
+        Constraint::Min(0),
+        Constraint::Length(position.chars().count() as u16),
+    ])
+    .areas(inner);
+    f.render_widget(Paragraph::new(text), msg_area);
+    f.render_widget(
+        Paragraph::new(Span::styled(position, Style::default().fg(Color::DarkGray))),
+        pos_area,
+    );
+}
+
+// " R 4,512/120,000 · C name[3/12]" for the selected cell; empty without data
+fn cursor_position(app: &App) -> String {
+    if app.columns.is_empty() || app.rows.is_empty() {
+        return String::new();
+    }
+    let row = group_thousands(app.cursor_row_abs() as i64 + 1);
+    let approx = if app.total_estimated { "~" } else { "" };
+    let total = app
+        .total_rows
+        .map(|t| format!("/{}{}", approx, group_thousands(t as i64)))
+        .unwrap_or_default();
+    let col = app
+        .columns
+        .get(app.sel_col)
+        .map(|c| c.as_str())
+        .unwrap_or("");
+    // Count the columns the user sees
+    let first = app.first_visible_col();
+    let was = match app.selected_previous_value() {
+        Some(prev) => {
+            let text = prev.as_deref().unwrap_or(&app.null_display);
+            format!(" · was: {}", clip_with_ellipsis(Cow::Borrowed(text), 24))
+        }
+        None => String::new(),
+    };
+    format!(
+        " R {}{} · C {}[{}/{}]{}",
+        row,
+        total,
+        col,
+        app.sel_col.saturating_sub(first) + 1,
+        app.columns.len() - first,
+        was
+    )
+}
+
+// Top rule of the status line, with database size and SQLite version on the right,
+// and a checkpoint warning on the left once the WAL has grown large
+fn status_block(app: &App) -> Block<'static> {
+    let info = match (app.db_size, &app.sqlite_version) {
+        (Some(size), Some(version)) => format!(" {} · SQLite {} ", format_bytes(size), version),
+        (Some(size), None) => format!(" {} ", format_bytes(size)),
+        (None, Some(version)) => format!(" SQLite {} ", version),
+        (None, None) => String::new(),
+    };
+    let block = Block::default()
+        .borders(Borders::TOP)
+        .title(Title::from(info).alignment(Alignment::Right))
+        .title_style(Style::default().fg(Color::DarkGray));
+    match app.wal_size {
+        Some(wal) if app.wal_oversized() => block.title(
+            Title::from(Span::styled(
+                format!(" WAL {} — K checkpoints it ", format_bytes(wal)),
+                Style::default().fg(Color::Yellow),
+            ))
+            .alignment(Alignment::Left),
+        ),
+        _ => block,
+    }
 }
 
 fn draw_data(f: &mut Frame, area: Rect, app: &mut App) {
-    let base_title = if let Some(t) = app.current_table_name() {
-        format!("Data — {} (page {})", t, app.page + 1)
+    let base_title = if app.query_view.is_some() {
+        let rows = app.total_rows.unwrap_or_default();
+        format!(
+            "Data — query results ({} row{}; Esc: back to the table)",
+            group_thousands(rows as i64),
+            if rows == 1 { "" } else { "s" }
+        )
+    } else if let Some(t) = app.current_table_name() {
+        let page = match app.page_count() {
+            Some(pages) if app.total_estimated => {
+                format!("Data — {} (page {} of ~{})", t, app.page + 1, pages)
+            }
+            Some(pages) => format!("Data — {} (page {} of {})", t, app.page + 1, pages),
+            None => format!("Data — {} (page {})", t, app.page + 1),
+        };
+        match &app.group_view {
+            Some(g) => format!("{page} — COUNT(*) by {} (G: back to rows)", g.column),
+            None => page,
+        }
     } else {
         "Data".to_string()
     };
@@ -185,6 +783,7 @@ fn draw_data(f: &mut Frame, area: Rect, app: &mut App) {
         Block::default().borders(Borders::ALL).title(title)
     };
     if app.columns.is_empty() {
+        app.header_hits.clear();
         let p = Paragraph::new("Select a table and press Enter").block(block);
         f.render_widget(p, area);
         return;
@@ -193,18 +792,54 @@ fn draw_data(f: &mut Frame, area: Rect, app: &mut App) {
     // Compute inner area then render outer block
     let inner = block.inner(area);
     f.render_widget(block, area);
+    // Zen mode drops the filter bar, except while a filter is being typed
+    let filter_bar = if app.zen && app.filter_input.is_none() && app.query_input.is_none() {
+        0
+    } else {
+        1
+    };
     let inner_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(1), Constraint::Min(1)].as_ref())
+        .constraints([Constraint::Length(filter_bar), Constraint::Min(1)].as_ref())
         .split(inner);
 
-    // Filter bar
-    let filter_text = if let Some(buf) = app.filter_input.as_ref() {
-        format!("Filter: {}_   (Enter to apply, Esc to clear)", buf)
+    // Filter bar; FTS5 tables search with MATCH (best matches first unless sorted)
+    let case_sensitive = app
+        .filter_input
+        .as_ref()
+        .or(app.filter.as_ref())
+        .is_some_and(|f| f.starts_with(crate::db::CASE_SENSITIVE_PREFIX));
+    let label = if app.loaded_is_fts() {
+        "FTS5 MATCH"
+    } else if case_sensitive {
+        "Filter (case-sensitive)"
+    } else {
+        "Filter"
+    };
+    let filter_text = if let Some(buf) = app.query_input.as_ref() {
+        let room = (inner_chunks[0].width as usize).saturating_sub("Query".len() + 3);
+        format!(
+            "Query: {}_   (Tab completes names, Enter to run, Esc to cancel)",
+            tail_to_width(buf, room)
+        )
+    } else if let Some(sql) = app.query_view.as_ref() {
+        format!("Query: {}   (: to edit, Esc to close)", sql)
+    } else if let Some(buf) = app.filter_input.as_ref() {
+        // Keep the end of long input (where the cursor is) on screen
+        let room = (inner_chunks[0].width as usize).saturating_sub(label.len() + 3);
+        format!(
+            "{label}: {}_   (Enter to apply, Esc to clear)",
+            tail_to_width(buf, room)
+        )
     } else if let Some(s) = app.filter.as_ref() {
-        format!("Filter: {}   (Esc to clear)", s)
+        match app.filter_summary() {
+            Some(summary) => format!("{label}: {}   → {}   (Esc to clear)", s, summary),
+            None => format!("{label}: {}   (Esc to clear)", s),
+        }
+    } else if app.loaded_is_fts() {
+        format!("{label}: (none)   (/ to search full text)")
     } else {
-        "Filter: (none)   (/ to filter)".to_string()
+        format!("{label}: (none)   (/ to filter)")
     };
     let filter_line = Paragraph::new(filter_text).style(Style::default().fg(Color::DarkGray));
     f.render_widget(filter_line, inner_chunks[0]);
@@ -234,13 +869,80 @@ fn draw_data(f: &mut Frame, area: Rect, app: &mut App) {
         }
     }
     // Table inside inner area
-    let widths = column_widths(
-        inner.width,
-        app.columns.len(),
-        app.column_width_tiers(),
-        &app.col_abs_widths,
+    // Optional row-number gutter: wide enough for the last visible row number
+    let first_row_number = app.buffer_offset + app.view_start + 1;
+    // Bookmarked rows get a ● in the gutter (one extra column while the table has any)
+    let marks = app.table_has_bookmarks();
+    let number_width = if app.show_row_numbers {
+        (first_row_number + app.rows.len()).to_string().len() as u16
+    } else {
+        0
+    };
+    let gutter_width = number_width + u16::from(marks);
+    let gutter_space = if gutter_width > 0 {
+        gutter_width + 1
+    } else {
+        0
+    };
+    // A hidden __rowid__ and columns scrolled off to the left are skipped here; indices
+    // below stay those of `app.columns`
+    app.columns_width = inner.width.saturating_sub(gutter_space);
+    let (first_col, shown) = app.shown_columns(app.columns_width);
+    app.col_scroll = first_col;
+    let mut widths = column_widths(
+        inner.width.saturating_sub(gutter_space),
+        shown,
+        app.column_width_tiers()
+            .get(first_col..first_col + shown)
+            .unwrap_or(&[]),
+        app.col_abs_widths
+            .get(first_col..first_col + shown)
+            .unwrap_or(&[]),
+    );
+    let mut header_cells = Vec::with_capacity(app.columns.len() + 1);
+    if gutter_width > 0 {
+        widths.insert(0, Constraint::Length(gutter_width));
+        let label = if app.show_row_numbers { "#" } else { "" };
+        header_cells.push(Cell::from(Line::from(label).alignment(Alignment::Right)));
+    }
+    header_cells.extend(
+        app.columns
+            .iter()
+            .enumerate()
+            .skip(first_col)
+            .take(shown)
+            .map(|(i, c)| {
+                let cell = if app.column_right_aligned(i) {
+                    Cell::from(Line::from(c.as_str()).alignment(Alignment::Right))
+                } else {
+                    Cell::from(c.as_str())
+                };
+                // Generated columns are computed by SQLite and can't be edited
+                if app.is_generated_column(i) {
+                    cell.style(Style::default().fg(Color::DarkGray))
+                } else {
+                    cell
+                }
+            }),
     );
-    let header = Row::new(app.columns.iter().map(|c| Cell::from(c.as_str()))).style(
+    // Resolve the widths the way Table does, so cells can be clipped with an ellipsis
+    let col_rects = Layout::horizontal(widths.clone())
+        .flex(Flex::Start)
+        .spacing(1)
+        .split(Rect::new(0, 0, inner.width, 1));
+    let skip = if gutter_width > 0 { 1 } else { 0 };
+    app.col_display_widths = std::iter::repeat_n(0, first_col)
+        .chain(col_rects.iter().skip(skip).map(|r| r.width))
+        .collect();
+    let header_y = inner_chunks[1].y;
+    app.header_hits = col_rects
+        .iter()
+        .skip(skip)
+        .zip(first_col..)
+        .map(|(r, col)| (header_y, inner.x + r.x..inner.x + r.x + r.width, col))
+        .collect();
+    let app = &*app;
+    let header = Row::new(header_cells).style(
         Style::default()
             .fg(Color::Cyan)
             .add_modifier(Modifier::BOLD),
@@ -248,8 +950,21 @@ fn draw_data(f: &mut Frame, area: Rect, app: &mut App) {
 
     let mut rows = Vec::with_capacity(app.rows.len());
     for (r_idx, row) in app.rows.iter().enumerate() {
-        let mut cells = Vec::with_capacity(row.len());
-        for (c_idx, val) in row.iter().enumerate() {
+        let mut cells = Vec::with_capacity(row.len() + 1);
+        if gutter_width > 0 {
+            let number = if app.show_row_numbers {
+                (first_row_number + r_idx).to_string()
+            } else {
+                String::new()
+            };
+            let mut gutter = vec![Span::styled(number, Style::default().fg(Color::DarkGray))];
+            if marks {
+                let mark = if app.is_bookmarked(r_idx) { "●" } else { " " };
+                gutter.push(Span::styled(mark, Style::default().fg(Color::Yellow)));
+            }
+            cells.push(Cell::from(Line::from(gutter).alignment(Alignment::Right)));
+        }
+        for (c_idx, val) in row.iter().enumerate().skip(first_col).take(shown) {
             // Live editing view: render edit buffer with a visible cursor for the editing cell.
             let mut cell = if let AppMode::Editing {
                 row: erow,
@@ -257,17 +972,37 @@ fn draw_data(f: &mut Frame, area: Rect, app: &mut App) {
                 cursor,
             } = app.mode
             {
-                if r_idx == erow && c_idx == ecol {
+                if r_idx == erow && c_idx == ecol && app.edit_is_null {
+                    // A pending NULL (or a new row's DEFAULT) shows a placeholder, set
+                    // apart from typed text
+                    let placeholder = if app.inserting() && !app.insert_field_is_null() {
+                        "DEFAULT"
+                    } else {
+                        app.null_display.as_str()
+                    };
+                    Cell::from(Line::from(vec![
+                        Span::raw("▏"),
+                        Span::styled(
+                            placeholder,
+                            Style::default().add_modifier(Modifier::ITALIC | Modifier::DIM),
+                        ),
+                    ]))
+                } else if r_idx == erow && c_idx == ecol {
                     let buf = app.edit_buffer.as_str();
                     let cur = cursor.min(buf.len());
                     let (left, right) = buf.split_at(cur);
                     let line = Line::from(vec![Span::raw(left), Span::raw("▏"), Span::raw(right)]);
                     Cell::from(line)
+                } else if app.is_new_row_default(r_idx, c_idx) {
+                    Cell::from(Span::styled(
+                        "DEFAULT",
+                        Style::default().add_modifier(Modifier::ITALIC | Modifier::DIM),
+                    ))
                 } else {
-                    Cell::from(val.as_str())
+                    display_cell(app, r_idx, c_idx, val)
                 }
             } else {
-                Cell::from(val.as_str())
+                display_cell(app, r_idx, c_idx, val)
             };
 
             // Highlight selection, and use a distinct highlight for the editing cell.
@@ -288,12 +1023,112 @@ fn draw_data(f: &mut Frame, area: Rect, app: &mut App) {
 
             cells.push(cell);
         }
-        rows.push(Row::new(cells));
+        let abs = app.buffer_offset + app.view_start + r_idx;
+        let in_selection = app
+            .selected_row_range()
+            .is_some_and(|(lo, hi)| (lo..=hi).contains(&abs));
+        if in_selection {
+            rows.push(Row::new(cells).style(Style::default().bg(Color::DarkGray)));
+        } else if app.zebra && abs % 2 == 1 {
+            rows.push(Row::new(cells).style(Style::default().bg(Color::Indexed(236))));
+        } else {
+            rows.push(Row::new(cells));
+        }
     }
 
     let table = Table::new(rows, widths).header(header).column_spacing(1);
 
     f.render_widget(table, inner_chunks[1]);
+
+    // A slow load leaves the old rows up: dim them and say they are being replaced
+    if let Some(since) = app.table_loading
+        && since.elapsed() >= LOADING_OVERLAY_DELAY
+    {
+        let area = inner_chunks[1];
+        f.buffer_mut()
+            .set_style(area, Style::default().add_modifier(Modifier::DIM));
+        let frame = (since.elapsed().as_millis() / 100) as usize % SPINNER.len();
+        let text = format!(" {} Loading… ", SPINNER[frame]);
+        let width = (text.chars().count() as u16 + 2).min(area.width);
+        let height = 3.min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(text)
+                .style(Style::default().fg(Color::Yellow))
+                .block(Block::default().borders(Borders::ALL)),
+            popup,
+        );
+    }
+}
+
+// A non-editing data cell: display text clipped to the column, right-aligned for numeric columns
+fn display_cell<'a>(app: &'a App, r_idx: usize, c_idx: usize, val: &'a str) -> Cell<'a> {
+    let width = app.col_display_widths.get(c_idx).copied().unwrap_or(0) as usize;
+    let text = clip_with_ellipsis(app.display_cell(r_idx, c_idx, val), width);
+    // Text with invalid UTF-8 is flagged: its � characters aren't the real bytes
+    let kind = app.cell_kind(r_idx, c_idx);
+    let span = if kind == Some(crate::db::CellKind::InvalidText) {
+        Span::styled(text, Style::default().fg(Color::LightRed))
+    } else if kind == Some(crate::db::CellKind::Text) && val.is_empty() {
+        // An empty string, so it doesn't pass for NULL (or a blank --null placeholder)
+        Span::styled("\"\"", Style::default().fg(Color::DarkGray))
+    } else if app.is_generated_column(c_idx) {
+        Span::styled(text, Style::default().fg(Color::DarkGray))
+    } else {
+        Span::raw(text)
+    };
+    // Edited this session
+    let span = if app.is_edited_cell(r_idx, c_idx) {
+        span.patch_style(Style::default().add_modifier(Modifier::UNDERLINED))
+    } else {
+        span
+    };
+    if app.column_right_aligned(c_idx) {
+        Cell::from(Line::from(span).alignment(Alignment::Right))
+    } else {
+        Cell::from(Line::from(span))
+    }
+}
+
+// The end of `text` that fits in `width` terminal cells, with "…" marking a cut start;
+// measured per grapheme so wide and combining characters count as drawn
+fn tail_to_width(text: &str, width: usize) -> Cow<'_, str> {
+    if Span::raw(text).width() <= width {
+        return Cow::Borrowed(text);
+    }
+    let mut used = 1; // the "…"
+    let mut start = text.len();
+    for (i, g) in text.grapheme_indices(true).rev() {
+        used += Span::raw(g).width();
+        if used > width {
+            break;
+        }
+        start = i;
+    }
+    Cow::Owned(format!("…{}", &text[start..]))
+}
+
+// Cut text that is wider than `width` (or spans lines) and mark the cut with "…"
+fn clip_with_ellipsis(text: Cow<'_, str>, width: usize) -> Cow<'_, str> {
+    let first_line = text.lines().next().unwrap_or("");
+    let multiline = first_line.len() < text.len();
+    if width == 0 || (!multiline && text.chars().count() <= width) {
+        return text;
+    }
+    let keep = if multiline && first_line.chars().count() < width {
+        first_line.chars().count()
+    } else {
+        width - 1
+    };
+    let mut clipped: String = first_line.chars().take(keep).collect();
+    clipped.push('…');
+    Cow::Owned(clipped)
 }
 
 fn column_widths(total_width: u16, cols: usize, tiers: &[u8], abs: &[u16]) -> Vec<Constraint> {
@@ -353,9 +1188,9 @@ fn measure_column_width(app: &App, col: usize) -> u16 {
         return 0;
     }
     let mut max_len = app.columns.get(col).map(|s| s.chars().count()).unwrap_or(0);
-    for row in &app.rows {
+    for (r_idx, row) in app.rows.iter().enumerate() {
         if let Some(cell) = row.get(col) {
-            let l = cell.chars().count();
+            let l = app.display_cell(r_idx, col, cell).chars().count();
             if l > max_len {
                 max_len = l;
             }
@@ -366,12 +1201,152 @@ fn measure_column_width(app: &App, col: usize) -> u16 {
 }
 
 // Draw a right-side viewer pane that shows the full content of the current cell.
-fn draw_cell_viewer(f: &mut Frame, area: Rect, app: &App) {
-    let title = "Cell";
-    let content = app.current_cell_text().unwrap_or("<empty>");
-    let p = Paragraph::new(content)
-        .block(Block::default().borders(Borders::ALL).title(title))
+fn draw_cell_viewer(f: &mut Frame, area: Rect, app: &mut App) {
+    // A new cell starts at the top
+    let cell = (app.cursor_row_abs(), app.sel_col);
+    if app.viewer_cell != Some(cell) {
+        app.viewer_cell = Some(cell);
+        app.viewer_scroll = 0;
+    }
+    // BLOBs: size and a hex dump of the first bytes instead of one long hex string
+    app.request_blob_head();
+    let raw = match app.current_blob_preview() {
+        Some((len, bytes)) => blob_preview(len, &bytes),
+        None => app.current_cell_text().unwrap_or("<empty>").to_string(),
+    };
+    let pretty = if app.viewer_pretty_json {
+        pretty_json(&raw)
+    } else {
+        None
+    };
+    let json = pretty.is_some();
+    let content = pretty.unwrap_or(raw);
+    let p = Paragraph::new(content.as_str())
         .wrap(Wrap { trim: false })
         .style(Style::default());
+
+    // Clamp scrolling to the wrapped height of the content
+    let page = area.height.saturating_sub(2);
+    let lines = p.line_count(area.width.saturating_sub(2)) as u16;
+    app.viewer_page = page;
+    app.viewer_max_scroll = lines.saturating_sub(page);
+    app.viewer_scroll = app.viewer_scroll.min(app.viewer_max_scroll);
+
+    let mut title = String::from(if json { "Cell (JSON)" } else { "Cell" });
+    if app.viewer_max_scroll > 0 {
+        title.push_str(&format!(
+            " — line {}/{} ([ ] scroll)",
+            app.viewer_scroll + 1,
+            lines
+        ));
+    }
+    let block = if app.focus == Focus::Viewer {
+        title.push_str(" ◀");
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .title(title)
+    } else {
+        Block::default().borders(Borders::ALL).title(title)
+    };
+    // Text metrics of the stored value (BLOBs already show their size)
+    let block = match app.current_cell_text() {
+        Some(text) if app.current_blob_preview().is_none() => {
+            let (chars, words, lines) = crate::app::text_counts(text);
+            block.title_bottom(
+                Line::from(format!(
+                    " {} chars · {} words · {} lines ",
+                    group_thousands(chars as i64),
+                    group_thousands(words as i64),
+                    group_thousands(lines as i64)
+                ))
+                .style(Style::default().fg(Color::DarkGray)),
+            )
+        }
+        _ => block,
+    };
+    let p = p.block(block).scroll((app.viewer_scroll, 0));
     f.render_widget(p, area);
 }
+
+// "BLOB (N bytes)", then 8 bytes of hex per line (fits the 40-column viewer) and the save hint
+fn blob_preview(len: usize, bytes: &[u8]) -> String {
+    let mut out = format!("BLOB ({} bytes)\n\n", len);
+    for chunk in bytes.chunks(8) {
+        let line: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+        out.push_str(&line.join(" "));
+        out.push('\n');
+    }
+    if bytes.is_empty() && len > 0 {
+        out.push_str("loading…\n");
+    } else if len > bytes.len() {
+        out.push_str("…\n");
+    }
+    out.push_str("\nW saves it to a file");
+    out
+}
+
+// Indent a JSON object or array by 2 spaces. The text is re-indented as written (not
+// re-serialized) so key order and number formatting survive. None when it isn't JSON.
+fn pretty_json(raw: &str) -> Option<String> {
+    let trimmed = raw.trim_start();
+    if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+        return None;
+    }
+    serde_json::from_str::<serde_json::Value>(raw).ok()?;
+
+    let mut out = String::with_capacity(raw.len() * 2);
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut chars = raw.chars().peekable();
+    let newline = |out: &mut String, depth: usize| {
+        out.push('\n');
+        out.push_str(&"  ".repeat(depth));
+    };
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '{' | '[' => {
+                out.push(c);
+                // Keep empty containers on one line
+                while chars.peek().is_some_and(|n| n.is_whitespace()) {
+                    chars.next();
+                }
+                if chars.peek().is_some_and(|&n| n == '}' || n == ']') {
+                    out.push(chars.next().unwrap_or(c));
+                } else {
+                    depth += 1;
+                    newline(&mut out, depth);
+                }
+            }
+            '}' | ']' => {
+                depth = depth.saturating_sub(1);
+                newline(&mut out, depth);
+                out.push(c);
+            }
+            ',' => {
+                out.push(c);
+                newline(&mut out, depth);
+            }
+            ':' => out.push_str(": "),
+            c if c.is_whitespace() => {}
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,108 @@
+//! Saved views: a table's filter and sort under a name, kept per database in
+//! `$XDG_CONFIG_HOME/sqlite-editor/views.json` (or `~/.config/...`).
+
+use std::io;
+use std::path::PathBuf;
+
+use crate::db::SortDir;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedView {
+    pub name: String,
+    pub table: String,
+    pub filter: Option<String>,
+    pub sort_by: Option<String>,
+    pub sort_dir: Option<SortDir>,
+}
+
+fn views_path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(dir.join("sqlite-editor").join("views.json"))
+}
+
+// The whole file: { "<database>": { "<view name>": { "table": ..., ... } } }. A missing
+// file is empty; one that doesn't parse is an error, so saving never writes over it
+fn read_all() -> io::Result<serde_json::Value> {
+    let Some(path) = views_path() else {
+        return Ok(serde_json::json!({}));
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(serde_json::json!({})),
+        Err(e) => return Err(e),
+    };
+    serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .filter(|v| v.is_object())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} isn't valid; fix or remove it", path.display()),
+            )
+        })
+}
+
+fn write_all(all: &serde_json::Value) -> io::Result<()> {
+    let path = views_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(all)?)
+}
+
+/// Views saved for the database `db_key`, by name
+pub fn load(db_key: &str) -> Vec<SavedView> {
+    let all = read_all().unwrap_or_default();
+    let Some(entries) = all.get(db_key).and_then(|v| v.as_object()) else {
+        return Vec::new();
+    };
+    let text = |v: &serde_json::Value, key: &str| v.get(key)?.as_str().map(String::from);
+    let mut views: Vec<SavedView> = entries
+        .iter()
+        .filter_map(|(name, v)| {
+            Some(SavedView {
+                name: name.clone(),
+                table: text(v, "table")?,
+                filter: text(v, "filter"),
+                sort_by: text(v, "sort_by"),
+                sort_dir: match v.get("sort_dir").and_then(|d| d.as_str()) {
+                    Some("asc") => Some(SortDir::Asc),
+                    Some("desc") => Some(SortDir::Desc),
+                    _ => None,
+                },
+            })
+        })
+        .collect();
+    views.sort_by(|a, b| a.name.cmp(&b.name));
+    views
+}
+
+/// Add the view, replacing one with the same name
+pub fn save(db_key: &str, view: &SavedView) -> io::Result<()> {
+    let mut all = read_all()?;
+    if !all.get(db_key).is_some_and(|v| v.is_object()) {
+        all[db_key] = serde_json::json!({});
+    }
+    all[db_key][view.name.as_str()] = serde_json::json!({
+        "table": view.table,
+        "filter": view.filter,
+        "sort_by": view.sort_by,
+        "sort_dir": view.sort_dir.map(|d| match d {
+            SortDir::Asc => "asc",
+            SortDir::Desc => "desc",
+        }),
+    });
+    write_all(&all)
+}
+
+pub fn delete(db_key: &str, name: &str) -> io::Result<()> {
+    let mut all = read_all()?;
+    if let Some(entries) = all.get_mut(db_key).and_then(|v| v.as_object_mut()) {
+        entries.remove(name);
+    }
+    write_all(&all)
+}